@@ -0,0 +1,133 @@
+use std::sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::{broadcast, watch};
+
+use crate::domain::events::TodoEvent;
+
+const EVENT_BUFFER: usize = 256;
+
+/// Something that reacts to todo changes, e.g. a webhook sender or history writer.
+#[async_trait]
+pub trait EventSubscriber: Send + Sync + 'static {
+    async fn handle(&self, event: &TodoEvent);
+}
+
+/// Outcome of [`EventBus::shutdown`], summed over all subscribers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DrainReport {
+    /// Events still queued at shutdown that were handled before the deadline.
+    pub flushed: u64,
+    /// Events still queued when the deadline passed.
+    pub dropped: u64,
+}
+
+struct Subscription {
+    task: tokio::task::JoinHandle<()>,
+    /// Events published before this subscriber existed.
+    start: u64,
+    /// Events handled (or skipped after lagging) so far.
+    processed: Arc<AtomicU64>,
+}
+
+/// Fan-out of [`TodoEvent`]s from the service to any number of subscribers.
+///
+/// Publishing never blocks; a subscriber that falls more than `EVENT_BUFFER` events
+/// behind skips the ones it missed rather than slowing writes down.
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<TodoEvent>,
+    /// Events published so far. Held while sending, so a subscriber's `start` taken under it
+    /// counts exactly the events its receiver will never see.
+    published: Arc<Mutex<u64>>,
+    closing: Arc<watch::Sender<bool>>,
+    subscriptions: Arc<Mutex<Vec<Subscription>>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self {
+            tx: broadcast::channel(EVENT_BUFFER).0,
+            published: Arc::default(),
+            closing: Arc::new(watch::channel(false).0),
+            subscriptions: Arc::default(),
+        }
+    }
+}
+
+impl EventBus {
+    pub fn publish(&self, event: TodoEvent) {
+        let mut published = self.published.lock().unwrap();
+        *published += 1;
+        // Err only means nobody is listening right now
+        let _ = self.tx.send(event);
+    }
+
+    /// Raw receiver for consumers that drive their own loop (e.g. an SSE stream).
+    pub fn subscribe(&self) -> broadcast::Receiver<TodoEvent> { self.tx.subscribe() }
+
+    /// Runs `subscriber` on a background task for every event published from now on.
+    /// [`shutdown`](Self::shutdown) lets it finish what is already queued.
+    pub fn spawn_subscriber<S: EventSubscriber>(&self, subscriber: S) {
+        let (mut rx, start) = {
+            let published = self.published.lock().unwrap();
+            (self.subscribe(), *published)
+        };
+        let mut closing = self.closing.subscribe();
+        let processed = Arc::new(AtomicU64::new(0));
+        let counter = processed.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = closing.changed() => break,
+                    received = rx.recv() => match received {
+                        Ok(event) => subscriber.handle(&event).await,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            tracing::warn!(skipped, "event subscriber lagged");
+                            counter.fetch_add(skipped, Ordering::SeqCst);
+                            continue;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return,
+                    },
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+            // Shutting down: handle whatever was published before, then stop
+            loop {
+                match rx.try_recv() {
+                    Ok(event) => subscriber.handle(&event).await,
+                    Err(broadcast::error::TryRecvError::Lagged(skipped)) => { counter.fetch_add(skipped, Ordering::SeqCst); continue; }
+                    Err(_) => break,
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        self.subscriptions.lock().unwrap().push(Subscription { task, start, processed });
+    }
+
+    /// Stops every spawned subscriber after it has handled the events already queued,
+    /// waiting at most `timeout`; subscribers still busy after that are aborted.
+    pub async fn shutdown(&self, timeout: Duration) -> DrainReport {
+        let mut subscriptions = std::mem::take(&mut *self.subscriptions.lock().unwrap());
+        // Every event counted after `start` reached the receiver and is handled or skipped at most
+        // once, so this can't go negative as long as `published` is read after `processed`
+        let pending = |s: &Subscription| {
+            let processed = s.processed.load(Ordering::SeqCst);
+            *self.published.lock().unwrap() - s.start - processed
+        };
+        let queued: u64 = subscriptions.iter().map(pending).sum();
+
+        self.closing.send_replace(true);
+        let deadline = tokio::time::Instant::now() + timeout;
+        for subscription in &mut subscriptions {
+            if tokio::time::timeout_at(deadline, &mut subscription.task).await.is_err() {
+                subscription.task.abort();
+            }
+        }
+
+        let dropped: u64 = subscriptions.iter().map(pending).sum();
+        DrainReport { flushed: queued.saturating_sub(dropped), dropped }
+    }
+}
@@ -0,0 +1,106 @@
+//! Operator-only maintenance, driven by the `admin` binary rather than HTTP.
+
+use anyhow::{anyhow, bail, Result};
+use futures_util::TryStreamExt;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde_json::json;
+
+use crate::domain::{repository::TodoRepository, todo::{ConflictPolicy, ListOptions, Todo, TodoFilter, TodoStatus}};
+
+#[derive(Debug)]
+pub enum AdminCommand {
+    Vacuum,
+    Reindex,
+    PurgeTrash,
+    /// Todos that aren't deleted and match the filter, as `GET /todos` would list them,
+    /// except that without a status every status is exported, archived included.
+    Export(TodoFilter),
+    Import(Vec<Todo>, ConflictPolicy),
+    Stats,
+}
+
+impl AdminCommand {
+    /// Parses everything but `import`, whose todos come from a file the caller reads.
+    pub fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "vacuum" => Self::Vacuum,
+            "reindex" => Self::Reindex,
+            "purge-trash" => Self::PurgeTrash,
+            "export" => Self::Export(TodoFilter::default()),
+            "stats" => Self::Stats,
+            "import" => bail!("import needs todos; use AdminCommand::Import"),
+            other => bail!("unknown command {:?}", other),
+        })
+    }
+
+    /// `export` with the list filters as flags; see [`parse_export_filter`].
+    pub fn parse_export(args: &[&str]) -> Result<Self> {
+        Ok(Self::Export(parse_export_filter(args)?))
+    }
+}
+
+/// The list filters as `export` and `export-ndjson` flags: `--status <status>`, `--tag <tag>`
+/// and `--completed-after <RFC3339 timestamp | YYYY-MM-DD>`.
+pub fn parse_export_filter(args: &[&str]) -> Result<TodoFilter> {
+    let mut filter = TodoFilter::default();
+    let mut args = args.iter();
+    while let Some(flag) = args.next() {
+        let Some(value) = args.next() else { bail!("{} needs a value", flag) };
+        match *flag {
+            "--status" => filter.status = Some(TodoStatus::ALL.into_iter().find(|s| s.as_str() == *value).ok_or_else(|| anyhow!("unknown status {:?}", value))?),
+            "--tag" => filter.tag = Some(value.to_string()),
+            "--completed-after" => filter.completed_after = Some(parse_date(value)?),
+            other => bail!("unknown export flag {:?}", other),
+        }
+    }
+    Ok(filter)
+}
+
+/// Same formats as the HTTP filters: an RFC3339 timestamp or a `YYYY-MM-DD` date (midnight UTC).
+fn parse_date(s: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|d| d.with_timezone(&Utc))
+        .or_else(|_| NaiveDate::parse_from_str(s, "%Y-%m-%d").map(|d| d.and_time(NaiveTime::MIN).and_utc()))
+        .map_err(|_| anyhow!("invalid date {:?}", s))
+}
+
+/// Writes every todo that isn't deleted and matches `filter` as one JSON object per line,
+/// reading them one at a time from [`TodoRepository::stream`]; returns how many were written.
+pub async fn export_ndjson<R: TodoRepository>(repo: &R, filter: TodoFilter, out: &mut impl std::io::Write) -> Result<u64> {
+    let mut todos = repo.stream(filter);
+    let mut count = 0;
+    while let Some(todo) = todos.try_next().await? {
+        serde_json::to_writer(&mut *out, &todo)?;
+        out.write_all(b"\n")?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Runs `command` and describes the outcome as JSON for the caller to print.
+pub async fn run<R: TodoRepository>(repo: &R, command: AdminCommand) -> Result<serde_json::Value> {
+    Ok(match command {
+        AdminCommand::Vacuum => { repo.vacuum().await?; json!({ "command": "vacuum", "status": "ok" }) }
+        AdminCommand::Reindex => { repo.reindex().await?; json!({ "command": "reindex", "status": "ok" }) }
+        AdminCommand::PurgeTrash => json!({ "command": "purge-trash", "purged": repo.purge_deleted().await? }),
+        AdminCommand::Export(filter) => {
+            let mut todos = repo.list(filter.clone(), ListOptions::default()).await?;
+            if filter.status.is_none() {
+                todos.extend(repo.list(TodoFilter { status: Some(TodoStatus::Archived), ..filter }, ListOptions::default()).await?);
+            }
+            json!({ "command": "export", "count": todos.len(), "todos": todos })
+        }
+        AdminCommand::Import(todos, policy) => json!({ "command": "import", "result": repo.import_todos(todos, policy).await? }),
+        AdminCommand::Stats => {
+            let mut by_status = serde_json::Map::new();
+            let mut total = 0;
+            for status in TodoStatus::ALL {
+                let name = status.as_str();
+                let workload = repo.workload(Some(status), None).await?;
+                total += workload.count;
+                by_status.insert(name.into(), json!({ "count": workload.count, "estimate_minutes": workload.total_minutes }));
+            }
+            json!({ "command": "stats", "total": total, "by_status": by_status })
+        }
+    })
+}
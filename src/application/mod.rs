@@ -1 +1,5 @@
+pub mod events;
+pub mod maintenance;
 pub mod todo_service;
+#[cfg(test)]
+mod todo_service_tests;
@@ -1,31 +1,387 @@
+use std::{collections::HashMap, hash::{DefaultHasher, Hash, Hasher}, sync::Arc, time::Instant};
+
+use super::events::EventBus;
+use crate::domain::events::TodoEvent;
 use crate::domain::repository::TodoRepository;
-use crate::domain::todo::{CreateTodo, Todo, TodoId, UpdateTodo};
-use anyhow::Result;
+use crate::domain::todo::{ConflictPolicy, ImportReport, normalize_tags, CreateTodo, ListOptions, Page, SortField, SortOrder, StatusCounts, Todo, TodoChange, TodoFilter, TodoId, TodoStatus, Trend, UndoWindowExpired, UpdateTodo, UpdatedTodo, ValidationError, Velocity, WeekCompletions, Workload};
+use anyhow::{Context, Result};
 use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
 
 #[async_trait]
 pub trait TodoService: Send + Sync + 'static {
     async fn create(&self, input: CreateTodo) -> Result<Todo>;
-    async fn get(&self, id: TodoId) -> Result<Option<Todo>>;
-    async fn list(&self) -> Result<Vec<Todo>>;
+    /// Creates all of `inputs` or none of them; duplicate detection doesn't apply.
+    async fn create_many(&self, inputs: Vec<CreateTodo>) -> Result<Vec<Todo>>;
+    /// Like every `owner` argument below, `Some` only considers todos created by that subject.
+    async fn get(&self, id: TodoId, owner: Option<&str>) -> Result<Option<Todo>>;
+    /// Like `get`, but a deleted todo comes back as its tombstone.
+    async fn get_including_deleted(&self, id: TodoId, owner: Option<&str>) -> Result<Option<Todo>>;
+    async fn get_by_code(&self, code: &str, owner: Option<&str>) -> Result<Option<Todo>>;
+    async fn get_many(&self, ids: Vec<TodoId>, owner: Option<&str>) -> Result<Vec<Todo>>;
+    async fn latest_updated(&self, owner: Option<&str>) -> Result<Option<Todo>>;
+    async fn list(&self, filter: TodoFilter, options: ListOptions) -> Result<Vec<Todo>>;
+    async fn list_paged(&self, filter: TodoFilter, options: ListOptions, limit: u32, offset: u32) -> Result<Page<Todo>>;
+    async fn list_archived_paged(&self, sort: SortField, order: SortOrder, limit: u32, offset: u32, owner: Option<&str>) -> Result<Vec<Todo>>;
+    /// Todos not updated since `before`, least recently updated first.
+    async fn stale(&self, before: DateTime<Utc>, status: Option<TodoStatus>, owner: Option<&str>) -> Result<Vec<Todo>>;
+    /// One chunk of an export; see [`TodoRepository::export_page`].
+    async fn export_page(&self, filter: TodoFilter, after: Option<TodoId>, limit: u32) -> Result<Vec<Todo>>;
+    /// Writes `todos` as they are, ids and timestamps included, in one transaction; see
+    /// [`TodoRepository::import_todos`].
+    async fn import(&self, todos: Vec<Todo>, policy: ConflictPolicy) -> Result<ImportReport>;
     async fn update(&self, id: TodoId, input: UpdateTodo) -> Result<Option<Todo>>;
+    /// Records that the todo was deferred; returns the new `defer_count`.
+    async fn defer(&self, id: TodoId) -> Result<Option<i64>>;
+    async fn clear_due(&self, id: TodoId) -> Result<Option<Todo>>;
+    /// Field-level diffs of every update, oldest first; `None` if the todo doesn't exist.
+    async fn changes(&self, id: TodoId) -> Result<Option<Vec<TodoChange>>>;
     async fn delete(&self, id: TodoId) -> Result<bool>;
+    async fn restore(&self, id: TodoId) -> Result<Option<Todo>>;
+    /// `restore` limited to the undo window: fails with [`UndoWindowExpired`] once a deleted
+    /// todo is older than it. Without a window this is plain `restore`.
+    async fn undo_delete(&self, id: TodoId) -> Result<Option<Todo>>;
+    /// Purges todos deleted longer ago than the undo window; 0 without a window.
+    async fn purge_expired_deletes(&self) -> Result<u64>;
+    /// Hard delete; deleted todos can be purged too.
+    async fn purge(&self, id: TodoId) -> Result<bool>;
+    async fn workload(&self, status: Option<TodoStatus>, owner: Option<&str>) -> Result<Workload>;
+    async fn count_by_status(&self, owner: Option<&str>) -> Result<StatusCounts>;
+    async fn velocity(&self, weeks: u32, owner: Option<&str>) -> Result<Velocity>;
+    /// Most items a single batch request may carry.
+    fn max_batch_size(&self) -> usize;
+    /// Most todos a list request without paging parameters gets back.
+    fn list_soft_limit(&self) -> u32;
+    /// Whether single-todo reads report deleted todos (as tombstones) unless asked not to.
+    fn include_deleted_by_default(&self) -> bool;
+}
+
+pub const DEFAULT_MAX_BATCH_SIZE: usize = 1000;
+pub const DEFAULT_LIST_SOFT_LIMIT: u32 = 50;
+/// Longest title, in characters, the service stores.
+pub const MAX_TITLE_CHARS: usize = 256;
+/// Longest description, in characters, the service stores.
+pub const MAX_DESCRIPTION_CHARS: usize = 4096;
+
+/// What happens to control characters in titles and descriptions
+/// (newlines and tabs are allowed in descriptions).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ControlCharPolicy {
+    /// Remove them and store the rest.
+    #[default]
+    Strip,
+    /// Refuse the write with a [`ValidationError`].
+    Reject,
+}
+
+/// Optional service behaviour; everything except the batch limit is off by default.
+#[derive(Debug, Clone, Default)]
+pub struct TodoServiceConfig {
+    /// When set, a create with the same normalized title and description as one made
+    /// within this window returns the earlier todo instead of inserting a duplicate.
+    pub dedup_window: Option<std::time::Duration>,
+    /// Description used when a create omits the field entirely (an explicit `null` or `""` is kept).
+    pub default_description: Option<String>,
+    /// Cap on items per batch request; `None` means [`DEFAULT_MAX_BATCH_SIZE`].
+    pub max_batch_size: Option<usize>,
+    /// Most todos `GET /todos` returns without `limit`/`offset`; `None` means [`DEFAULT_LIST_SOFT_LIMIT`].
+    pub list_soft_limit: Option<u32>,
+    /// Default for `GET /todos/:id?include_deleted`.
+    pub include_deleted_by_default: bool,
+    pub control_chars: ControlCharPolicy,
+    /// Upper-cases the first letter of titles on create and update; the rest is left alone.
+    pub capitalize_titles: bool,
+    /// How long a deleted todo can be brought back with `undo_delete`; after that it is purged.
+    pub undo_delete_window: Option<std::time::Duration>,
+    /// Statuses a todo may only move into with a non-blank description (e.g. `Done`).
+    pub description_required_for: Vec<TodoStatus>,
+}
+
+impl TodoServiceConfig {
+    /// Reads `CREATE_DEDUP_WINDOW_SECS` (unset or `0` disables dedup), `DEFAULT_DESCRIPTION`
+    /// `MAX_BATCH_SIZE`, `LIST_SOFT_LIMIT`, `GET_INCLUDE_DELETED`, `CONTROL_CHARS` (`strip` or `reject`), `CAPITALIZE_TITLES`
+    /// `UNDO_DELETE_WINDOW_SECS` (unset means deletes can always be undone and are never purged)
+    /// and `REQUIRE_DESCRIPTION_FOR` (comma-separated statuses such as `done`).
+    pub fn from_env() -> Result<Self> {
+        let dedup_window = match std::env::var("CREATE_DEDUP_WINDOW_SECS") {
+            Ok(v) => {
+                let secs: u64 = v.trim().parse().with_context(|| format!("invalid CREATE_DEDUP_WINDOW_SECS {:?}", v))?;
+                (secs > 0).then(|| std::time::Duration::from_secs(secs))
+            }
+            Err(_) => None,
+        };
+        let default_description = std::env::var("DEFAULT_DESCRIPTION").ok();
+        let max_batch_size = match std::env::var("MAX_BATCH_SIZE") {
+            Ok(v) => match v.trim().parse::<usize>() {
+                Ok(n) if n > 0 => Some(n),
+                _ => anyhow::bail!("invalid MAX_BATCH_SIZE {:?}", v),
+            },
+            Err(_) => None,
+        };
+        let list_soft_limit = match std::env::var("LIST_SOFT_LIMIT") {
+            Ok(v) => match v.trim().parse::<u32>() {
+                Ok(n) if n > 0 => Some(n),
+                _ => anyhow::bail!("invalid LIST_SOFT_LIMIT {:?}", v),
+            },
+            Err(_) => None,
+        };
+        let include_deleted_by_default = match std::env::var("GET_INCLUDE_DELETED") {
+            Ok(v) => v.trim().parse().with_context(|| format!("invalid GET_INCLUDE_DELETED {:?}", v))?,
+            Err(_) => false,
+        };
+        let control_chars = match std::env::var("CONTROL_CHARS").as_deref().map(str::trim) {
+            Err(_) | Ok("strip") => ControlCharPolicy::Strip,
+            Ok("reject") => ControlCharPolicy::Reject,
+            Ok(other) => anyhow::bail!("invalid CONTROL_CHARS {:?} (expected strip or reject)", other),
+        };
+        let capitalize_titles = match std::env::var("CAPITALIZE_TITLES") {
+            Ok(v) => v.trim().parse().with_context(|| format!("invalid CAPITALIZE_TITLES {:?}", v))?,
+            Err(_) => false,
+        };
+        let undo_delete_window = match std::env::var("UNDO_DELETE_WINDOW_SECS") {
+            Ok(v) => Some(std::time::Duration::from_secs(v.trim().parse().with_context(|| format!("invalid UNDO_DELETE_WINDOW_SECS {:?}", v))?)),
+            Err(_) => None,
+        };
+        let description_required_for = match std::env::var("REQUIRE_DESCRIPTION_FOR") {
+            Ok(v) => v.split(',').map(str::trim).filter(|s| !s.is_empty())
+                .map(|s| TodoStatus::ALL.into_iter().find(|status| status.as_str() == s).with_context(|| format!("invalid status {:?} in REQUIRE_DESCRIPTION_FOR", s)))
+                .collect::<Result<_>>()?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Self { dedup_window, default_description, max_batch_size, list_soft_limit, include_deleted_by_default, control_chars, capitalize_titles, undo_delete_window, description_required_for })
+    }
 }
 
 #[derive(Clone)]
 pub struct TodoServiceImpl<R: TodoRepository> {
     repo: R,
+    config: TodoServiceConfig,
+    recent_creates: Arc<tokio::sync::Mutex<HashMap<u64, (TodoId, Instant)>>>,
+    events: EventBus,
 }
 
 impl<R: TodoRepository> TodoServiceImpl<R> {
-    pub fn new(repo: R) -> Self { Self { repo } }
+    pub fn new(repo: R) -> Self { Self::with_config(repo, TodoServiceConfig::default()) }
+
+    pub fn with_config(repo: R, config: TodoServiceConfig) -> Self {
+        Self { repo, config, recent_creates: Arc::default(), events: EventBus::default() }
+    }
+
+    /// Bus every successful create, update and delete is published on.
+    pub fn events(&self) -> &EventBus { &self.events }
+
+    async fn insert(&self, input: CreateTodo) -> Result<Todo> {
+        let todo = self.repo.create(input).await?;
+        self.events.publish(TodoEvent::Created(todo.clone()));
+        Ok(todo)
+    }
+
+    /// Cleans the text fields and fills in the default description.
+    fn prepare_create(&self, mut input: CreateTodo) -> Result<CreateTodo> {
+        input.title = self.clean_title(input.title)?;
+        if let Some(Some(description)) = input.description { input.description = Some(Some(self.clean_description(description)?)); }
+        if input.description.is_none() { input.description = Some(self.config.default_description.clone()); }
+        input.tags = self.clean_tags(input.tags)?;
+        input.waiting_on = input.waiting_on.map(|w| self.clean_waiting_on(w)).transpose()?;
+        Ok(input)
+    }
+
+    /// Imports skip `create`, so they get the same checks here; ids, timestamps and status are
+    /// kept as exported.
+    fn prepare_import(&self, mut todo: Todo) -> Result<Todo> {
+        todo.title = self.clean_title(todo.title)?;
+        todo.description = todo.description.map(|d| self.clean_description(d)).transpose()?;
+        todo.tags = self.clean_tags(todo.tags)?;
+        todo.waiting_on = todo.waiting_on.map(|w| self.clean_waiting_on(w)).transpose()?;
+        Ok(todo)
+    }
+
+    /// Refuses titles that are blank (after control characters are dealt with) or longer than
+    /// [`MAX_TITLE_CHARS`].
+    fn clean_title(&self, title: String) -> Result<String> {
+        let title = check_length("title", clean_text("title", title, &[], self.config.control_chars)?, MAX_TITLE_CHARS)?;
+        if title.trim().is_empty() { return Err(ValidationError("title must not be empty".into()).into()); }
+        Ok(if self.config.capitalize_titles { capitalize_first(&title) } else { title })
+    }
+
+    fn clean_description(&self, description: String) -> Result<String> {
+        check_length("description", clean_text("description", description, &['\n', '\t'], self.config.control_chars)?, MAX_DESCRIPTION_CHARS)
+    }
+
+    fn clean_waiting_on(&self, waiting_on: String) -> Result<String> {
+        clean_text("waiting_on", waiting_on, &[], self.config.control_chars)
+    }
+
+    /// Refuses a move into a status listed in `description_required_for` while the description
+    /// the todo would end up with is blank; staying in that status is not checked.
+    fn check_description_required(&self, before: &Todo, status: &TodoStatus, description: &Option<String>) -> Result<()> {
+        if before.status == *status || !self.config.description_required_for.contains(status) { return Ok(()); }
+        if description.as_deref().is_none_or(|d| d.trim().is_empty()) {
+            return Err(ValidationError(format!("a description is required before a todo can be {}", status.as_str())).into());
+        }
+        Ok(())
+    }
+
+    fn clean_tags(&self, tags: Vec<String>) -> Result<Vec<String>> {
+        let tags = tags.into_iter().map(|t| clean_text("tag", t, &[], self.config.control_chars)).collect::<Result<_>>()?;
+        Ok(normalize_tags(tags))
+    }
+
+}
+
+/// Applies `policy` to the control characters in `value` other than `allowed`.
+fn clean_text(field: &str, value: String, allowed: &[char], policy: ControlCharPolicy) -> Result<String> {
+    let unwanted = |c: char| c.is_control() && !allowed.contains(&c);
+    if !value.contains(unwanted) { return Ok(value); }
+    match policy {
+        ControlCharPolicy::Strip => Ok(value.replace(unwanted, "")),
+        ControlCharPolicy::Reject => Err(ValidationError(format!("{} contains control characters", field)).into()),
+    }
+}
+
+fn check_length(field: &str, value: String, max: usize) -> Result<String> {
+    if value.chars().count() > max { return Err(ValidationError(format!("{} must be at most {} characters", field, max)).into()); }
+    Ok(value)
+}
+
+/// Upper-cases the first non-whitespace character, which may expand (e.g. `ß` to `SS`).
+fn capitalize_first(value: &str) -> String {
+    let Some((at, first)) = value.char_indices().find(|(_, c)| !c.is_whitespace()) else { return value.to_string() };
+    let rest = &value[at + first.len_utf8()..];
+    format!("{}{}{}", &value[..at], first.to_uppercase(), rest)
+}
+
+/// Hash of the create payload with case and whitespace differences removed.
+fn content_hash(input: &CreateTodo) -> u64 {
+    let normalize = |s: &str| s.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    let mut hasher = DefaultHasher::new();
+    normalize(&input.title).hash(&mut hasher);
+    input.description.clone().flatten().as_deref().map(normalize).hash(&mut hasher);
+    // Two subjects making the same todo get one each
+    input.owner.hash(&mut hasher);
+    hasher.finish()
 }
 
 #[async_trait]
 impl<R: TodoRepository> TodoService for TodoServiceImpl<R> {
-    async fn create(&self, input: CreateTodo) -> Result<Todo> { self.repo.create(input).await }
-    async fn get(&self, id: TodoId) -> Result<Option<Todo>> { self.repo.get(id).await }
-    async fn list(&self) -> Result<Vec<Todo>> { self.repo.list().await }
-    async fn update(&self, id: TodoId, input: UpdateTodo) -> Result<Option<Todo>> { self.repo.update(id, input).await }
-    async fn delete(&self, id: TodoId) -> Result<bool> { self.repo.delete(id).await }
+    async fn create(&self, input: CreateTodo) -> Result<Todo> {
+        let input = self.prepare_create(input)?;
+        let Some(window) = self.config.dedup_window else { return self.insert(input).await };
+        let key = content_hash(&input);
+        // Held across the insert so concurrent double-submits can't both miss
+        let mut recent = self.recent_creates.lock().await;
+        recent.retain(|_, (_, at)| at.elapsed() < window);
+        if let Some((id, _)) = recent.get(&key) && let Some(existing) = self.repo.get(id.clone(), None).await? {
+            return Ok(existing);
+        }
+        let todo = self.insert(input).await?;
+        recent.insert(key, (todo.id.clone(), Instant::now()));
+        Ok(todo)
+    }
+    async fn create_many(&self, inputs: Vec<CreateTodo>) -> Result<Vec<Todo>> {
+        let inputs = inputs.into_iter().map(|input| self.prepare_create(input)).collect::<Result<Vec<_>>>()?;
+        let todos = self.repo.create_many(inputs).await?;
+        for todo in &todos { self.events.publish(TodoEvent::Created(todo.clone())); }
+        Ok(todos)
+    }
+    async fn get(&self, id: TodoId, owner: Option<&str>) -> Result<Option<Todo>> { self.repo.get(id, owner).await }
+    async fn get_including_deleted(&self, id: TodoId, owner: Option<&str>) -> Result<Option<Todo>> { self.repo.get_including_deleted(id, owner).await }
+    async fn get_by_code(&self, code: &str, owner: Option<&str>) -> Result<Option<Todo>> { self.repo.get_by_code(code, owner).await }
+    async fn get_many(&self, ids: Vec<TodoId>, owner: Option<&str>) -> Result<Vec<Todo>> { self.repo.get_many(ids, owner).await }
+    async fn latest_updated(&self, owner: Option<&str>) -> Result<Option<Todo>> { self.repo.latest_updated(owner).await }
+    async fn list(&self, filter: TodoFilter, options: ListOptions) -> Result<Vec<Todo>> { self.repo.list(filter, options).await }
+    async fn list_paged(&self, filter: TodoFilter, options: ListOptions, limit: u32, offset: u32) -> Result<Page<Todo>> {
+        self.repo.list_paged(filter, options, limit, offset).await
+    }
+    async fn list_archived_paged(&self, sort: SortField, order: SortOrder, limit: u32, offset: u32, owner: Option<&str>) -> Result<Vec<Todo>> {
+        self.repo.list_archived_paged(sort, order, limit, offset, owner).await
+    }
+    async fn stale(&self, before: DateTime<Utc>, status: Option<TodoStatus>, owner: Option<&str>) -> Result<Vec<Todo>> { self.repo.list_stale(before, status, owner).await }
+    async fn export_page(&self, filter: TodoFilter, after: Option<TodoId>, limit: u32) -> Result<Vec<Todo>> { self.repo.export_page(filter, after, limit).await }
+    async fn import(&self, todos: Vec<Todo>, policy: ConflictPolicy) -> Result<ImportReport> {
+        let todos = todos.into_iter().map(|todo| self.prepare_import(todo)).collect::<Result<Vec<_>>>()?;
+        self.repo.import_todos(todos, policy).await
+    }
+    async fn update(&self, id: TodoId, mut input: UpdateTodo) -> Result<Option<Todo>> {
+        input.title = input.title.map(|t| self.clean_title(t)).transpose()?;
+        input.description = input.description.map(|d| d.map(|d| self.clean_description(d)).transpose()).transpose()?;
+        input.tags = input.tags.map(|t| self.clean_tags(t)).transpose()?;
+        input.waiting_on = input.waiting_on.map(|w| w.map(|w| self.clean_waiting_on(w)).transpose()).transpose()?;
+        if let Some(status) = &input.status && let Some(before) = self.repo.get(id.clone(), None).await? {
+            self.check_description_required(&before, status, input.description.as_ref().unwrap_or(&before.description))?;
+        }
+        let Some(UpdatedTodo { todo, next }) = self.repo.update(id, input).await? else { return Ok(None) };
+        self.events.publish(TodoEvent::Updated(todo.clone()));
+        if let Some(next) = next { self.events.publish(TodoEvent::Created(next)); }
+        Ok(Some(todo))
+    }
+    async fn defer(&self, id: TodoId) -> Result<Option<i64>> { self.repo.increment_defer_count(id).await }
+    async fn clear_due(&self, id: TodoId) -> Result<Option<Todo>> {
+        let updated = self.repo.clear_due(id).await?;
+        if let Some(todo) = &updated { self.events.publish(TodoEvent::Updated(todo.clone())); }
+        Ok(updated)
+    }
+    async fn changes(&self, id: TodoId) -> Result<Option<Vec<TodoChange>>> {
+        if self.repo.get(id.clone(), None).await?.is_none() { return Ok(None); }
+        Ok(Some(self.repo.list_changes(id).await?))
+    }
+    async fn delete(&self, id: TodoId) -> Result<bool> {
+        let deleted = self.repo.delete(id.clone()).await?;
+        if deleted { self.events.publish(TodoEvent::Deleted(id)); }
+        Ok(deleted)
+    }
+    async fn restore(&self, id: TodoId) -> Result<Option<Todo>> {
+        let was_deleted = self.repo.get(id.clone(), None).await?.is_none();
+        let restored = self.repo.restore(id).await?;
+        if let Some(todo) = &restored && was_deleted { self.events.publish(TodoEvent::Updated(todo.clone())); }
+        Ok(restored)
+    }
+    async fn undo_delete(&self, id: TodoId) -> Result<Option<Todo>> {
+        let Some(todo) = self.repo.get_including_deleted(id.clone(), None).await? else { return Ok(None) };
+        if let (Some(deleted_at), Some(window)) = (todo.deleted_at, self.config.undo_delete_window)
+            && (Utc::now() - deleted_at).to_std().is_ok_and(|age| age > window)
+        {
+            return Err(UndoWindowExpired.into());
+        }
+        self.restore(id).await
+    }
+    async fn purge_expired_deletes(&self) -> Result<u64> {
+        let Some(window) = self.config.undo_delete_window else { return Ok(0) };
+        self.repo.purge_deleted_before(Utc::now() - Duration::from_std(window)?).await
+    }
+    async fn purge(&self, id: TodoId) -> Result<bool> {
+        // Subscribers already heard about todos that were deleted before
+        let was_live = self.repo.get(id.clone(), None).await?.is_some();
+        let purged = self.repo.purge(id.clone()).await?;
+        if purged && was_live { self.events.publish(TodoEvent::Deleted(id)); }
+        Ok(purged)
+    }
+    async fn workload(&self, status: Option<TodoStatus>, owner: Option<&str>) -> Result<Workload> { self.repo.workload(status, owner).await }
+    async fn count_by_status(&self, owner: Option<&str>) -> Result<StatusCounts> { self.repo.count_by_status(owner).await }
+
+    async fn velocity(&self, weeks: u32, owner: Option<&str>) -> Result<Velocity> {
+        let now = Utc::now();
+        let grouped = self.repo.completions_by_week(now, weeks, owner).await?;
+        let weeks: Vec<WeekCompletions> = (0..weeks).rev().map(|ago| WeekCompletions {
+            week_start: now - Duration::weeks(i64::from(ago) + 1),
+            completed: grouped.iter().filter(|(w, _)| *w == ago).map(|(_, n)| n).sum(),
+        }).collect();
+        let trend = match weeks.split_last() {
+            Some((latest, earlier)) if !earlier.is_empty() => {
+                let earlier_total: i64 = earlier.iter().map(|w| w.completed).sum();
+                // Compare latest * n against the earlier sum to avoid fractional averages
+                match (latest.completed * earlier.len() as i64).cmp(&earlier_total) {
+                    std::cmp::Ordering::Greater => Trend::Up,
+                    std::cmp::Ordering::Less => Trend::Down,
+                    std::cmp::Ordering::Equal => Trend::Flat,
+                }
+            }
+            _ => Trend::Flat,
+        };
+        Ok(Velocity { weeks, trend })
+    }
+
+    fn max_batch_size(&self) -> usize { self.config.max_batch_size.unwrap_or(DEFAULT_MAX_BATCH_SIZE) }
+    fn list_soft_limit(&self) -> u32 { self.config.list_soft_limit.unwrap_or(DEFAULT_LIST_SOFT_LIMIT) }
+    fn include_deleted_by_default(&self) -> bool { self.config.include_deleted_by_default }
 }
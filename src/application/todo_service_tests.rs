@@ -1,48 +1,378 @@
 #[cfg(test)]
 mod tests {
-    use super::super::todo_service::{TodoService, TodoServiceImpl};
-    use crate::domain::{repository::TodoRepository, todo::{CreateTodo, Todo, TodoId, TodoStatus, UpdateTodo}};
-    use anyhow::Result;
+    use super::super::events::{DrainReport, EventSubscriber};
+    use super::super::todo_service::{ControlCharPolicy, TodoService, TodoServiceConfig, TodoServiceImpl, MAX_DESCRIPTION_CHARS, MAX_TITLE_CHARS};
+    use crate::domain::{events::TodoEvent, repository::TodoRepository, todo::{ConflictPolicy, CreateTodo, Inconsistency, InconsistencyKind, Recurrence, TodoFilter, TodoStatus, UndoWindowExpired, UpdateTodo, ValidationError}};
+    use crate::infrastructure::memory_repo::InMemoryTodoRepository as InMemoryRepo;
     use async_trait::async_trait;
-    use chrono::Utc;
+
+    #[tokio::test]
+    async fn unit_create_and_get() {
+        let repo = InMemoryRepo::default();
+        let service = TodoServiceImpl::new(repo);
+        let created = service.create(CreateTodo { title: "X".into(), ..Default::default() }).await.unwrap();
+        assert_eq!(created.title, "X");
+        let got = service.get(created.id.clone(), None).await.unwrap().unwrap();
+        assert_eq!(got.id, created.id);
+    }
+
+    #[tokio::test]
+    async fn unit_reads_only_return_the_owners_todos() {
+        let service = TodoServiceImpl::new(InMemoryRepo::default());
+        let mine = service.create(CreateTodo { title: "Mine".into(), owner: Some("a@example.com".into()), ..Default::default() }).await.unwrap();
+        let unowned = service.create(CreateTodo { title: "Unowned".into(), ..Default::default() }).await.unwrap();
+
+        for owner in [Some("a@example.com"), None] {
+            assert!(service.get(mine.id.clone(), owner).await.unwrap().is_some());
+            assert!(service.get_by_code(&mine.code, owner).await.unwrap().is_some());
+        }
+        assert!(service.get(mine.id.clone(), Some("b@example.com")).await.unwrap().is_none());
+        assert!(service.get_including_deleted(mine.id.clone(), Some("b@example.com")).await.unwrap().is_none());
+        assert!(service.get_by_code(&mine.code, Some("b@example.com")).await.unwrap().is_none());
+        assert!(service.get(unowned.id.clone(), Some("a@example.com")).await.unwrap().is_none());
+        let many = service.get_many(vec![mine.id.clone(), unowned.id.clone()], Some("a@example.com")).await.unwrap();
+        assert_eq!(many.iter().map(|t| &t.id).collect::<Vec<_>>(), vec![&mine.id]);
+    }
+
+    #[tokio::test]
+    async fn unit_create_dedups_identical_content_within_window() {
+        let repo = InMemoryRepo::default();
+        let service = TodoServiceImpl::with_config(repo.clone(), TodoServiceConfig { dedup_window: Some(std::time::Duration::from_secs(60)), ..Default::default() });
+        let first = service.create(CreateTodo { title: "Buy milk".into(), description: Some(Some("2 litres".into())), ..Default::default() }).await.unwrap();
+        let second = service.create(CreateTodo { title: "  buy   MILK ".into(), description: Some(Some("2 litres".into())), ..Default::default() }).await.unwrap();
+        assert_eq!(first.id, second.id);
+        assert_eq!(repo.list(TodoFilter::default(), Default::default()).await.unwrap().len(), 1);
+
+        service.create(CreateTodo { title: "Buy milk".into(), description: None, ..Default::default() }).await.unwrap();
+        assert_eq!(repo.list(TodoFilter::default(), Default::default()).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn unit_create_without_dedup_inserts_duplicates() {
+        let repo = InMemoryRepo::default();
+        let service = TodoServiceImpl::new(repo.clone());
+        service.create(CreateTodo { title: "Buy milk".into(), ..Default::default() }).await.unwrap();
+        service.create(CreateTodo { title: "Buy milk".into(), ..Default::default() }).await.unwrap();
+        assert_eq!(repo.list(TodoFilter::default(), Default::default()).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn unit_default_description_applies_only_when_omitted() {
+        let repo = InMemoryRepo::default();
+        let config = TodoServiceConfig { default_description: Some("- [ ] step".into()), ..Default::default() };
+        let service = TodoServiceImpl::with_config(repo, config);
+
+        let omitted: CreateTodo = serde_json::from_value(serde_json::json!({ "title": "A" })).unwrap();
+        let null: CreateTodo = serde_json::from_value(serde_json::json!({ "title": "B", "description": null })).unwrap();
+        let empty: CreateTodo = serde_json::from_value(serde_json::json!({ "title": "C", "description": "" })).unwrap();
+        let given: CreateTodo = serde_json::from_value(serde_json::json!({ "title": "D", "description": "mine" })).unwrap();
+
+        assert_eq!(service.create(omitted).await.unwrap().description.as_deref(), Some("- [ ] step"));
+        assert_eq!(service.create(null).await.unwrap().description, None);
+        assert_eq!(service.create(empty).await.unwrap().description.as_deref(), Some(""));
+        assert_eq!(service.create(given).await.unwrap().description.as_deref(), Some("mine"));
+    }
+
+    #[tokio::test]
+    async fn unit_no_default_description_by_default() {
+        let service = TodoServiceImpl::new(InMemoryRepo::default());
+        let created = service.create(CreateTodo { title: "A".into(), ..Default::default() }).await.unwrap();
+        assert_eq!(created.description, None);
+    }
+
+    #[tokio::test]
+    async fn unit_control_characters_are_stripped_by_default() {
+        let service = TodoServiceImpl::new(InMemoryRepo::default());
+        let created = service.create(CreateTodo { title: "a\0b\x1b[2Jc".into(), description: Some(Some("x\r\ny\tz\x7f".into())), ..Default::default() }).await.unwrap();
+        assert_eq!(created.title, "ab[2Jc");
+        assert_eq!(created.description.as_deref(), Some("x\ny\tz"));
+
+        let updated = service.update(created.id, UpdateTodo { title: Some("\n\ttitle\u{9b}".into()), ..Default::default() }).await.unwrap().unwrap();
+        assert_eq!(updated.title, "title");
+    }
+
+    #[tokio::test]
+    async fn unit_control_characters_are_rejected_when_configured() {
+        let repo = InMemoryRepo::default();
+        let service = TodoServiceImpl::with_config(repo.clone(), TodoServiceConfig { control_chars: ControlCharPolicy::Reject, ..Default::default() });
+        let err = service.create(CreateTodo { title: "nul\0".into(), ..Default::default() }).await.unwrap_err();
+        assert!(err.downcast_ref::<ValidationError>().is_some());
+        assert!(repo.list(TodoFilter::default(), Default::default()).await.unwrap().is_empty());
+
+        let created = service.create(CreateTodo { title: "fine".into(), description: Some(Some("a\n\tb".into())), ..Default::default() }).await.unwrap();
+        let err = service.update(created.id.clone(), UpdateTodo { description: Some(Some("\x1b[31m".into())), ..Default::default() }).await.unwrap_err();
+        assert!(err.downcast_ref::<ValidationError>().is_some());
+        assert_eq!(repo.get(created.id, None).await.unwrap().unwrap().description.as_deref(), Some("a\n\tb"));
+    }
+
+    #[tokio::test]
+    async fn unit_titles_must_be_non_blank_and_at_most_256_characters() {
+        let repo = InMemoryRepo::default();
+        let service = TodoServiceImpl::new(repo.clone());
+        let create = |title: String| service.create(CreateTodo { title, ..Default::default() });
+        for title in [String::new(), "   ".into(), "\0\t".into(), "x".repeat(MAX_TITLE_CHARS + 1), "é".repeat(MAX_TITLE_CHARS + 1)] {
+            let err = create(title.clone()).await.unwrap_err();
+            let message = err.downcast_ref::<ValidationError>().unwrap_or_else(|| panic!("{:?}: {}", title, err)).to_string();
+            assert!(message.starts_with("title "), "{}", message);
+        }
+        assert!(repo.list(TodoFilter::default(), Default::default()).await.unwrap().is_empty());
+        // Exactly the limit counts characters, not bytes
+        let longest = create("é".repeat(MAX_TITLE_CHARS)).await.unwrap();
+        create("x".into()).await.unwrap();
+
+        let err = service.update(longest.id.clone(), UpdateTodo { title: Some(" ".into()), ..Default::default() }).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<ValidationError>().unwrap().to_string(), "title must not be empty");
+        let err = service.update(longest.id.clone(), UpdateTodo { title: Some("x".repeat(MAX_TITLE_CHARS + 1)), ..Default::default() }).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<ValidationError>().unwrap().to_string(), "title must be at most 256 characters");
+        assert_eq!(repo.get(longest.id, None).await.unwrap().unwrap().title, "é".repeat(MAX_TITLE_CHARS));
+    }
+
+    #[tokio::test]
+    async fn unit_descriptions_are_at_most_4096_characters() {
+        let service = TodoServiceImpl::new(InMemoryRepo::default());
+        let with_description = |d: String| CreateTodo { title: "T".into(), description: Some(Some(d)), ..Default::default() };
+        let created = service.create(with_description("d".repeat(MAX_DESCRIPTION_CHARS))).await.unwrap();
+        let err = service.create(with_description("d".repeat(MAX_DESCRIPTION_CHARS + 1))).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<ValidationError>().unwrap().to_string(), "description must be at most 4096 characters");
+        // Empty descriptions stay allowed
+        service.update(created.id.clone(), UpdateTodo { description: Some(Some(String::new())), ..Default::default() }).await.unwrap();
+        let err = service.update(created.id, UpdateTodo { description: Some(Some("d".repeat(MAX_DESCRIPTION_CHARS + 1))), ..Default::default() }).await.unwrap_err();
+        assert!(err.downcast_ref::<ValidationError>().unwrap().to_string().starts_with("description "));
+    }
+
+    #[tokio::test]
+    async fn unit_imports_are_validated_like_creates() {
+        let exported = TodoServiceImpl::new(InMemoryRepo::default()).create(CreateTodo { title: "Exported".into(), ..Default::default() }).await.unwrap();
+        let repo = InMemoryRepo::default();
+        let service = TodoServiceImpl::new(repo.clone());
+
+        let blank = crate::domain::todo::Todo { title: " \0".into(), ..exported.clone() };
+        let err = service.import(vec![exported.clone(), blank], ConflictPolicy::Overwrite).await.unwrap_err();
+        assert_eq!(err.downcast_ref::<ValidationError>().unwrap().to_string(), "title must not be empty");
+        assert!(repo.list(TodoFilter::default(), Default::default()).await.unwrap().is_empty());
+
+        let messy = crate::domain::todo::Todo { title: "Tidy\x1b".into(), description: Some("a\r\nb".into()), tags: vec!["Home".into(), "home".into()], waiting_on: Some("Sam\0".into()), ..exported };
+        service.import(vec![messy.clone()], ConflictPolicy::Overwrite).await.unwrap();
+        let imported = repo.get(messy.id, None).await.unwrap().unwrap();
+        assert_eq!((imported.title.as_str(), imported.description.as_deref(), imported.tags.len(), imported.waiting_on.as_deref()), ("Tidy", Some("a\nb"), 1, Some("Sam")));
+    }
+
+    #[tokio::test]
+    async fn unit_titles_are_capitalized_when_configured() {
+        let service = TodoServiceImpl::with_config(InMemoryRepo::default(), TodoServiceConfig { capitalize_titles: true, ..Default::default() });
+        let created = service.create(CreateTodo { title: "  buy milk and EGGS".into(), ..Default::default() }).await.unwrap();
+        assert_eq!(created.title, "  Buy milk and EGGS");
+        let updated = service.update(created.id, UpdateTodo { title: Some("écrire à Zoë".into()), ..Default::default() }).await.unwrap().unwrap();
+        assert_eq!(updated.title, "Écrire à Zoë");
+        assert_eq!(service.create(CreateTodo { title: "42 things".into(), ..Default::default() }).await.unwrap().title, "42 things");
+
+        let off = TodoServiceImpl::new(InMemoryRepo::default());
+        assert_eq!(off.create(CreateTodo { title: "buy milk".into(), ..Default::default() }).await.unwrap().title, "buy milk");
+    }
+
+    #[tokio::test]
+    async fn unit_expired_deletes_are_purged_and_cannot_be_undone() {
+        let repo = InMemoryRepo::default();
+        let service = TodoServiceImpl::with_config(repo.clone(), TodoServiceConfig { undo_delete_window: Some(std::time::Duration::from_millis(20)), ..Default::default() });
+        let kept = service.create(CreateTodo { title: "kept".into(), ..Default::default() }).await.unwrap();
+        let gone = service.create(CreateTodo { title: "gone".into(), ..Default::default() }).await.unwrap();
+        service.delete(gone.id.clone()).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(30)).await;
+
+        let err = service.undo_delete(gone.id.clone()).await.unwrap_err();
+        assert!(err.downcast_ref::<UndoWindowExpired>().is_some(), "{}", err);
+        assert_eq!(service.purge_expired_deletes().await.unwrap(), 1);
+        assert!(repo.get_including_deleted(gone.id.clone(), None).await.unwrap().is_none());
+        assert!(service.undo_delete(gone.id).await.unwrap().is_none());
+        assert!(service.get(kept.id, None).await.unwrap().is_some());
+
+        let unlimited = TodoServiceImpl::new(InMemoryRepo::default());
+        assert_eq!(unlimited.purge_expired_deletes().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn unit_done_requires_a_description_only_when_configured() {
+        let lenient = TodoServiceImpl::new(InMemoryRepo::default());
+        let todo = lenient.create(CreateTodo { title: "quick".into(), ..Default::default() }).await.unwrap();
+        let done = lenient.update(todo.id, UpdateTodo { status: Some(TodoStatus::Done), ..Default::default() }).await.unwrap().unwrap();
+        assert_eq!(done.status, TodoStatus::Done);
+
+        let strict = TodoServiceImpl::with_config(InMemoryRepo::default(), TodoServiceConfig { description_required_for: vec![TodoStatus::Done], ..Default::default() });
+        let todo = strict.create(CreateTodo { title: "needs notes".into(), description: Some(Some("  ".into())), ..Default::default() }).await.unwrap();
+        let err = strict.update(todo.id.clone(), UpdateTodo { status: Some(TodoStatus::Done), ..Default::default() }).await.unwrap_err();
+        assert!(err.downcast_ref::<ValidationError>().is_some(), "{}", err);
+        assert_eq!(strict.get(todo.id.clone(), None).await.unwrap().unwrap().status, TodoStatus::Pending);
+        // Other statuses stay free, and a description sent along with the move counts
+        strict.update(todo.id.clone(), UpdateTodo { status: Some(TodoStatus::Archived), ..Default::default() }).await.unwrap().unwrap();
+        let done = strict.update(todo.id, UpdateTodo { status: Some(TodoStatus::Done), description: Some(Some("shipped in v2".into())), ..Default::default() }).await.unwrap().unwrap();
+        assert_eq!(done.status, TodoStatus::Done);
+    }
 
     #[derive(Clone, Default)]
-    struct InMemoryRepo {
-        items: std::sync::Arc<std::sync::Mutex<std::collections::HashMap<String, Todo>>>,
+    struct RecordingSubscriber { seen: std::sync::Arc<std::sync::Mutex<Vec<TodoEvent>>> }
+
+    #[async_trait]
+    impl EventSubscriber for RecordingSubscriber {
+        async fn handle(&self, event: &TodoEvent) { self.seen.lock().unwrap().push(event.clone()); }
     }
 
+    #[tokio::test]
+    async fn unit_completing_a_daily_todo_creates_the_next_one() {
+        let repo = InMemoryRepo::default();
+        let service = TodoServiceImpl::new(repo.clone());
+        let due = chrono::Utc::now();
+        let daily = service.create(CreateTodo { title: "Water plants".into(), due_at: Some(due), recurrence: Some(Recurrence::Daily), ..Default::default() }).await.unwrap();
+
+        service.update(daily.id.clone(), UpdateTodo { status: Some(TodoStatus::Done), ..Default::default() }).await.unwrap();
+
+        let todos = repo.list(TodoFilter::default(), Default::default()).await.unwrap();
+        assert_eq!(todos.len(), 2);
+        assert_eq!(todos.iter().find(|t| t.id == daily.id).unwrap().status, TodoStatus::Done);
+        let next = todos.iter().find(|t| t.id != daily.id).unwrap();
+        assert_eq!((next.title.as_str(), &next.status, next.recurrence), ("Water plants", &TodoStatus::Pending, Some(Recurrence::Daily)));
+        assert_eq!(next.due_at, Some(due + chrono::Duration::days(1)));
+
+        // Saving the done todo again doesn't spawn another copy
+        service.update(daily.id, UpdateTodo { status: Some(TodoStatus::Done), ..Default::default() }).await.unwrap();
+        assert_eq!(repo.list(TodoFilter::default(), Default::default()).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn unit_waiting_todos_serialize_and_filter_by_status() {
+        let repo = InMemoryRepo::default();
+        let service = TodoServiceImpl::new(repo.clone());
+        let blocked = service.create(CreateTodo { title: "Sign contract".into(), ..Default::default() }).await.unwrap();
+        service.create(CreateTodo { title: "Book flights".into(), ..Default::default() }).await.unwrap();
+        service.update(blocked.id.clone(), UpdateTodo { status: Some(TodoStatus::Waiting), waiting_on: Some(Some("legal@example.com".into())), ..Default::default() }).await.unwrap();
+
+        let waiting = repo.list(TodoFilter { status: Some(TodoStatus::Waiting), ..Default::default() }, Default::default()).await.unwrap();
+        assert_eq!(waiting.iter().map(|t| &t.id).collect::<Vec<_>>(), [&blocked.id]);
+        assert_eq!(waiting[0].waiting_on.as_deref(), Some("legal@example.com"));
+        assert_eq!(repo.list(TodoFilter::default(), Default::default()).await.unwrap().len(), 2);
+
+        assert_eq!(TodoStatus::Waiting.as_str(), "waiting");
+        let json = serde_json::to_value(&waiting[0]).unwrap();
+        assert_eq!((&json["status"], &json["waiting_on"]), (&serde_json::json!("waiting"), &serde_json::json!("legal@example.com")));
+        assert_eq!(serde_json::from_value::<crate::domain::todo::Todo>(json).unwrap(), waiting[0]);
+    }
+
+    #[tokio::test]
+    async fn unit_events_fire_for_create_update_delete() {
+        let service = TodoServiceImpl::new(InMemoryRepo::default());
+        let recorder = RecordingSubscriber::default();
+        service.events().spawn_subscriber(recorder.clone());
+
+        let created = service.create(CreateTodo { title: "A".into(), ..Default::default() }).await.unwrap();
+        let updated = service.update(created.id.clone(), UpdateTodo { status: Some(TodoStatus::Done), ..Default::default() }).await.unwrap().unwrap();
+        service.delete(created.id.clone()).await.unwrap();
+        // misses publish nothing
+        service.delete(created.id.clone()).await.unwrap();
+
+        let expected = vec![TodoEvent::Created(created.clone()), TodoEvent::Updated(updated), TodoEvent::Deleted(created.id)];
+        tokio::time::timeout(std::time::Duration::from_secs(1), async {
+            while recorder.seen.lock().unwrap().len() < expected.len() { tokio::task::yield_now().await; }
+        }).await.unwrap();
+        assert_eq!(*recorder.seen.lock().unwrap(), expected);
+    }
+
+    /// Records events slowly, like a webhook sender waiting on the network.
+    #[derive(Clone, Default)]
+    struct SlowSubscriber { seen: std::sync::Arc<std::sync::Mutex<Vec<TodoEvent>>>, delay_ms: u64 }
+
     #[async_trait]
-    impl TodoRepository for InMemoryRepo {
-        async fn init(&self) -> Result<()> { Ok(()) }
-        async fn create(&self, input: CreateTodo) -> Result<Todo> {
-            let now = Utc::now();
-            let id = TodoId(uuid::Uuid::new_v4());
-            let todo = Todo { id: id.clone(), title: input.title, description: input.description, status: TodoStatus::Pending, created_at: now, updated_at: now };
-            self.items.lock().unwrap().insert(id.0.to_string(), todo.clone());
-            Ok(todo)
+    impl EventSubscriber for SlowSubscriber {
+        async fn handle(&self, event: &TodoEvent) {
+            tokio::time::sleep(std::time::Duration::from_millis(self.delay_ms)).await;
+            self.seen.lock().unwrap().push(event.clone());
         }
-        async fn get(&self, id: TodoId) -> Result<Option<Todo>> { Ok(self.items.lock().unwrap().get(&id.0.to_string()).cloned()) }
-        async fn list(&self) -> Result<Vec<Todo>> { Ok(self.items.lock().unwrap().values().cloned().collect()) }
-        async fn update(&self, id: TodoId, input: UpdateTodo) -> Result<Option<Todo>> {
-            let mut map = self.items.lock().unwrap();
-            let Some(mut todo) = map.get(&id.0.to_string()).cloned() else { return Ok(None) };
-            if let Some(t) = input.title { todo.title = t; }
-            if let Some(d) = input.description { todo.description = Some(d); }
-            if let Some(s) = input.status { todo.status = s; }
-            todo.updated_at = Utc::now();
-            map.insert(id.0.to_string(), todo.clone());
-            Ok(Some(todo))
+    }
+
+    #[tokio::test]
+    async fn unit_shutdown_flushes_queued_events() {
+        let service = TodoServiceImpl::new(InMemoryRepo::default());
+        let slow = SlowSubscriber { delay_ms: 20, ..Default::default() };
+        service.events().spawn_subscriber(slow.clone());
+        for title in ["a", "b", "c", "d", "e"] {
+            service.create(CreateTodo { title: title.into(), ..Default::default() }).await.unwrap();
         }
-        async fn delete(&self, id: TodoId) -> Result<bool> { Ok(self.items.lock().unwrap().remove(&id.0.to_string()).is_some()) }
+
+        let report = service.events().shutdown(std::time::Duration::from_secs(5)).await;
+        assert_eq!(slow.seen.lock().unwrap().len(), 5);
+        assert_eq!(report.dropped, 0);
+        assert!(report.flushed > 0);
     }
 
     #[tokio::test]
-    async fn unit_create_and_get() {
+    async fn unit_shutdown_reports_events_dropped_at_deadline() {
+        let service = TodoServiceImpl::new(InMemoryRepo::default());
+        let slow = SlowSubscriber { delay_ms: 200, ..Default::default() };
+        service.events().spawn_subscriber(slow.clone());
+        for title in ["a", "b", "c", "d", "e"] {
+            service.create(CreateTodo { title: title.into(), ..Default::default() }).await.unwrap();
+        }
+
+        let report = service.events().shutdown(std::time::Duration::from_millis(50)).await;
+        let handled = slow.seen.lock().unwrap().len() as u64;
+        assert_eq!(report, DrainReport { flushed: handled, dropped: 5 - handled });
+        assert!(report.dropped > 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn unit_subscribers_spawned_while_publishing_drop_nothing_at_shutdown() {
+        let bus = super::super::events::EventBus::default();
+        let publisher = {
+            let bus = bus.clone();
+            std::thread::spawn(move || for _ in 0..2000 { bus.publish(TodoEvent::Deleted(crate::domain::todo::TodoId(uuid::Uuid::new_v4()))); })
+        };
+        let subscribers: Vec<RecordingSubscriber> = (0..50).map(|_| RecordingSubscriber::default()).collect();
+        for subscriber in &subscribers { bus.spawn_subscriber(subscriber.clone()); }
+        publisher.join().unwrap();
+
+        // Each `start` matches what its receiver missed, so the exact count in `shutdown` can't underflow
+        let report = bus.shutdown(std::time::Duration::from_secs(10)).await;
+        assert_eq!(report.dropped, 0);
+    }
+
+    #[tokio::test]
+    async fn unit_memory_repo_sorts_by_priority_then_soonest_due() {
+        use crate::domain::todo::{ListOptions, Priority, SortField, SortOrder};
         let repo = InMemoryRepo::default();
-        let service = TodoServiceImpl::new(repo);
-        let created = service.create(CreateTodo { title: "X".into(), description: None }).await.unwrap();
-        assert_eq!(created.title, "X");
-        let got = service.get(created.id.clone()).await.unwrap().unwrap();
-        assert_eq!(got.id, created.id);
+        let due = |s: &str| Some(s.parse::<chrono::DateTime<chrono::Utc>>().unwrap());
+        for (title, priority, due_at) in [
+            ("low", Priority::Low, due("2030-01-01T00:00:00Z")),
+            ("high undated", Priority::High, None),
+            ("medium", Priority::Medium, None),
+            ("high later", Priority::High, due("2031-01-01T00:00:00Z")),
+            ("high soon", Priority::High, due("2030-06-01T00:00:00Z")),
+        ] {
+            repo.create(CreateTodo { title: title.into(), priority: Some(priority), due_at, ..Default::default() }).await.unwrap();
+        }
+
+        let repo = &repo;
+        let titles = |order| async move {
+            let todos = repo.list(TodoFilter::default(), ListOptions { sort: SortField::Priority, order }).await.unwrap();
+            todos.into_iter().map(|t| t.title).collect::<Vec<_>>()
+        };
+        let expected = vec!["high soon", "high later", "high undated", "medium", "low"];
+        assert_eq!(titles(SortOrder::Desc).await, expected);
+        assert_eq!(titles(SortOrder::Asc).await, expected.into_iter().rev().collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn unit_memory_repo_finds_and_fixes_a_done_todo_without_completed_at() {
+        let repo = InMemoryRepo::default();
+        let mut todo = repo.create(CreateTodo { title: "Done long ago".into(), ..Default::default() }).await.unwrap();
+        let fine = repo.create(CreateTodo { title: "Fine".into(), ..Default::default() }).await.unwrap();
+        todo.status = TodoStatus::Done;
+        todo.completed_at = None;
+        repo.import_todos(vec![todo.clone()], ConflictPolicy::Overwrite).await.unwrap();
+
+        let found = repo.find_inconsistencies().await.unwrap();
+        assert_eq!(found, vec![Inconsistency { id: todo.id.clone(), kind: InconsistencyKind::DoneWithoutCompletedAt }]);
+        assert!(found.iter().all(|i| i.id != fine.id));
+
+        assert_eq!(repo.fix_inconsistencies().await.unwrap(), 1);
+        assert_eq!(repo.get(todo.id.clone(), None).await.unwrap().unwrap().completed_at, Some(todo.updated_at));
+        assert!(repo.find_inconsistencies().await.unwrap().is_empty());
     }
 }
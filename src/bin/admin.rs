@@ -0,0 +1,49 @@
+use std::io::Write;
+
+use anyhow::{bail, Context, Result};
+
+use api::{application::maintenance::{self, AdminCommand}, domain::{repository::TodoRepository, todo::{ConflictPolicy, Todo}}, infrastructure::sqlite_repo::SqliteTodoRepository};
+
+const USAGE: &str = "usage: admin <vacuum | reindex | purge-trash | export [--status <status>] [--tag <tag>] [--completed-after <date>] | export-ndjson [same flags] | import <file.json> [--on-conflict skip|overwrite|fail] | stats>";
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let _ = dotenvy::dotenv();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(("export-ndjson", flags)) = args.split_first().map(|(name, flags)| (name.as_str(), flags)) {
+        let filter = maintenance::parse_export_filter(&flags.iter().map(String::as_str).collect::<Vec<_>>()).context(USAGE)?;
+        // Streams straight to stdout instead of building one JSON document
+        let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+        maintenance::export_ndjson(&open_repo().await?, filter, &mut out).await?;
+        out.flush()?;
+        return Ok(());
+    }
+    let command = match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["import", path, rest @ ..] => {
+            let policy = match rest {
+                [] => ConflictPolicy::default(),
+                ["--on-conflict", policy] => policy.parse()?,
+                _ => bail!(USAGE),
+            };
+            let text = std::fs::read_to_string(path).with_context(|| format!("cannot read {}", path))?;
+            // Accept both a bare array and the document `export` prints
+            let value: serde_json::Value = serde_json::from_str(&text).with_context(|| format!("invalid JSON in {}", path))?;
+            let todos = value.get("todos").cloned().unwrap_or(value);
+            AdminCommand::Import(serde_json::from_value::<Vec<Todo>>(todos).context("expected an array of todos")?, policy)
+        }
+        ["export", flags @ ..] => AdminCommand::parse_export(flags).context(USAGE)?,
+        [name] => AdminCommand::parse(name).context(USAGE)?,
+        _ => bail!(USAGE),
+    };
+
+    let result = maintenance::run(&open_repo().await?, command).await?;
+    println!("{}", serde_json::to_string_pretty(&result)?);
+    Ok(())
+}
+
+async fn open_repo() -> Result<SqliteTodoRepository> {
+    let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://todos.db".to_string());
+    let repo = SqliteTodoRepository::connect(&database_url).await?;
+    repo.init().await?;
+    Ok(repo)
+}
@@ -1,19 +1,38 @@
-use std::time::{Duration, Instant};
+use std::{future::Future, net::SocketAddr, path::PathBuf, sync::{atomic::{AtomicUsize, Ordering}, Arc}, time::{Duration, Instant}};
 
-use anyhow::Result;
+use anyhow::{anyhow, bail, Context, Result};
 use crossterm::{event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind}, execute, terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen}};
-use ratatui::{backend::CrosstermBackend, Terminal, widgets::{Block, Borders, List, ListItem, Paragraph, ListState}, layout::{Layout, Constraint, Direction}, style::{Style, Modifier, Color}};
+use ratatui::{backend::CrosstermBackend, Terminal, text::{Line, Span}, widgets::{Block, Borders, Gauge, List, ListItem, Paragraph, ListState}, layout::{Layout, Constraint, Direction}, style::{Style, Modifier, Color}};
 
-use api::{application::todo_service::{TodoService, TodoServiceImpl}, domain::{repository::TodoRepository, todo::{CreateTodo, TodoStatus}}, infrastructure::sqlite_repo::SqliteTodoRepository};
+use chrono::{DateTime, Local, Utc};
+use serde::Deserialize;
+
+use api::{application::todo_service::{TodoService, TodoServiceConfig, TodoServiceImpl}, http::routing, domain::{repository::TodoRepository, todo::{normalize_tags, CreateTodo, ListOptions, Priority, SortField, SortOrder, TodoFilter, TodoId, TodoStatus, UpdateTodo}}, infrastructure::sqlite_repo::{self, SqliteTodoRepository}};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let _ = dotenvy::dotenv();
+    let serve_addr = parse_args()?;
+    let config = load_config()?;
+    let keys = config.keys.resolve()?;
+    let date_format = config.resolve_date_format()?;
+    let list_options = config.resolve_list_options()?;
     let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://todos.db".to_string());
     prepare_sqlite_file(&database_url)?;
     let repo = SqliteTodoRepository::connect(&database_url).await?;
     repo.init().await?;
-    let service = TodoServiceImpl::new(repo);
+    let service = TodoServiceImpl::with_config(repo.clone(), TodoServiceConfig::from_env()?);
+    // Bound before the terminal is taken over so a busy port is reported as a plain error
+    let serving = match serve_addr {
+        Some(addr) => {
+            let listener = tokio::net::TcpListener::bind(addr).await.with_context(|| format!("cannot serve the API on {}", addr))?;
+            let addr = listener.local_addr()?;
+            let router = routing::api(repo, service.clone(), None)?;
+            tokio::spawn(async move { axum::serve(listener, router).await });
+            Some(addr)
+        }
+        None => None,
+    };
 
     // Terminal setup
     enable_raw_mode()?;
@@ -22,7 +41,7 @@ async fn main() -> Result<()> {
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    let res = run_app(&mut terminal, service).await;
+    let res = run_app(&mut terminal, service, keys, date_format, list_options, serving).await;
 
     // Restore terminal
     disable_raw_mode()?;
@@ -32,20 +51,245 @@ async fn main() -> Result<()> {
     res
 }
 
+const DEFAULT_SERVE_ADDR: &str = "127.0.0.1:3000";
+/// How often the list reloads while serving, so writes made through the API show up.
+const SERVE_RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// `--serve` also runs the HTTP API on 127.0.0.1:3000, `--serve=ADDR` on another address.
+fn parse_args() -> Result<Option<SocketAddr>> {
+    let mut serve = None;
+    for arg in std::env::args().skip(1) {
+        let addr = match arg.as_str() {
+            "--serve" => DEFAULT_SERVE_ADDR,
+            other => other.strip_prefix("--serve=").ok_or_else(|| anyhow!("unknown argument {:?} (expected --serve or --serve=ADDR)", other))?,
+        };
+        serve = Some(addr.parse().with_context(|| format!("--serve: invalid address {:?}", addr))?);
+    }
+    Ok(serve)
+}
+
+/// `ConfirmDelete` holds the id of the todo being confirmed, so a reload that reorders the list
+/// while the prompt is up cannot retarget the delete; `ConfirmDeleteShown` likewise deletes the
+/// ids in `App::confirm_ids`, taken when it was asked. `TagFilter` is the tag prompt.
 #[derive(Clone, Copy, PartialEq, Eq)]
-enum Mode { View, Create, Edit }
+enum Mode { View, Create, Edit, ConfirmDelete(uuid::Uuid), ConfirmDeleteShown, TagFilter }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
-enum Filter { All, Pending, Done }
+enum Filter { All, Pending, Waiting, Done, Overdue }
 
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum ActiveField { Title, Description }
 
+/// `ThreePane` adds a history pane next to the details when the terminal is wide enough.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PaneLayout { TwoPane, ThreePane }
+
+const MIN_THREE_PANE_WIDTH: u16 = 120;
+
+/// A change made from the TUI, with what it takes to reverse it.
+enum Undo {
+    /// Deletes are soft, so undoing one restores the same todo.
+    Delete { id: uuid::Uuid, title: String },
+    Status { id: uuid::Uuid, previous: TodoStatus },
+    /// Completing a recurring todo also created its next occurrence, so flipping the status
+    /// back would leave a duplicate; undoing it only explains why it can't be.
+    Recurring { title: String },
+    Edit { id: uuid::Uuid, title: String, description: Option<String> },
+}
+
+/// Older changes fall off the bottom of the undo stack.
+const UNDO_LIMIT: usize = 20;
+
+/// View-mode keys; each can be remapped in the `[keys]` table of `tui.toml`.
+#[derive(Clone, Copy)]
+struct KeyBindings { quit: KeyCode, new: KeyCode, edit: KeyCode, delete: KeyCode, toggle: KeyCode, filter: KeyCode, import: KeyCode, layout: KeyCode, complete_all: KeyCode, delete_all: KeyCode, priority_up: KeyCode, priority_down: KeyCode, sort: KeyCode, tag: KeyCode, undo: KeyCode, archive: KeyCode }
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self { quit: KeyCode::Char('q'), new: KeyCode::Char('n'), edit: KeyCode::Char('e'), delete: KeyCode::Char('d'), toggle: KeyCode::Enter, filter: KeyCode::Char('f'), import: KeyCode::Char('p'), layout: KeyCode::Char('L'), complete_all: KeyCode::Char('C'), delete_all: KeyCode::Char('D'), priority_up: KeyCode::Char('+'), priority_down: KeyCode::Char('-'), sort: KeyCode::Char('s'), tag: KeyCode::Char('t'), undo: KeyCode::Char('u'), archive: KeyCode::Char('a') }
+    }
+}
+
+impl KeyBindings {
+    fn actions(&self) -> [(&'static str, KeyCode); 16] {
+        [("quit", self.quit), ("new", self.new), ("edit", self.edit), ("delete", self.delete), ("toggle", self.toggle), ("filter", self.filter), ("import", self.import), ("layout", self.layout), ("complete_all", self.complete_all), ("delete_all", self.delete_all), ("priority_up", self.priority_up), ("priority_down", self.priority_down), ("sort", self.sort), ("tag", self.tag), ("undo", self.undo), ("archive", self.archive)]
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct TuiConfig { keys: KeysConfig, date_format: Option<String>, sort: Option<String> }
+
+/// Short local time, e.g. `2024-05-06 14:30`.
+const DEFAULT_DATE_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+impl TuiConfig {
+    /// The configured chrono `strftime` pattern, rejected up front so a typo can't panic mid-render.
+    fn resolve_date_format(&self) -> Result<String> {
+        let Some(format) = &self.date_format else { return Ok(DEFAULT_DATE_FORMAT.to_string()) };
+        if chrono::format::StrftimeItems::new(format).any(|item| matches!(item, chrono::format::Item::Error)) {
+            bail!("date_format: invalid format string {:?}", format);
+        }
+        Ok(format.clone())
+    }
+
+    /// `sort = "created"` (default, newest first), `"staleness"` (pending todos untouched the longest
+    /// first) or `"priority"` (high first, soonest due first within a priority).
+    fn resolve_list_options(&self) -> Result<ListOptions> {
+        match self.sort.as_deref() {
+            None | Some("created") => Ok(ListOptions::default()),
+            Some("staleness") => Ok(ListOptions { sort: SortField::Staleness, ..Default::default() }),
+            Some("priority") => Ok(ListOptions { sort: SortField::Priority, ..Default::default() }),
+            Some(other) => bail!("sort: unknown sort {:?} (expected \"created\", \"staleness\" or \"priority\")", other),
+        }
+    }
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct KeysConfig { quit: Option<String>, new: Option<String>, edit: Option<String>, delete: Option<String>, toggle: Option<String>, filter: Option<String>, import: Option<String>, layout: Option<String>, complete_all: Option<String>, delete_all: Option<String>, priority_up: Option<String>, priority_down: Option<String>, sort: Option<String>, tag: Option<String>, undo: Option<String>, archive: Option<String> }
+
+impl KeysConfig {
+    /// Applies the configured keys over the defaults and rejects keys bound to more than one action.
+    fn resolve(&self) -> Result<KeyBindings> {
+        let defaults = KeyBindings::default();
+        let pick = |name: &str, value: &Option<String>, default: KeyCode| -> Result<KeyCode> {
+            match value {
+                Some(v) => parse_key(v).ok_or_else(|| anyhow!("keys.{}: unknown key {:?}", name, v)),
+                None => Ok(default),
+            }
+        };
+        let keys = KeyBindings {
+            quit: pick("quit", &self.quit, defaults.quit)?,
+            new: pick("new", &self.new, defaults.new)?,
+            edit: pick("edit", &self.edit, defaults.edit)?,
+            delete: pick("delete", &self.delete, defaults.delete)?,
+            toggle: pick("toggle", &self.toggle, defaults.toggle)?,
+            filter: pick("filter", &self.filter, defaults.filter)?,
+            import: pick("import", &self.import, defaults.import)?,
+            layout: pick("layout", &self.layout, defaults.layout)?,
+            complete_all: pick("complete_all", &self.complete_all, defaults.complete_all)?,
+            delete_all: pick("delete_all", &self.delete_all, defaults.delete_all)?,
+            priority_up: pick("priority_up", &self.priority_up, defaults.priority_up)?,
+            priority_down: pick("priority_down", &self.priority_down, defaults.priority_down)?,
+            sort: pick("sort", &self.sort, defaults.sort)?,
+            tag: pick("tag", &self.tag, defaults.tag)?,
+            undo: pick("undo", &self.undo, defaults.undo)?,
+            archive: pick("archive", &self.archive, defaults.archive)?,
+        };
+        let actions = keys.actions();
+        let mut conflicts = Vec::new();
+        for (i, (a, key)) in actions.iter().enumerate() {
+            for (b, other) in &actions[i + 1..] {
+                if key == other { conflicts.push(format!("{} and {} are both bound to {}", a, b, key_label(*key))); }
+            }
+        }
+        if !conflicts.is_empty() { bail!("conflicting TUI key bindings:\n  {}", conflicts.join("\n  ")); }
+        Ok(keys)
+    }
+}
+
+/// `TUI_CONFIG` overrides the default `~/.config/gpt5-todo/tui.toml` (`%APPDATA%\gpt5-todo\tui.toml` on Windows).
+fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("TUI_CONFIG") { return Some(PathBuf::from(path)); }
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from).or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")))
+    };
+    base.map(|b| b.join("gpt5-todo").join("tui.toml"))
+}
+
+fn load_config() -> Result<TuiConfig> {
+    let Some(path) = config_path() else { return Ok(TuiConfig::default()) };
+    match std::fs::read_to_string(&path) {
+        Ok(text) => toml::from_str(&text).with_context(|| format!("invalid TUI config {}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(TuiConfig::default()),
+        Err(e) => Err(e).with_context(|| format!("cannot read TUI config {}", path.display())),
+    }
+}
+
+fn parse_key(s: &str) -> Option<KeyCode> {
+    let mut chars = s.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) { return Some(KeyCode::Char(c)); }
+    match s.to_ascii_lowercase().as_str() {
+        "enter" => Some(KeyCode::Enter),
+        "space" => Some(KeyCode::Char(' ')),
+        "tab" => Some(KeyCode::Tab),
+        "backspace" => Some(KeyCode::Backspace),
+        "delete" | "del" => Some(KeyCode::Delete),
+        "esc" | "escape" => Some(KeyCode::Esc),
+        _ => None,
+    }
+}
+
+fn key_label(key: KeyCode) -> String {
+    match key {
+        KeyCode::Char(' ') => "Space".into(),
+        KeyCode::Char(c) => c.to_string(),
+        KeyCode::Enter => "Enter".into(),
+        KeyCode::Tab => "Tab".into(),
+        KeyCode::Backspace => "Backspace".into(),
+        KeyCode::Delete => "Del".into(),
+        KeyCode::Esc => "Esc".into(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// What the `sort` key steps through: newest first, title A–Z, the stalest pending todos first,
+/// then high priority first.
+fn next_sort(current: ListOptions) -> ListOptions {
+    match current.sort {
+        SortField::Title => ListOptions { sort: SortField::Staleness, order: SortOrder::Desc },
+        SortField::Staleness => ListOptions { sort: SortField::Priority, order: SortOrder::Desc },
+        SortField::Priority => ListOptions::default(),
+        SortField::CreatedAt | SortField::UpdatedAt => ListOptions { sort: SortField::Title, order: SortOrder::Asc },
+    }
+}
+
+/// Colour of the priority tag in the list.
+fn priority_color(priority: Priority) -> Color {
+    match priority { Priority::High => Color::Red, Priority::Medium => Color::Yellow, Priority::Low => Color::DarkGray }
+}
+
+fn sort_label(options: ListOptions) -> &'static str {
+    match options.sort { SortField::Title => "title", SortField::Staleness => "staleness", SortField::Priority => "priority", SortField::CreatedAt | SortField::UpdatedAt => "created" }
+}
+
 struct ListEntry {
     id: uuid::Uuid,
     status: TodoStatus,
     title: String,
     description: Option<String>,
+    priority: Priority,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+    tags: Vec<String>,
+    waiting_on: Option<String>,
+    due_at: Option<DateTime<Utc>>,
+    recurring: bool,
+}
+
+impl ListEntry {
+    /// Still to be done: pending, in progress or waiting.
+    fn is_open(&self) -> bool { matches!(self.status, TodoStatus::Pending | TodoStatus::InProgress | TodoStatus::Waiting) }
+
+    /// Open and past its due date; todos without a due date are never overdue.
+    fn is_overdue(&self, now: DateTime<Utc>) -> bool { self.is_open() && self.due_at.is_some_and(|due| due < now) }
+}
+
+/// "Due in 3 days" / "Overdue by 1 day", counting any part of a day as a whole one.
+fn due_label(due: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let days = |secs: i64| (secs + 86_399) / 86_400;
+    let plural = |n: i64| if n == 1 { "" } else { "s" };
+    if due >= now {
+        let n = days((due - now).num_seconds()).max(1);
+        format!("Due in {} day{}", n, plural(n))
+    } else {
+        let n = days((now - due).num_seconds()).max(1);
+        format!("Overdue by {} day{}", n, plural(n))
+    }
 }
 
 struct App<R: TodoRepository> {
@@ -60,29 +304,166 @@ struct App<R: TodoRepository> {
     field: ActiveField,
     draft_title: String,
     draft_desc: String,
+    keys: KeyBindings,
+    message: Option<String>,
+    /// The last failed action; stays in the footer until an action succeeds.
+    last_error: Option<String>,
+    layout: PaneLayout,
+    date_format: String,
+    list_options: ListOptions,
+    /// Only todos carrying this tag (ignoring case) are shown, on top of `filter`.
+    tag_filter: Option<String>,
+    draft_tag: String,
+    bulk: Option<BulkJob>,
+    /// The todos `ConfirmDeleteShown` asks about.
+    confirm_ids: Vec<uuid::Uuid>,
+    /// Where the embedded API listens, with `--serve`.
+    serving: Option<SocketAddr>,
+    last_load: Instant,
+    /// Most recent change last; cleared when the filter changes.
+    undo: Vec<Undo>,
+}
+
+/// A bulk operation running on a background task so the UI stays responsive; the footer
+/// shows its progress and keys that would change todos are ignored until it finishes.
+struct BulkJob {
+    label: &'static str,
+    total: usize,
+    done: Arc<AtomicUsize>,
+    task: tokio::task::JoinHandle<(usize, usize)>,
+    summary: fn(usize, usize) -> String,
+}
+
+impl BulkJob {
+    /// Runs `op` for each input in order; the task yields (succeeded, failed).
+    fn spawn<T, F, Fut>(label: &'static str, inputs: Vec<T>, op: F, summary: fn(usize, usize) -> String) -> Self
+    where
+        T: Send + 'static,
+        F: Fn(T) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send,
+    {
+        let total = inputs.len();
+        let done = Arc::new(AtomicUsize::new(0));
+        let counter = done.clone();
+        let task = tokio::spawn(async move {
+            let (mut ok, mut failed) = (0, 0);
+            for input in inputs {
+                match op(input).await { Ok(()) => ok += 1, Err(_) => failed += 1 }
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+            (ok, failed)
+        });
+        Self { label, total, done, task, summary }
+    }
 }
 
 impl<R: TodoRepository> App<R> {
     async fn load(&mut self) -> Result<()> {
-        let todos = self.service.list().await?;
+        let pinned = self.selected_id();
+        let todos = self.service.list(TodoFilter::default(), self.list_options).await?;
         self.items = todos
             .into_iter()
-            .map(|t| ListEntry { id: t.id.0, status: t.status, title: t.title, description: t.description })
+            .map(|t| ListEntry { id: t.id.0, status: t.status, title: t.title, description: t.description, priority: t.priority, created_at: t.created_at, updated_at: t.updated_at, completed_at: t.completed_at, tags: t.tags, waiting_on: t.waiting_on, due_at: t.due_at, recurring: t.recurrence.is_some() })
             .collect();
-        self.recompute_filtered();
+        self.refilter(pinned);
+        self.last_load = Instant::now();
         Ok(())
     }
 
+    /// Settles a write: success clears `last_error` and reloads; failure is recorded and the
+    /// list is left alone, since it still shows what the database holds.
+    async fn settle<T>(&mut self, action: &str, result: Result<T>) -> Result<()> {
+        match result {
+            Ok(_) => { self.last_error = None; self.load().await }
+            Err(e) => { self.last_error = Some(format!("{} failed: {}", action, e)); Ok(()) }
+        }
+    }
+
+    /// Records a change that succeeded so `u` can reverse it.
+    fn push_undo(&mut self, undo: Undo) {
+        if self.undo.len() == UNDO_LIMIT { self.undo.remove(0); }
+        self.undo.push(undo);
+    }
+
+    /// Reverses the most recent change still on the stack.
+    async fn undo_last(&mut self) -> Result<()> {
+        let Some(undo) = self.undo.pop() else {
+            self.message = Some("Nothing to undo".to_string());
+            return Ok(());
+        };
+        let (label, result) = match &undo {
+            Undo::Delete { id, title } => (format!("Restored '{}'", title), retry_transient(|| self.service.restore(TodoId(*id))).await),
+            Undo::Status { id, previous } => (format!("Status back to {}", previous), retry_transient(|| self.service.update(TodoId(*id), UpdateTodo { status: Some(previous.clone()), ..Default::default() })).await),
+            Undo::Edit { id, title, description } => (format!("Edit of '{}' undone", title), retry_transient(|| self.service.update(TodoId(*id), UpdateTodo { title: Some(title.clone()), description: Some(description.clone()), ..Default::default() })).await),
+            Undo::Recurring { title } => {
+                self.message = Some(format!("Can't undo completing '{}': its next occurrence already exists", title));
+                return Ok(());
+            }
+        };
+        let result = result.and_then(|todo| todo.ok_or_else(|| anyhow!("todo is gone")));
+        let undone = result.is_ok();
+        self.settle("Undo", result).await?;
+        if undone { self.message = Some(label); }
+        Ok(())
+    }
+
+    /// Applies a new status or tag filter; undo history doesn't carry over to the new view.
+    fn filter_changed(&mut self) {
+        self.undo.clear();
+        self.recompute_filtered();
+    }
+
+    /// The highlighted entry. `selected` indexes `filtered_indices`, not `items`, so every
+    /// lookup of "the todo under the cursor" must go through here.
+    fn current_entry(&self) -> Option<&ListEntry> {
+        self.filtered_indices.get(self.selected).and_then(|&idx| self.items.get(idx))
+    }
+
+    fn selected_id(&self) -> Option<uuid::Uuid> {
+        self.current_entry().map(|e| e.id)
+    }
+
+    /// Every tag on the loaded todos, for completing the tag prompt.
+    fn known_tags(&self) -> Vec<String> {
+        normalize_tags(self.items.iter().flat_map(|e| e.tags.iter().cloned()).collect())
+    }
+
+    /// Known tags starting with what has been typed so far, ignoring case.
+    fn tag_completions(&self) -> Vec<String> {
+        let typed = self.draft_tag.trim().to_lowercase();
+        self.known_tags().into_iter().filter(|t| t.to_lowercase().starts_with(&typed)).collect()
+    }
+
     fn recompute_filtered(&mut self) {
+        let pinned = self.selected_id();
+        self.refilter(pinned);
+    }
+
+    /// Rebuilds the filtered view, ordered by `list_options`, and keeps `pinned` selected wherever it moved to. If it is
+    /// gone or no longer passes the filter (e.g. just marked done under the Pending filter),
+    /// the selection stays at the same position, clamped to the list.
+    fn refilter(&mut self, pinned: Option<uuid::Uuid>) {
         self.filtered_indices.clear();
+        let now = Utc::now();
         for (i, e) in self.items.iter().enumerate() {
             let include = match self.filter {
                 Filter::All => true,
                 Filter::Pending => matches!(e.status, TodoStatus::Pending),
+                Filter::Waiting => matches!(e.status, TodoStatus::Waiting),
                 Filter::Done => matches!(e.status, TodoStatus::Done),
+                Filter::Overdue => e.is_overdue(now),
             };
-            if include { self.filtered_indices.push(i); }
+            let tagged = self.tag_filter.as_ref().is_none_or(|tag| e.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)));
+            if include && tagged { self.filtered_indices.push(i); }
         }
+        let items = &self.items;
+        match self.list_options.sort {
+            SortField::Title => self.filtered_indices.sort_by_cached_key(|&i| items[i].title.to_lowercase()),
+            SortField::Staleness => self.filtered_indices.sort_by_key(|&i| (items[i].status != TodoStatus::Pending, items[i].updated_at)),
+            SortField::Priority => self.filtered_indices.sort_by_key(|&i| (std::cmp::Reverse(items[i].priority), items[i].due_at.is_none(), items[i].due_at)),
+            SortField::CreatedAt | SortField::UpdatedAt => self.filtered_indices.sort_by_key(|&i| std::cmp::Reverse(items[i].created_at)),
+        }
+        if let Some(pos) = pinned.and_then(|id| self.filtered_indices.iter().position(|&i| self.items[i].id == id)) { self.selected = pos; }
         // Clamp selection within filtered bounds
         let len = self.filtered_indices.len();
         if len == 0 { self.selected = 0; self.list_state.select(None); }
@@ -90,9 +471,9 @@ impl<R: TodoRepository> App<R> {
     }
 }
 
-async fn run_app<R: TodoRepository>(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, service: TodoServiceImpl<R>) -> Result<()> {
+async fn run_app<R: TodoRepository + Clone>(terminal: &mut Terminal<CrosstermBackend<std::io::Stdout>>, service: TodoServiceImpl<R>, keys: KeyBindings, date_format: String, list_options: ListOptions, serving: Option<SocketAddr>) -> Result<()> {
     let tick_rate = Duration::from_millis(200);
-    let mut app = App { service, items: vec![], selected: 0, last_tick: Instant::now(), mode: Mode::View, list_state: ListState::default(), filter: Filter::All, filtered_indices: Vec::new(), field: ActiveField::Title, draft_title: String::new(), draft_desc: String::new() };
+    let mut app = App { service, items: vec![], selected: 0, last_tick: Instant::now(), mode: Mode::View, list_state: ListState::default(), filter: Filter::All, filtered_indices: Vec::new(), field: ActiveField::Title, draft_title: String::new(), draft_desc: String::new(), keys, message: None, last_error: None, layout: PaneLayout::TwoPane, date_format, list_options, tag_filter: None, draft_tag: String::new(), bulk: None, confirm_ids: Vec::new(), serving, last_load: Instant::now(), undo: Vec::new() };
     app.load().await?;
 
     loop {
@@ -106,142 +487,323 @@ async fn run_app<R: TodoRepository>(terminal: &mut Terminal<CrosstermBackend<std
                 ])
                 .split(f.size());
 
-            let header = Paragraph::new("Todos (Enter: toggle, n: new, e: edit, d: delete, f: filter, q: quit)  |  New/Edit: type title, Enter to save, Esc to cancel")
-                .block(Block::default().borders(Borders::ALL).title("api-tui"));
+            let k = app.keys;
+            let header = Paragraph::new(format!("Todos ({}: toggle, {}: new, {}: edit, {}: delete, {}: archive, {}: undo, {}: filter, {}: sort, {}: tag, {}: paste import, {}: complete shown, {}: delete shown, {}/{}: priority, {}: layout, {}: quit)  |  New/Edit: type title, Enter to save, Esc to cancel", key_label(k.toggle), key_label(k.new), key_label(k.edit), key_label(k.delete), key_label(k.archive), key_label(k.undo), key_label(k.filter), key_label(k.sort), key_label(k.tag), key_label(k.import), key_label(k.complete_all), key_label(k.delete_all), key_label(k.priority_up), key_label(k.priority_down), key_label(k.layout), key_label(k.quit)))
+                .block(Block::default().borders(Borders::ALL).title(match &app.tag_filter { Some(tag) => format!("api-tui — tag: {} (Esc clears)", tag), None => "api-tui".to_string() }));
             f.render_widget(header, chunks[0]);
 
+            // Narrow terminals fall back to two panes even when three are requested
+            let three_pane = app.layout == PaneLayout::ThreePane && chunks[1].width >= MIN_THREE_PANE_WIDTH;
             let middle = Layout::default()
                 .direction(Direction::Horizontal)
-                .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                .constraints(if three_pane {
+                    vec![Constraint::Percentage(45), Constraint::Percentage(30), Constraint::Percentage(25)]
+                } else {
+                    vec![Constraint::Percentage(60), Constraint::Percentage(40)]
+                })
                 .split(chunks[1]);
 
+            let now = Utc::now();
             let list_items: Vec<ListItem> = app.filtered_indices.iter().filter_map(|&idx| app.items.get(idx)).map(|e| {
-                let mark = match e.status { TodoStatus::Pending => "[ ]", TodoStatus::Done => "[x]" };
-                ListItem::new(format!("{} {}", mark, e.title))
+                let mark = match e.status { TodoStatus::Pending => "[ ]", TodoStatus::InProgress => "[~]", TodoStatus::Waiting => "[w]", TodoStatus::Done => "[x]", TodoStatus::Cancelled => "[-]", TodoStatus::Archived => "[a]" };
+                let tag = Span::styled(format!("[{}]", e.priority.as_str()), Style::default().fg(priority_color(e.priority)));
+                let item = ListItem::new(Line::from(vec![Span::raw(format!("{} ", mark)), tag, Span::raw(format!(" {}", e.title))]));
+                if e.is_overdue(now) { item.style(Style::default().fg(Color::Red)) } else { item }
             }).collect();
             // Keep list_state selection in sync with current index
             if app.filtered_indices.is_empty() { app.list_state.select(None); } else { app.list_state.select(Some(app.selected)); }
             let list = List::new(list_items)
-                .block(Block::default().borders(Borders::ALL).title(format!("items [{}] (highlighted = target for Enter/d/e)", match app.filter { Filter::All => "All", Filter::Pending => "Pending", Filter::Done => "Done" })))
+                .block(Block::default().borders(Borders::ALL).title(format!("items [{}] (highlighted = target for Enter/d/e)", match app.filter { Filter::All => "All", Filter::Pending => "Pending", Filter::Waiting => "Waiting", Filter::Done => "Done", Filter::Overdue => "Overdue" })))
                 .highlight_style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD | Modifier::REVERSED))
                 .highlight_symbol(">> ");
             f.render_stateful_widget(list, middle[0], &mut app.list_state);
 
             // Details pane for selected item (shows description)
-            let detail = if let Some(&idx) = app.filtered_indices.get(app.selected) {
-                if let Some(e) = app.items.get(idx) {
-                    let desc = e.description.clone().unwrap_or_else(|| "(no description)".to_string());
-                    format!("Title:\n{}\n\nStatus: {}\n\nDescription:\n{}", e.title, match e.status { TodoStatus::Pending => "Pending", TodoStatus::Done => "Done" }, desc)
-                } else { "".to_string() }
+            let detail = if let Some(e) = app.current_entry() {
+                let desc = e.description.clone().unwrap_or_else(|| "(no description)".to_string());
+                let fmt = |d: DateTime<Utc>| d.with_timezone(&Local).format(&app.date_format).to_string();
+                let waiting_on = match (&e.status, &e.waiting_on) {
+                    (TodoStatus::Waiting, Some(contact)) => format!("\nWaiting on: {}", contact),
+                    _ => String::new(),
+                };
+                let due = e.due_at.filter(|_| e.is_open()).map(|d| format!("\n{}", due_label(d, now))).unwrap_or_default();
+                format!("Title:\n{}\n\nStatus: {}{}\nPriority: {}{}\nCreated: {}\nUpdated: {}\n\nDescription:\n{}", e.title, e.status, waiting_on, e.priority.as_str(), due, fmt(e.created_at), fmt(e.updated_at), desc)
             } else { "".to_string() };
             let details = Paragraph::new(detail)
                 .block(Block::default().borders(Borders::ALL).title("details"));
             f.render_widget(details, middle[1]);
 
+            if three_pane {
+                let history = match app.current_entry() {
+                    Some(e) => {
+                        let fmt = |d: DateTime<Utc>| d.with_timezone(&Local).format(&app.date_format).to_string();
+                        let completed = e.completed_at.map(fmt).unwrap_or_else(|| "-".to_string());
+                        format!("Created:\n{}\n\nLast updated:\n{}\n\nCompleted:\n{}", fmt(e.created_at), fmt(e.updated_at), completed)
+                    }
+                    None => String::new(),
+                };
+                let history = Paragraph::new(history)
+                    .block(Block::default().borders(Borders::ALL).title("history"));
+                f.render_widget(history, middle[2]);
+            }
+
             let footer_text = match app.mode {
-                Mode::View => format!("DATABASE_URL={}  |  Filter=[{}]", std::env::var("DATABASE_URL").unwrap_or_default(), match app.filter { Filter::All => "All", Filter::Pending => "Pending", Filter::Done => "Done" }),
+                Mode::View => match app.last_error.as_ref().or(app.message.as_ref()) {
+                    Some(msg) => msg.clone(),
+                    None => format!("DATABASE_URL={}  |  Filter=[{}]  |  Sort=[{}]{}", std::env::var("DATABASE_URL").unwrap_or_default(), match app.filter { Filter::All => "All", Filter::Pending => "Pending", Filter::Waiting => "Waiting", Filter::Done => "Done", Filter::Overdue => "Overdue" }, sort_label(app.list_options), app.serving.map(|addr| format!("  |  API=http://{}", addr)).unwrap_or_default()),
+                },
                 Mode::Create => format!("Create — {}: {}_  |  (Tab to switch, Enter to save, Esc to cancel)", match app.field { ActiveField::Title => "Title", ActiveField::Description => "Desc" }, match app.field { ActiveField::Title => &app.draft_title, ActiveField::Description => &app.draft_desc }),
                 Mode::Edit => format!("Edit — {}: {}_  |  (Tab to switch, Enter to save, Esc to cancel)", match app.field { ActiveField::Title => "Title", ActiveField::Description => "Desc" }, match app.field { ActiveField::Title => &app.draft_title, ActiveField::Description => &app.draft_desc }),
+                Mode::ConfirmDelete(id) => format!("Delete '{}'? (y/n)", app.items.iter().find(|e| e.id == id).map(|e| e.title.as_str()).unwrap_or_default()),
+                Mode::ConfirmDeleteShown => format!("Delete all {} shown todo(s)? (y/n)", app.confirm_ids.len()),
+                Mode::TagFilter => {
+                    let completions = app.tag_completions();
+                    let hint = if completions.is_empty() { "no matching tags".to_string() } else { completions.join(", ") };
+                    format!("Tag: {}_  |  {}  |  (Tab to complete, Enter to apply, Esc to clear)", app.draft_tag, hint)
+                }
             };
-            let footer = Paragraph::new(footer_text)
-                .block(Block::default().borders(Borders::ALL).title(match app.mode { Mode::View => "info", Mode::Create => "create", Mode::Edit => "edit" }));
-            f.render_widget(footer, chunks[2]);
+            if let Some(job) = &app.bulk {
+                let done = job.done.load(Ordering::Relaxed).min(job.total);
+                let progress = Gauge::default()
+                    .block(Block::default().borders(Borders::ALL).title("working"))
+                    .gauge_style(Style::default().fg(Color::Cyan))
+                    .ratio(if job.total == 0 { 1.0 } else { done as f64 / job.total as f64 })
+                    .label(format!("{} {}/{}", job.label, done, job.total));
+                f.render_widget(progress, chunks[2]);
+            } else {
+                let footer_style = if app.mode == Mode::View && app.last_error.is_some() { Style::default().fg(Color::Red) } else { Style::default() };
+                let footer = Paragraph::new(footer_text)
+                    .style(footer_style)
+                    .block(Block::default().borders(Borders::ALL).title(match app.mode { Mode::View if app.last_error.is_some() => "error", Mode::View => "info", Mode::Create => "create", Mode::Edit => "edit", Mode::ConfirmDelete(_) | Mode::ConfirmDeleteShown => "delete", Mode::TagFilter => "tag" }));
+                f.render_widget(footer, chunks[2]);
+            }
         })?;
 
         let timeout = tick_rate.saturating_sub(app.last_tick.elapsed());
-        if event::poll(timeout)? {
-            if let Event::Key(key) = event::read()? {
-                // Only act on key presses; ignore repeats and releases to prevent duplicate input
-                if key.kind != KeyEventKind::Press { continue; }
-                match app.mode {
-                    Mode::View => match key.code {
-                        KeyCode::Char('q') => break,
-                        KeyCode::Up => { if app.selected > 0 { app.selected -= 1; } }
-                        KeyCode::Down => { let len = app.filtered_indices.len(); if app.selected + 1 < len { app.selected += 1; } }
-                        KeyCode::Enter => {
-                            if let Some(entry) = app.items.get(app.selected) {
-                                let new_status = match entry.status { TodoStatus::Pending => TodoStatus::Done, TodoStatus::Done => TodoStatus::Pending };
-                                let _ = app.service.update(api::domain::todo::TodoId(entry.id), api::domain::todo::UpdateTodo { title: None, description: None, status: Some(new_status) }).await;
-                                app.load().await?;
-                            }
+        if event::poll(timeout)? && let Event::Key(key) = event::read()? {
+            // Only act on key presses; ignore repeats and releases to prevent duplicate input
+            if key.kind != KeyEventKind::Press { continue; }
+            if app.mode == Mode::View { app.message = None; }
+            let k = app.keys;
+            let busy = app.bulk.is_some();
+            match app.mode {
+                Mode::View => match key.code {
+                    code if code == app.keys.quit => { if let Some(job) = app.bulk.take() { job.task.abort(); } break }
+                    // Navigation, filter and layout keep working during a bulk job; edits wait
+                    code if busy && [k.toggle, k.new, k.edit, k.delete, k.archive, k.undo, k.import, k.complete_all, k.delete_all, k.priority_up, k.priority_down].contains(&code) => {}
+                    KeyCode::Up if app.selected > 0 => { app.selected -= 1; }
+                    KeyCode::Down => { let len = app.filtered_indices.len(); if app.selected + 1 < len { app.selected += 1; } }
+                    code if code == app.keys.toggle => {
+                        if let Some(entry) = app.current_entry() {
+                            // Pending → InProgress → Done → Pending; Cancelled is only ever set explicitly
+                            let new_status = match entry.status {
+                                TodoStatus::Pending | TodoStatus::Waiting => TodoStatus::InProgress,
+                                TodoStatus::InProgress => TodoStatus::Done,
+                                TodoStatus::Done | TodoStatus::Cancelled | TodoStatus::Archived => TodoStatus::Pending,
+                            };
+                            let (id, previous) = (entry.id, entry.status.clone());
+                            let undo = if entry.recurring && new_status == TodoStatus::Done { Undo::Recurring { title: entry.title.clone() } } else { Undo::Status { id, previous } };
+                            let result = retry_transient(|| app.service.update(TodoId(id), UpdateTodo { status: Some(new_status.clone()), ..Default::default() })).await;
+                            if result.is_ok() { app.push_undo(undo); }
+                            app.settle("Update", result).await?;
                         }
-                        KeyCode::Char('n') => {
-                            app.mode = Mode::Create;
+                    }
+                    code if code == app.keys.new => {
+                        app.mode = Mode::Create;
+                        app.field = ActiveField::Title;
+                        app.draft_title.clear();
+                        app.draft_desc.clear();
+                    }
+                    code if code == app.keys.edit => {
+                        if let Some(entry) = app.current_entry() {
+                            let (title, desc) = (entry.title.clone(), entry.description.clone().unwrap_or_default());
+                            app.mode = Mode::Edit;
                             app.field = ActiveField::Title;
-                            app.draft_title.clear();
-                            app.draft_desc.clear();
+                            app.draft_title = title;
+                            app.draft_desc = desc;
                         }
-                        KeyCode::Char('e') => {
-                            if let Some(&idx) = app.filtered_indices.get(app.selected) {
-                                if let Some(entry) = app.items.get(idx) { 
-                                    app.mode = Mode::Edit; 
-                                    app.field = ActiveField::Title;
-                                    app.draft_title = entry.title.clone();
-                                    app.draft_desc = entry.description.clone().unwrap_or_default();
-                                }
-                            }
+                    }
+                    code if code == app.keys.delete => {
+                        if let Some(id) = app.selected_id() { app.mode = Mode::ConfirmDelete(id); }
+                    }
+                    code if code == app.keys.archive => {
+                        if let Some(entry) = app.current_entry() {
+                            let (id, previous, title) = (entry.id, entry.status.clone(), entry.title.clone());
+                            let result = retry_transient(|| app.service.update(TodoId(id), UpdateTodo { status: Some(TodoStatus::Archived), ..Default::default() })).await
+                                .and_then(|todo| todo.ok_or_else(|| anyhow!("todo is gone")));
+                            let archived = result.is_ok();
+                            if archived { app.push_undo(Undo::Status { id, previous }); }
+                            // Archived todos aren't loaded, so the reload drops it and clamps the selection
+                            app.settle("Archive", result).await?;
+                            if archived { app.message = Some(format!("Archived '{}'", title)); }
                         }
-                        KeyCode::Char('d') => {
-                            if let Some(&idx) = app.filtered_indices.get(app.selected) {
-                                if let Some(entry) = app.items.get(idx) {
-                                let _ = app.service.delete(api::domain::todo::TodoId(entry.id)).await;
-                                if app.selected > 0 { app.selected -= 1; }
-                                app.load().await?;
-                                }
+                    }
+                    code if code == app.keys.undo => app.undo_last().await?,
+                    code if code == app.keys.import => {
+                        match import_from_clipboard(&app.service) {
+                            Ok(job) => app.bulk = Some(job),
+                            Err(e) => app.last_error = Some(format!("Clipboard import failed: {}", e)),
+                        }
+                    }
+                    code if code == app.keys.complete_all => {
+                        let pending: Vec<TodoId> = app.filtered_indices.iter().filter_map(|&idx| app.items.get(idx))
+                            .filter(|e| e.status == TodoStatus::Pending).map(|e| TodoId(e.id)).collect();
+                        let service = app.service.clone();
+                        app.bulk = Some(BulkJob::spawn("Completing", pending, move |id| {
+                            let service = service.clone();
+                            async move {
+                                retry_transient(|| service.update(id.clone(), UpdateTodo { status: Some(TodoStatus::Done), ..Default::default() })).await?.ok_or_else(|| anyhow!("todo is gone"))?;
+                                Ok(())
+                            }
+                        }, |done, failed| if failed == 0 { format!("Completed {} todo(s)", done) } else { format!("Completed {} todo(s), {} failed", done, failed) }));
+                    }
+                    code if code == app.keys.delete_all => {
+                        app.confirm_ids = app.filtered_indices.iter().filter_map(|&idx| app.items.get(idx)).map(|e| e.id).collect();
+                        if app.confirm_ids.is_empty() { app.message = Some("Nothing to delete".into()); } else { app.mode = Mode::ConfirmDeleteShown; }
+                    }
+                    code if code == app.keys.priority_up || code == app.keys.priority_down => {
+                        if let Some(entry) = app.current_entry() {
+                            let priority = if code == app.keys.priority_up { entry.priority.raised() } else { entry.priority.lowered() };
+                            if priority == entry.priority {
+                                app.message = Some(format!("Priority already {}", priority.as_str()));
+                            } else {
+                                let result = retry_transient(|| app.service.update(TodoId(entry.id), UpdateTodo { priority: Some(priority), ..Default::default() })).await;
+                                let changed = result.is_ok();
+                                app.settle("Priority change", result).await?;
+                                if changed { app.message = Some(format!("Priority: {}", priority.as_str())); }
                             }
                         }
-                        KeyCode::Char('f') => {
-                            app.filter = match app.filter { Filter::All => Filter::Pending, Filter::Pending => Filter::Done, Filter::Done => Filter::All };
-                            app.recompute_filtered();
+                    }
+                    code if code == app.keys.layout => {
+                        app.layout = match app.layout { PaneLayout::TwoPane => PaneLayout::ThreePane, PaneLayout::ThreePane => PaneLayout::TwoPane };
+                    }
+                    code if code == app.keys.sort => {
+                        app.list_options = next_sort(app.list_options);
+                        app.recompute_filtered();
+                    }
+                    code if code == app.keys.filter => {
+                        app.filter = match app.filter { Filter::All => Filter::Pending, Filter::Pending => Filter::Waiting, Filter::Waiting => Filter::Done, Filter::Done => Filter::Overdue, Filter::Overdue => Filter::All };
+                        app.filter_changed();
+                    }
+                    code if code == app.keys.tag => {
+                        app.draft_tag = app.tag_filter.clone().unwrap_or_default();
+                        app.mode = Mode::TagFilter;
+                    }
+                    KeyCode::Esc if app.tag_filter.is_some() => {
+                        app.tag_filter = None;
+                        app.filter_changed();
+                    }
+                    _ => {}
+                },
+                // Enter with nothing typed also clears the filter
+                Mode::TagFilter => match key.code {
+                    KeyCode::Esc => { app.mode = Mode::View; app.draft_tag.clear(); app.tag_filter = None; app.filter_changed(); }
+                    KeyCode::Enter => {
+                        let tag = app.draft_tag.trim();
+                        app.tag_filter = (!tag.is_empty()).then(|| tag.to_string());
+                        app.mode = Mode::View;
+                        app.draft_tag.clear();
+                        app.filter_changed();
+                    }
+                    // Completes a partial tag; on a full one, steps to the next known tag (wrapping)
+                    KeyCode::Tab => {
+                        let known = app.known_tags();
+                        let next = match known.iter().position(|t| t.eq_ignore_ascii_case(app.draft_tag.trim())) {
+                            Some(pos) => known.get(pos + 1).or(known.first()).cloned(),
+                            None => app.tag_completions().into_iter().next(),
+                        };
+                        if let Some(tag) = next { app.draft_tag = tag; }
+                    }
+                    KeyCode::Backspace => { app.draft_tag.pop(); }
+                    KeyCode::Char(c) => app.draft_tag.push(c),
+                    _ => {}
+                },
+                Mode::Create => match key.code {
+                    KeyCode::Esc => { app.mode = Mode::View; app.draft_title.clear(); app.draft_desc.clear(); }
+                    KeyCode::Enter => {
+                        let title = app.draft_title.trim();
+                        let desc = app.draft_desc.trim();
+                        if !title.is_empty() {
+                            // A blank description counts as omitted so DEFAULT_DESCRIPTION applies
+                            let desc_opt = if desc.is_empty() { None } else { Some(Some(desc.to_string())) };
+                            let result = retry_transient(|| app.service.create(CreateTodo { title: title.to_string(), description: desc_opt.clone(), ..Default::default() })).await;
+                            app.settle("Create", result).await?;
                         }
-                        _ => {}
-                    },
-                    Mode::Create => match key.code {
-                        KeyCode::Esc => { app.mode = Mode::View; app.draft_title.clear(); app.draft_desc.clear(); }
-                        KeyCode::Enter => {
-                            let title = app.draft_title.trim();
-                            let desc = app.draft_desc.trim();
-                            if !title.is_empty() {
-                                let desc_opt = if desc.is_empty() { None } else { Some(desc.to_string()) };
-                                let _ = app.service.create(CreateTodo { title: title.to_string(), description: desc_opt }).await;
-                            }
-                            app.mode = Mode::View;
-                            app.draft_title.clear();
-                            app.draft_desc.clear();
-                            app.load().await?;
+                        app.mode = Mode::View;
+                        app.draft_title.clear();
+                        app.draft_desc.clear();
+                    }
+                    KeyCode::Backspace => { match app.field { ActiveField::Title => { app.draft_title.pop(); }, ActiveField::Description => { app.draft_desc.pop(); } } }
+                    KeyCode::Char(c) => { match app.field { ActiveField::Title => app.draft_title.push(c), ActiveField::Description => app.draft_desc.push(c) } }
+                    KeyCode::Tab => { app.field = match app.field { ActiveField::Title => ActiveField::Description, ActiveField::Description => ActiveField::Title }; }
+                    KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down => { /* ignore nav in input */ }
+                    _ => {}
+                },
+                Mode::Edit => match key.code {
+                    KeyCode::Esc => { app.mode = Mode::View; app.draft_title.clear(); app.draft_desc.clear(); }
+                    KeyCode::Enter => {
+                        if let Some(entry) = app.current_entry() {
+                            let before = Undo::Edit { id: entry.id, title: entry.title.clone(), description: entry.description.clone() };
+                            let title = app.draft_title.trim().to_string();
+                            let desc = app.draft_desc.trim().to_string();
+                            let title_opt = if title.is_empty() { None } else { Some(title) };
+                            // Emptying the description clears it
+                            let desc_opt = Some((!desc.is_empty()).then_some(desc));
+                            let result = retry_transient(|| app.service.update(TodoId(entry.id), UpdateTodo { title: title_opt.clone(), description: desc_opt.clone(), ..Default::default() })).await;
+                            if result.is_ok() { app.push_undo(before); }
+                            app.settle("Update", result).await?;
                         }
-                        KeyCode::Backspace => { match app.field { ActiveField::Title => { app.draft_title.pop(); }, ActiveField::Description => { app.draft_desc.pop(); } } }
-                        KeyCode::Char(c) => { match app.field { ActiveField::Title => app.draft_title.push(c), ActiveField::Description => app.draft_desc.push(c) } }
-                        KeyCode::Tab => { app.field = match app.field { ActiveField::Title => ActiveField::Description, ActiveField::Description => ActiveField::Title }; }
-                        KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down => { /* ignore nav in input */ }
-                        _ => {}
-                    },
-                    Mode::Edit => match key.code {
-                        KeyCode::Esc => { app.mode = Mode::View; app.draft_title.clear(); app.draft_desc.clear(); }
-                        KeyCode::Enter => {
-                            if let Some(&idx) = app.filtered_indices.get(app.selected) {
-                                if let Some(entry) = app.items.get(idx) {
-                                    let title = app.draft_title.trim().to_string();
-                                    let desc = app.draft_desc.trim().to_string();
-                                    let title_opt = if title.is_empty() { None } else { Some(title) };
-                                    let desc_opt = if desc.is_empty() { Some(String::new()) } else { Some(desc) };
-                                    let _ = app.service.update(api::domain::todo::TodoId(entry.id), api::domain::todo::UpdateTodo { title: title_opt, description: desc_opt, status: None }).await;
-                                }
+                        app.mode = Mode::View;
+                        app.draft_title.clear();
+                        app.draft_desc.clear();
+                    }
+                    KeyCode::Backspace => { match app.field { ActiveField::Title => { app.draft_title.pop(); }, ActiveField::Description => { app.draft_desc.pop(); } } }
+                    KeyCode::Char(c) => { match app.field { ActiveField::Title => app.draft_title.push(c), ActiveField::Description => app.draft_desc.push(c) } }
+                    KeyCode::Tab => { app.field = match app.field { ActiveField::Title => ActiveField::Description, ActiveField::Description => ActiveField::Title }; }
+                    KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down => { /* ignore nav in input */ }
+                    _ => {}
+                },
+                // Only `y` deletes; any other key backs out
+                Mode::ConfirmDelete(id) => {
+                    app.mode = Mode::View;
+                    if key.code == KeyCode::Char('y') {
+                        let title = app.items.iter().find(|e| e.id == id).map(|e| e.title.clone()).unwrap_or_default();
+                        let result = retry_transient(|| app.service.delete(TodoId(id))).await;
+                        if result.is_ok() && app.selected > 0 { app.selected -= 1; }
+                        if result.as_ref().is_ok_and(|deleted| *deleted) { app.push_undo(Undo::Delete { id, title }); }
+                        app.settle("Delete", result).await?;
+                    }
+                }
+                // Soft deletes like `d`, but not undoable here; they stay restorable through the API
+                Mode::ConfirmDeleteShown => {
+                    app.mode = Mode::View;
+                    let ids: Vec<TodoId> = std::mem::take(&mut app.confirm_ids).into_iter().map(TodoId).collect();
+                    if key.code == KeyCode::Char('y') {
+                        let service = app.service.clone();
+                        app.bulk = Some(BulkJob::spawn("Deleting", ids, move |id| {
+                            let service = service.clone();
+                            async move {
+                                if !retry_transient(|| service.delete(id.clone())).await? { bail!("todo is gone"); }
+                                Ok(())
                             }
-                            app.mode = Mode::View;
-                            app.draft_title.clear();
-                            app.draft_desc.clear();
-                            app.load().await?;
-                        }
-                        KeyCode::Backspace => { match app.field { ActiveField::Title => { app.draft_title.pop(); }, ActiveField::Description => { app.draft_desc.pop(); } } }
-                        KeyCode::Char(c) => { match app.field { ActiveField::Title => app.draft_title.push(c), ActiveField::Description => app.draft_desc.push(c) } }
-                        KeyCode::Tab => { app.field = match app.field { ActiveField::Title => ActiveField::Description, ActiveField::Description => ActiveField::Title }; }
-                        KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down => { /* ignore nav in input */ }
-                        _ => {}
-                    },
+                        }, |done, failed| if failed == 0 { format!("Deleted {} todo(s)", done) } else { format!("Deleted {} todo(s), {} failed", done, failed) }));
+                    }
                 }
             }
         }
+        if app.bulk.as_ref().is_some_and(|job| job.task.is_finished()) && let Some(job) = app.bulk.take() {
+            match job.task.await {
+                Ok((done, failed)) => app.message = Some((job.summary)(done, failed)),
+                Err(e) => app.last_error = Some(format!("{} stopped: {}", job.label, e)),
+            }
+            app.load().await?;
+        }
+        // Not while a prompt is open, so the todo it refers to stays put
+        if app.serving.is_some() && app.mode == Mode::View && app.bulk.is_none() && app.last_load.elapsed() >= SERVE_RELOAD_INTERVAL
+            && let Err(e) = app.load().await {
+            app.last_error = Some(format!("Reload failed: {}", e));
+            app.last_load = Instant::now();
+        }
         if app.last_tick.elapsed() >= tick_rate {
             app.last_tick = Instant::now();
         }
@@ -249,13 +811,60 @@ async fn run_app<R: TodoRepository>(terminal: &mut Terminal<CrosstermBackend<std
     Ok(())
 }
 
+const WRITE_ATTEMPTS: u32 = 3;
+
+/// Retries `write` while the database is locked by another process (e.g. the API server),
+/// on top of the busy timeout each attempt already waits; other errors return at once.
+async fn retry_transient<T, F, Fut>(mut write: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match write().await {
+            Err(e) if attempt < WRITE_ATTEMPTS && sqlite_repo::is_transient(&e) => {
+                tokio::time::sleep(Duration::from_millis(100 * u64::from(attempt))).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Starts creating one todo per non-blank clipboard line; fails only if the clipboard can't be read.
+fn import_from_clipboard<R: TodoRepository + Clone>(service: &TodoServiceImpl<R>) -> Result<BulkJob> {
+    let text = arboard::Clipboard::new().and_then(|mut c| c.get_text()).map_err(|e| anyhow!("clipboard unavailable ({})", e))?;
+    let service = service.clone();
+    Ok(BulkJob::spawn("Importing", clipboard_titles(&text), move |title| {
+        let service = service.clone();
+        async move { retry_transient(|| service.create(CreateTodo { title: title.clone(), ..Default::default() })).await.map(drop) }
+    }, |created, failed| if failed == 0 {
+        format!("Imported {} todo(s) from clipboard", created)
+    } else {
+        format!("Imported {} todo(s) from clipboard, {} failed", created, failed)
+    }))
+}
+
+/// Trims each line and drops common bullet markers so a pasted list becomes plain titles.
+fn clipboard_titles(text: &str) -> Vec<String> {
+    text.lines()
+        .map(|line| {
+            let line = line.trim();
+            ["- [ ] ", "- ", "* ", "• "].iter().find_map(|b| line.strip_prefix(b)).unwrap_or(line).trim()
+        })
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
 fn prepare_sqlite_file(database_url: &str) -> anyhow::Result<()> {
     if database_url.starts_with("sqlite::memory:") { return Ok(()); }
     if let Some(path) = database_url.strip_prefix("sqlite://") {
         let path = if cfg!(windows) && path.len() >= 3 && path.as_bytes()[0] == b'/' && path.as_bytes()[2] == b':' { &path[1..] } else { path };
         use std::{fs, path::Path, fs::OpenOptions};
         let p = Path::new(path);
-        if let Some(parent) = p.parent() { if !parent.as_os_str().is_empty() { fs::create_dir_all(parent)?; } }
+        if let Some(parent) = p.parent() && !parent.as_os_str().is_empty() { fs::create_dir_all(parent)?; }
         if !p.exists() { let _ = OpenOptions::new().create(true).append(true).open(p)?; }
     }
     Ok(())
@@ -0,0 +1,12 @@
+use serde::Serialize;
+
+use super::todo::{Todo, TodoId};
+
+/// A change the service has committed, published after the write succeeds.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum TodoEvent {
+    Created(Todo),
+    Updated(Todo),
+    Deleted(TodoId),
+}
@@ -1,12 +1,87 @@
 use async_trait::async_trait;
-use super::todo::{Todo, TodoId, CreateTodo, UpdateTodo};
+use chrono::{DateTime, Utc};
+use futures_util::stream::BoxStream;
+use super::todo::{ConflictPolicy, ImportReport, Inconsistency, ListOptions, Page, SortField, SortOrder, StatusCounts, Todo, TodoChange, TodoFilter, TodoId, TodoStatus, CreateTodo, UpdateTodo, UpdatedTodo, Workload};
 
 #[async_trait]
 pub trait TodoRepository: Send + Sync + 'static {
     async fn init(&self) -> anyhow::Result<()>;
+    /// Cheapest round trip to the backing store; fails when it can't be reached.
+    async fn ping(&self) -> anyhow::Result<()>;
+    /// Whether the backing store has every column the current code expects.
+    async fn is_schema_current(&self) -> anyhow::Result<bool>;
+    /// Rebuilds indexes and refreshes query-planner statistics; safe to run at any time.
+    async fn reindex(&self) -> anyhow::Result<()>;
+    /// Compacts the database file; slow on big databases and blocks writers while it runs.
+    async fn vacuum(&self) -> anyhow::Result<()>;
+    /// Permanently removes deleted todos; returns how many were removed.
+    async fn purge_deleted(&self) -> anyhow::Result<u64>;
+    /// Like `purge_deleted`, but only todos deleted before `cutoff`.
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> anyhow::Result<u64>;
+    /// Inserts todos as-is (ids, codes and timestamps included) in one transaction,
+    /// resolving taken ids according to `policy`.
+    async fn import_todos(&self, todos: Vec<Todo>, policy: ConflictPolicy) -> anyhow::Result<ImportReport>;
+    /// Todos whose status and timestamps contradict each other.
+    async fn find_inconsistencies(&self) -> anyhow::Result<Vec<Inconsistency>>;
+    /// Repairs what [`find_inconsistencies`](Self::find_inconsistencies) reports about
+    /// `completed_at`; a due date before creation is left alone. Returns rows changed.
+    async fn fix_inconsistencies(&self) -> anyhow::Result<u64>;
     async fn create(&self, input: CreateTodo) -> anyhow::Result<Todo>;
-    async fn get(&self, id: TodoId) -> anyhow::Result<Option<Todo>>;
-    async fn list(&self) -> anyhow::Result<Vec<Todo>>;
-    async fn update(&self, id: TodoId, input: UpdateTodo) -> anyhow::Result<Option<Todo>>;
+    /// Creates every todo or, if any insert fails, none of them; results keep the input order.
+    async fn create_many(&self, inputs: Vec<CreateTodo>) -> anyhow::Result<Vec<Todo>>;
+    /// Like every `owner` argument below, `Some` only considers todos created by that subject.
+    async fn get(&self, id: TodoId, owner: Option<&str>) -> anyhow::Result<Option<Todo>>;
+    /// Like `get`, but also returns a deleted todo (with `deleted_at` set).
+    async fn get_including_deleted(&self, id: TodoId, owner: Option<&str>) -> anyhow::Result<Option<Todo>>;
+    /// Looks a todo up by its short code, ignoring case.
+    async fn get_by_code(&self, code: &str, owner: Option<&str>) -> anyhow::Result<Option<Todo>>;
+    async fn get_many(&self, ids: Vec<TodoId>, owner: Option<&str>) -> anyhow::Result<Vec<Todo>>;
+    /// The todo changed most recently (archived ones included), or `None` when there are none.
+    async fn latest_updated(&self, owner: Option<&str>) -> anyhow::Result<Option<Todo>>;
+    /// Todos matching `filter`, ordered by `options` with `id` breaking ties.
+    async fn list(&self, filter: TodoFilter, options: ListOptions) -> anyhow::Result<Vec<Todo>>;
+    /// What `list` returns for todos tagged `tag` (ignoring case), newest first.
+    async fn list_by_tag(&self, tag: &str) -> anyhow::Result<Vec<Todo>> {
+        self.list(TodoFilter { tag: Some(tag.to_string()), ..Default::default() }, ListOptions::default()).await
+    }
+    /// Every todo that isn't deleted and matches `filter`, oldest first, yielded one row at a
+    /// time so arbitrarily large tables can be processed in bounded memory. Unlike `list`, a
+    /// filter without a status matches every status, archived included.
+    fn stream(&self, filter: TodoFilter) -> BoxStream<'_, anyhow::Result<Todo>>;
+    /// `limit` todos of what `list` returns, starting at `offset`, with the total number matching.
+    async fn list_paged(&self, filter: TodoFilter, options: ListOptions, limit: u32, offset: u32) -> anyhow::Result<Page<Todo>>;
+    async fn list_archived_paged(&self, sort: SortField, order: SortOrder, limit: u32, offset: u32, owner: Option<&str>) -> anyhow::Result<Vec<Todo>>;
+    /// Todos last updated before `cutoff`, least recently updated first; like `list`, archived
+    /// ones only when `status` asks for them.
+    async fn list_stale(&self, cutoff: DateTime<Utc>, status: Option<TodoStatus>, owner: Option<&str>) -> anyhow::Result<Vec<Todo>>;
+    /// Up to `limit` todos matching `filter` as [`stream`](Self::stream) does, with an id greater
+    /// than `after`, in id order. Keyset pages like this can be resumed from the last id without
+    /// skipping or repeating a todo, however the table changes in between.
+    async fn export_page(&self, filter: TodoFilter, after: Option<TodoId>, limit: u32) -> anyhow::Result<Vec<Todo>>;
+    /// Applies `input`; `None` if the todo is missing or deleted. What changed is appended to
+    /// the changelog in the same transaction, diffed against the row the update replaced.
+    /// Moving a recurring todo to `Done` also creates its next occurrence there, so the
+    /// transition is seen (and the copy made) exactly once however many updates race.
+    async fn update(&self, id: TodoId, input: UpdateTodo) -> anyhow::Result<Option<UpdatedTodo>>;
+    /// Adds one to `defer_count` in a single atomic write and returns the new value;
+    /// `None` if the todo is missing or deleted.
+    async fn increment_defer_count(&self, id: TodoId) -> anyhow::Result<Option<i64>>;
+    /// The todo's changelog, oldest first; empty for a todo that was never updated.
+    async fn list_changes(&self, id: TodoId) -> anyhow::Result<Vec<TodoChange>>;
+    /// Sets `due_at` to null without touching other fields, logging the change like `update`;
+    /// `None` if the todo is missing or deleted.
+    async fn clear_due(&self, id: TodoId) -> anyhow::Result<Option<Todo>>;
+    /// Clears `deleted_at`, bringing a deleted todo back; a live todo is returned unchanged.
+    /// `None` if there is no todo with that id, e.g. after a purge.
+    async fn restore(&self, id: TodoId) -> anyhow::Result<Option<Todo>>;
+    /// Removes the row for good, deleted or not; false if there was none.
+    async fn purge(&self, id: TodoId) -> anyhow::Result<bool>;
+    /// Marks the todo deleted, leaving a tombstone; false if it was missing or already deleted.
     async fn delete(&self, id: TodoId) -> anyhow::Result<bool>;
+    async fn workload(&self, status: Option<TodoStatus>, owner: Option<&str>) -> anyhow::Result<Workload>;
+    /// Todos per status, counted in one grouped query rather than by loading them.
+    async fn count_by_status(&self, owner: Option<&str>) -> anyhow::Result<StatusCounts>;
+    /// Completion counts grouped by whole weeks before `now` (0 = the last 7 days), for the last `weeks` weeks.
+    /// Weeks without completions may be omitted.
+    async fn completions_by_week(&self, now: DateTime<Utc>, weeks: u32, owner: Option<&str>) -> anyhow::Result<Vec<(u32, i64)>>;
 }
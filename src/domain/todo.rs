@@ -1,5 +1,5 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Duration, Months, Utc};
+use serde::{Deserialize, Deserializer, Serialize};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -9,8 +9,103 @@ impl Default for TodoId {
     fn default() -> Self { Self(Uuid::new_v4()) }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
-pub enum TodoStatus { Pending, Done }
+const CODE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CODE_LEN: usize = 4;
+
+/// Returns a random `TODO-XXXX` code (Crockford base32). Codes are short, so callers
+/// must handle collisions by generating another one.
+pub fn new_code() -> String {
+    let mut bits = Uuid::new_v4().as_u128();
+    let mut code = String::from("TODO-");
+    for _ in 0..CODE_LEN {
+        code.push(CODE_ALPHABET[(bits & 31) as usize] as char);
+        bits >>= 5;
+    }
+    code
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+/// `Waiting` todos are blocked on someone else (see `waiting_on`); `Cancelled` ones were dropped
+/// without being done. `Archived` todos are kept but left out of the default list.
+#[serde(rename_all = "snake_case")]
+#[schema(rename_all = "snake_case")]
+pub enum TodoStatus { Pending, InProgress, Waiting, Done, Cancelled, Archived }
+
+impl TodoStatus {
+    pub const ALL: [TodoStatus; 6] = [TodoStatus::Pending, TodoStatus::InProgress, TodoStatus::Waiting, TodoStatus::Done, TodoStatus::Cancelled, TodoStatus::Archived];
+
+    /// Lowercase (snake_case) name used in the API and the database.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TodoStatus::Pending => "pending",
+            TodoStatus::InProgress => "in_progress",
+            TodoStatus::Waiting => "waiting",
+            TodoStatus::Done => "done",
+            TodoStatus::Cancelled => "cancelled",
+            TodoStatus::Archived => "archived",
+        }
+    }
+}
+
+/// Display form for people, e.g. `status_label` in responses and the TUI details pane.
+impl std::fmt::Display for TodoStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            TodoStatus::Pending => "Pending",
+            TodoStatus::InProgress => "In progress",
+            TodoStatus::Waiting => "Waiting",
+            TodoStatus::Done => "Done",
+            TodoStatus::Cancelled => "Cancelled",
+            TodoStatus::Archived => "Archived",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority { Low, #[default] Medium, High }
+
+impl Priority {
+    pub const ALL: [Priority; 3] = [Priority::Low, Priority::Medium, Priority::High];
+
+    /// Lowercase name used in the API and the database.
+    pub fn as_str(&self) -> &'static str {
+        match self { Priority::Low => "low", Priority::Medium => "medium", Priority::High => "high" }
+    }
+
+    /// One level up, staying at `High`.
+    pub fn raised(self) -> Self {
+        match self { Priority::Low => Priority::Medium, Priority::Medium | Priority::High => Priority::High }
+    }
+
+    /// One level down, staying at `Low`.
+    pub fn lowered(self) -> Self {
+        match self { Priority::High => Priority::Medium, Priority::Medium | Priority::Low => Priority::Low }
+    }
+}
+
+/// How often a todo comes back: completing a recurring todo creates the next one.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Recurrence { Daily, Weekly, Monthly }
+
+impl Recurrence {
+    pub const ALL: [Recurrence; 3] = [Recurrence::Daily, Recurrence::Weekly, Recurrence::Monthly];
+
+    /// Lowercase name used in the API and the database.
+    pub fn as_str(&self) -> &'static str {
+        match self { Recurrence::Daily => "daily", Recurrence::Weekly => "weekly", Recurrence::Monthly => "monthly" }
+    }
+
+    /// `from` plus one interval; a monthly step from the 31st lands on the month's last day.
+    pub fn next(self, from: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Recurrence::Daily => from + Duration::days(1),
+            Recurrence::Weekly => from + Duration::weeks(1),
+            Recurrence::Monthly => from.checked_add_months(Months::new(1)).unwrap_or(from),
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct Todo {
@@ -18,19 +113,333 @@ pub struct Todo {
     pub title: String,
     pub description: Option<String>,
     pub status: TodoStatus,
+    /// Short human-friendly reference such as `TODO-1A2B`, unique per todo.
+    pub code: String,
+    pub estimate_minutes: Option<i64>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Set when the todo moves to `Done`, cleared when it leaves it.
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Set on delete; deleted todos are tombstones hidden from every read except
+    /// [`get_including_deleted`](crate::domain::repository::TodoRepository::get_including_deleted).
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Optional deadline.
+    pub due_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub priority: Priority,
+    /// How many times the todo was deferred (snoozed); only ever incremented.
+    #[serde(default)]
+    pub defer_count: i64,
+    /// Labels such as `work`, in the form [`normalize_tags`] leaves them.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    /// Who the todo is waiting on, e.g. a name or an email address; usually set with `Waiting`.
+    #[serde(default)]
+    pub waiting_on: Option<String>,
+    /// Subject of the bearer token that created it; `None` for todos made without auth.
+    #[serde(default)]
+    pub owner: Option<String>,
+}
+
+impl Todo {
+    /// The pending copy that completing a recurring todo creates: same title, description,
+    /// estimate, priority, tags and owner, due one interval after this one (or after `now` when
+    /// it had no due date). `None` when the todo doesn't recur.
+    pub fn next_occurrence(&self, now: DateTime<Utc>) -> Option<CreateTodo> {
+        let recurrence = self.recurrence?;
+        Some(CreateTodo {
+            title: self.title.clone(),
+            description: Some(self.description.clone()),
+            estimate_minutes: self.estimate_minutes,
+            due_at: Some(recurrence.next(self.due_at.unwrap_or(now))),
+            priority: Some(self.priority),
+            tags: self.tags.clone(),
+            recurrence: Some(recurrence),
+            waiting_on: None,
+            owner: self.owner.clone(),
+        })
+    }
+}
+
+/// What [`TodoRepository::update`](crate::domain::repository::TodoRepository::update) wrote.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdatedTodo {
+    pub todo: Todo,
+    /// The [`next_occurrence`](Todo::next_occurrence), created in the same transaction when the
+    /// update moved a recurring todo to `Done`.
+    pub next: Option<Todo>,
+}
+
+/// One update in a todo's changelog: each changed field maps to `{ "from": old, "to": new }`,
+/// values in their API form.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TodoChange {
+    pub changed_at: DateTime<Utc>,
+    pub fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl TodoChange {
+    /// The entry for a write that turned `before` into `after`, stamped with the new
+    /// `updated_at`; `None` when no tracked field changed.
+    pub fn between(before: &Todo, after: &Todo) -> Option<Self> {
+        let fields = changed_fields(before, after);
+        (!fields.is_empty()).then_some(Self { changed_at: after.updated_at, fields })
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// The fields that differ between two versions of a todo; `updated_at` is left out since it
+/// changes on every write.
+pub fn changed_fields(before: &Todo, after: &Todo) -> serde_json::Map<String, serde_json::Value> {
+    let tracked = |t: &Todo| [
+        ("title", serde_json::json!(t.title)),
+        ("description", serde_json::json!(t.description)),
+        ("status", serde_json::json!(t.status.as_str())),
+        ("estimate_minutes", serde_json::json!(t.estimate_minutes)),
+        ("due_at", serde_json::json!(t.due_at)),
+        ("priority", serde_json::json!(t.priority.as_str())),
+        ("tags", serde_json::json!(t.tags)),
+        ("recurrence", serde_json::json!(t.recurrence.map(|r| r.as_str()))),
+        ("waiting_on", serde_json::json!(t.waiting_on)),
+        ("completed_at", serde_json::json!(t.completed_at)),
+    ];
+    tracked(before).into_iter().zip(tracked(after))
+        .filter(|((_, from), (_, to))| from != to)
+        .map(|((field, from), (_, to))| (field.to_string(), serde_json::json!({ "from": from, "to": to })))
+        .collect()
+}
+
+/// Trims tags, drops empty ones and duplicates that differ only in case (the first spelling
+/// wins), and sorts the rest ignoring case.
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    let mut tags: Vec<String> = tags.into_iter()
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty() && seen.insert(t.to_lowercase()))
+        .collect();
+    tags.sort_by_key(|t| t.to_lowercase());
+    tags
+}
+
+/// For `Option<Option<T>>` fields marked `#[serde(default)]`: a missing key stays `None`,
+/// while an explicit `null` becomes `Some(None)`.
+pub fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Clone, Default, Deserialize, utoipa::ToSchema)]
 pub struct CreateTodo {
     pub title: String,
-    pub description: Option<String>,
+    /// `None` when the field was omitted, so the service can apply its default description.
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    pub description: Option<Option<String>>,
+    pub estimate_minutes: Option<i64>,
+    pub due_at: Option<DateTime<Utc>>,
+    /// [`Priority::Medium`] when omitted.
+    pub priority: Option<Priority>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub recurrence: Option<Recurrence>,
+    pub waiting_on: Option<String>,
+    /// Set by the HTTP layer from the authenticated subject, never from the body.
+    #[serde(skip)]
+    pub owner: Option<String>,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct UpdateTodo {
     pub title: Option<String>,
-    pub description: Option<String>,
+    /// `None` leaves the description as it is; `Some(None)` clears it.
+    #[serde(default, deserialize_with = "nullable")]
+    pub description: Option<Option<String>>,
     pub status: Option<TodoStatus>,
+    pub estimate_minutes: Option<i64>,
+    pub due_at: Option<DateTime<Utc>>,
+    pub priority: Option<Priority>,
+    /// `None` leaves the tags as they are; `Some` replaces all of them.
+    pub tags: Option<Vec<String>>,
+    /// `None` leaves the recurrence as it is; `Some(None)` stops the todo recurring.
+    #[serde(default, deserialize_with = "nullable")]
+    pub recurrence: Option<Option<Recurrence>>,
+    /// `None` leaves the contact as it is; `Some(None)` clears it.
+    #[serde(default, deserialize_with = "nullable")]
+    pub waiting_on: Option<Option<String>>,
+    /// When set, the update only applies if the stored `updated_at` is this instant (to the
+    /// microsecond) and fails with [`VersionMismatch`] otherwise.
+    #[serde(skip)]
+    pub if_updated_at: Option<DateTime<Utc>>,
+}
+
+impl UpdateTodo {
+    /// Whether a todo last updated at `updated_at` satisfies `if_updated_at`.
+    pub fn version_matches(&self, updated_at: DateTime<Utc>) -> bool {
+        self.if_updated_at.is_none_or(|expected| expected.timestamp_micros() == updated_at.timestamp_micros())
+    }
+}
+
+/// Input the service refuses to store; the HTTP layer answers 422 with the message.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct ValidationError(pub String);
+
+/// A conditional update found the todo changed since the caller read it; the HTTP layer
+/// answers 412.
+#[derive(Debug, thiserror::Error)]
+#[error("The todo was changed since it was read")]
+pub struct VersionMismatch;
+
+/// A write would give a todo the same `field` (`id` or `code`) as another one; the HTTP layer
+/// answers 409.
+#[derive(Debug, thiserror::Error)]
+#[error("another todo already has this {field}")]
+pub struct UniqueViolation { pub field: &'static str }
+
+/// The todo was deleted longer ago than the undo window allows; the HTTP layer answers 410.
+#[derive(Debug, thiserror::Error)]
+#[error("The undo window for this delete has passed")]
+pub struct UndoWindowExpired;
+
+/// Field a paged listing is ordered by. `Staleness` puts pending todos first and, with the
+/// default descending order, the longest-untouched (oldest `updated_at`) of them at the top.
+/// `Priority`, descending, puts high priority first and within a priority the soonest `due_at`,
+/// todos without one last; ascending is the exact reverse.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortField { UpdatedAt, #[default] CreatedAt, Title, Staleness, Priority }
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortOrder { Asc, #[default] Desc }
+
+/// How `list` and `list_paged` order their results; newest first by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ListOptions {
+    pub sort: SortField,
+    pub order: SortOrder,
+}
+
+/// Criteria for listing todos; `None` fields don't filter, except that
+/// archived todos are only included when `status` asks for them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TodoFilter {
+    pub status: Option<TodoStatus>,
+    /// Only todos completed at or after this instant.
+    pub completed_after: Option<DateTime<Utc>>,
+    /// Also list deleted todos (tombstones with `deleted_at` set).
+    pub include_deleted: bool,
+    /// Only todos carrying this tag, ignoring case.
+    pub tag: Option<String>,
+    /// Only todos created by this subject.
+    pub owner: Option<String>,
+}
+
+/// One page of a listing plus how many items match in total.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: u64,
+}
+
+/// Sum of estimates over a set of todos; `count` includes todos without an estimate.
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq)]
+pub struct Workload {
+    pub total_minutes: i64,
+    pub count: i64,
+}
+
+/// How many todos that aren't deleted are in each status.
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+pub struct StatusCounts {
+    pub pending: i64,
+    pub in_progress: i64,
+    pub waiting: i64,
+    pub done: i64,
+    pub cancelled: i64,
+    pub archived: i64,
+}
+
+impl StatusCounts {
+    /// Adds `count` todos with `status` to the tally.
+    pub fn add(&mut self, status: &TodoStatus, count: i64) {
+        match status {
+            TodoStatus::Pending => self.pending += count,
+            TodoStatus::InProgress => self.in_progress += count,
+            TodoStatus::Waiting => self.waiting += count,
+            TodoStatus::Done => self.done += count,
+            TodoStatus::Cancelled => self.cancelled += count,
+            TodoStatus::Archived => self.archived += count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Trend { Up, Down, Flat }
+
+/// Todos completed in the 7 days starting at `week_start`.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct WeekCompletions {
+    pub week_start: DateTime<Utc>,
+    pub completed: i64,
+}
+
+/// Completions per week, oldest first, with the latest week compared against the earlier ones.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Velocity {
+    pub weeks: Vec<WeekCompletions>,
+    pub trend: Trend,
+}
+
+/// A todo whose fields contradict each other.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct Inconsistency {
+    pub id: TodoId,
+    pub kind: InconsistencyKind,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum InconsistencyKind {
+    /// Done, but `completed_at` is missing.
+    DoneWithoutCompletedAt,
+    /// Not done, but `completed_at` is still set.
+    CompletedAtWhileNotDone,
+    /// Due before it was created; reported only, since there is no right date to pick.
+    DueBeforeCreated,
+}
+
+/// What an import does with a todo whose id is already taken.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictPolicy {
+    /// Keep the existing todo.
+    Skip,
+    /// Replace the existing todo with the imported one.
+    Overwrite,
+    /// Abort the whole import.
+    #[default]
+    Fail,
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "skip" => Ok(Self::Skip),
+            "overwrite" => Ok(Self::Overwrite),
+            "fail" => Ok(Self::Fail),
+            other => anyhow::bail!("unknown conflict policy {:?} (expected skip, overwrite or fail)", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, PartialEq, Eq)]
+pub struct ImportReport {
+    pub inserted: u64,
+    pub skipped: u64,
+    pub overwritten: u64,
 }
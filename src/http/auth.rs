@@ -0,0 +1,156 @@
+//! Optional password-less auth: a magic link carries a short-lived token that is
+//! exchanged for a bearer token, which [`protect`] then requires on every request.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use axum::{
+    extract::{Query, Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::Response,
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{Duration, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_ACCESS_TTL_SECS: i64 = 24 * 60 * 60;
+const LINK_TTL_MINUTES: i64 = 15;
+
+/// What a token may be used for; a magic-link token is not a bearer token.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Purpose { MagicLink, Access }
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    pub purpose: Purpose,
+    pub iat: i64,
+    pub exp: i64,
+}
+
+/// The authenticated caller, added to request extensions by [`protect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthSubject(pub String);
+
+/// Delivers magic links; swap in a mailer for real deployments.
+#[async_trait]
+pub trait MagicLinkSender: Send + Sync + 'static {
+    async fn send(&self, email: &str, link: &str) -> Result<()>;
+}
+
+/// Writes the link to the log, which is enough for a single-user setup.
+pub struct LogSender;
+
+#[async_trait]
+impl MagicLinkSender for LogSender {
+    async fn send(&self, email: &str, link: &str) -> Result<()> {
+        tracing::info!(%email, %link, "magic link issued");
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthConfig {
+    /// HMAC secret for signing tokens (HS256).
+    pub secret: String,
+    pub access_ttl: Duration,
+    pub link_ttl: Duration,
+    /// Prefix of the emailed link, e.g. `https://todos.example.com`.
+    pub base_url: String,
+}
+
+impl AuthConfig {
+    pub fn new(secret: impl Into<String>) -> Self {
+        Self {
+            secret: secret.into(),
+            access_ttl: Duration::seconds(DEFAULT_ACCESS_TTL_SECS),
+            link_ttl: Duration::minutes(LINK_TTL_MINUTES),
+            base_url: "http://127.0.0.1:3000".into(),
+        }
+    }
+
+    /// `None` unless `AUTH_JWT_SECRET` is set; also reads `AUTH_TOKEN_TTL_SECS` and `MAGIC_LINK_BASE_URL`.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(secret) = std::env::var("AUTH_JWT_SECRET").ok().filter(|s| !s.is_empty()) else { return Ok(None) };
+        let mut config = Self::new(secret);
+        if let Ok(v) = std::env::var("AUTH_TOKEN_TTL_SECS") {
+            let secs: i64 = v.trim().parse().with_context(|| format!("invalid AUTH_TOKEN_TTL_SECS {:?}", v))?;
+            config.access_ttl = Duration::seconds(secs);
+        }
+        if let Ok(url) = std::env::var("MAGIC_LINK_BASE_URL") { config.base_url = url.trim_end_matches('/').to_string(); }
+        Ok(Some(config))
+    }
+
+    pub fn issue(&self, subject: &str, purpose: Purpose) -> Result<String> {
+        let now = Utc::now();
+        let ttl = match purpose { Purpose::MagicLink => self.link_ttl, Purpose::Access => self.access_ttl };
+        let claims = Claims { sub: subject.to_string(), purpose, iat: now.timestamp(), exp: (now + ttl).timestamp() };
+        Ok(jsonwebtoken::encode(&Header::default(), &claims, &EncodingKey::from_secret(self.secret.as_bytes()))?)
+    }
+
+    /// Checks signature, expiry (no leeway) and that the token was issued for `purpose`.
+    pub fn verify(&self, token: &str, purpose: Purpose) -> Result<Claims> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.leeway = 0;
+        let data = jsonwebtoken::decode::<Claims>(token, &DecodingKey::from_secret(self.secret.as_bytes()), &validation)?;
+        anyhow::ensure!(data.claims.purpose == purpose, "token was issued for {:?}", data.claims.purpose);
+        Ok(data.claims)
+    }
+}
+
+#[derive(Clone)]
+struct AuthState { config: Arc<AuthConfig>, sender: Arc<dyn MagicLinkSender> }
+
+/// `POST /auth/magic-link` and `GET /auth/verify`.
+pub fn router(config: AuthConfig, sender: Arc<dyn MagicLinkSender>) -> Router {
+    Router::new()
+        .route("/auth/magic-link", post(request_link))
+        .route("/auth/verify", get(verify_link))
+        .with_state(AuthState { config: Arc::new(config), sender })
+}
+
+/// Requires a valid access token on every route of `router`.
+pub fn protect(router: Router, config: AuthConfig) -> Router {
+    router.route_layer(middleware::from_fn_with_state(Arc::new(config), require_bearer))
+}
+
+#[derive(Deserialize)]
+struct LinkRequest { email: String }
+
+async fn request_link(State(state): State<AuthState>, Json(payload): Json<LinkRequest>) -> Result<StatusCode, (StatusCode, String)> {
+    let email = payload.email.trim().to_lowercase();
+    if !email.contains('@') { return Err((StatusCode::BAD_REQUEST, "invalid email".into())); }
+    let token = state.config.issue(&email, Purpose::MagicLink).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let link = format!("{}/auth/verify?token={}", state.config.base_url, token);
+    state.sender.send(&email, &link).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(StatusCode::ACCEPTED)
+}
+
+#[derive(Deserialize)]
+struct VerifyQuery { token: String }
+
+async fn verify_link(State(state): State<AuthState>, Query(query): Query<VerifyQuery>) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let claims = state.config.verify(&query.token, Purpose::MagicLink).map_err(unauthorized)?;
+    let access_token = state.config.issue(&claims.sub, Purpose::Access).map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(serde_json::json!({ "access_token": access_token, "token_type": "Bearer", "expires_in": state.config.access_ttl.num_seconds() })))
+}
+
+async fn require_bearer(State(config): State<Arc<AuthConfig>>, mut req: Request, next: Next) -> Result<Response, (StatusCode, String)> {
+    let token = req.headers().get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| (StatusCode::UNAUTHORIZED, "Missing bearer token".to_string()))?;
+    let claims = config.verify(token, Purpose::Access).map_err(unauthorized)?;
+    req.extensions_mut().insert(AuthSubject(claims.sub));
+    Ok(next.run(req).await)
+}
+
+fn unauthorized(e: anyhow::Error) -> (StatusCode, String) {
+    tracing::debug!(error = %e, "rejected token");
+    (StatusCode::UNAUTHORIZED, "Invalid or expired token".into())
+}
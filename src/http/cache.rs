@@ -0,0 +1,56 @@
+//! `Cache-Control` hints for caches and CDNs in front of the API.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method},
+    middleware::{self, Next},
+    response::Response,
+    Router,
+};
+
+const NO_STORE: &str = "no-store";
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CacheConfig {
+    /// `max-age` for successful reads; `None` keeps every response `no-store`.
+    pub max_age_secs: Option<u32>,
+}
+
+impl CacheConfig {
+    /// Reads `CACHE_MAX_AGE_SECS`; unset or `0` means nothing may be cached.
+    pub fn from_env() -> Result<Self> {
+        let max_age_secs = match std::env::var("CACHE_MAX_AGE_SECS") {
+            Ok(v) => Some(v.trim().parse::<u32>().with_context(|| format!("invalid CACHE_MAX_AGE_SECS {:?}", v))?).filter(|&s| s > 0),
+            Err(_) => None,
+        };
+        Ok(Self { max_age_secs })
+    }
+
+    fn header_for(&self, method: &Method, response: &Response) -> HeaderValue {
+        match self.max_age_secs {
+            Some(secs) if (method == Method::GET || method == Method::HEAD) && response.status().is_success() => {
+                HeaderValue::from_str(&format!("max-age={}", secs)).expect("digits are a valid header value")
+            }
+            // Mutations and errors must never be replayed from a cache
+            _ => HeaderValue::from_static(NO_STORE),
+        }
+    }
+}
+
+/// Adds `Cache-Control` to every response of `router` that doesn't already set one.
+pub fn apply(router: Router, config: CacheConfig) -> Router {
+    router.layer(middleware::from_fn_with_state(Arc::new(config), set_cache_control))
+}
+
+async fn set_cache_control(State(config): State<Arc<CacheConfig>>, req: Request, next: Next) -> Response {
+    let method = req.method().clone();
+    let mut response = next.run(req).await;
+    if !response.headers().contains_key(header::CACHE_CONTROL) {
+        let value = config.header_for(&method, &response);
+        response.headers_mut().insert(header::CACHE_CONTROL, value);
+    }
+    response
+}
@@ -0,0 +1,37 @@
+//! Upstream deadlines: a caller's `X-Deadline` bounds how long the request may run.
+
+use std::time::Duration;
+
+use axum::{
+    extract::Request,
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use chrono::{DateTime, Utc};
+
+use crate::http::types::ApiError;
+
+pub const DEADLINE_HEADER: &str = "x-deadline";
+
+/// Runs each request of `router` under the deadline in its `X-Deadline` header (unix
+/// milliseconds). Past the deadline the handler future is dropped, which cancels any query in
+/// flight, and the caller gets 504. Requests without the header are left alone.
+pub fn apply(router: Router) -> Router {
+    router.layer(middleware::from_fn(enforce_deadline))
+}
+
+async fn enforce_deadline(req: Request, next: Next) -> Response {
+    let Some(value) = req.headers().get(DEADLINE_HEADER) else { return next.run(req).await };
+    let Some(deadline) = value.to_str().ok().and_then(|v| v.trim().parse::<i64>().ok()).and_then(DateTime::<Utc>::from_timestamp_millis) else {
+        return ApiError::bad_request("invalid X-Deadline; expected unix milliseconds").into_response();
+    };
+    let Ok(remaining) = (deadline - Utc::now()).to_std() else { return exceeded() };
+    if remaining == Duration::ZERO { return exceeded(); }
+    tokio::time::timeout(remaining, next.run(req)).await.unwrap_or_else(|_| exceeded())
+}
+
+fn exceeded() -> Response {
+    ApiError::new(StatusCode::GATEWAY_TIMEOUT, "Deadline exceeded").into_response()
+}
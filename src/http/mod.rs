@@ -1,2 +1,8 @@
+pub mod auth;
+pub mod cache;
+pub mod deadline;
+pub mod rate_limit;
+pub mod request_id;
 pub mod routing;
 pub mod types;
+pub mod write_queue;
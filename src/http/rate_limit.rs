@@ -0,0 +1,91 @@
+//! Per-caller request smoothing with a leaky bucket.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+
+use crate::http::{auth::AuthSubject, types::ApiError};
+
+const DEFAULT_BURST: u32 = 10;
+/// How often idle buckets are swept out of the map.
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitConfig {
+    /// Requests per second that drain from each bucket.
+    pub rate_per_sec: f64,
+    /// Requests a bucket holds before callers get 429.
+    pub burst: u32,
+}
+
+impl RateLimitConfig {
+    /// `None` unless `RATE_LIMIT_PER_SEC` is set; `RATE_LIMIT_BURST` defaults to 10.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(rate) = std::env::var("RATE_LIMIT_PER_SEC").ok().filter(|s| !s.trim().is_empty()) else { return Ok(None) };
+        let rate_per_sec: f64 = rate.trim().parse().ok().filter(|r: &f64| r.is_finite() && *r > 0.0)
+            .with_context(|| format!("invalid RATE_LIMIT_PER_SEC {:?}", rate))?;
+        let burst = match std::env::var("RATE_LIMIT_BURST") {
+            Ok(v) => v.trim().parse::<u32>().ok().filter(|&b| b > 0).with_context(|| format!("invalid RATE_LIMIT_BURST {:?}", v))?,
+            Err(_) => DEFAULT_BURST,
+        };
+        Ok(Some(Self { rate_per_sec, burst }))
+    }
+}
+
+struct Bucket { level: f64, last: Instant }
+
+struct Limiter {
+    config: RateLimitConfig,
+    state: Mutex<(HashMap<String, Bucket>, Instant)>,
+}
+
+impl Limiter {
+    /// Adds one request to `key`'s bucket, or says how long until there is room for it.
+    fn admit(&self, key: &str, now: Instant) -> Result<(), Duration> {
+        let mut guard = self.state.lock().unwrap();
+        let (buckets, last_sweep) = &mut *guard;
+        let rate = self.config.rate_per_sec;
+        if now.duration_since(*last_sweep) >= SWEEP_INTERVAL {
+            buckets.retain(|_, b| b.level - now.duration_since(b.last).as_secs_f64() * rate > 0.0);
+            *last_sweep = now;
+        }
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket { level: 0.0, last: now });
+        bucket.level = (bucket.level - now.duration_since(bucket.last).as_secs_f64() * rate).max(0.0);
+        bucket.last = now;
+        let overflow = bucket.level + 1.0 - f64::from(self.config.burst);
+        if overflow > 0.0 { return Err(Duration::from_secs_f64(overflow / rate)); }
+        bucket.level += 1.0;
+        Ok(())
+    }
+}
+
+/// Limits every route of `router` per [`AuthSubject`], so it must sit inside [`crate::http::auth::protect`];
+/// without auth all callers share one bucket.
+pub fn apply(router: Router, config: RateLimitConfig) -> Router {
+    let limiter = Limiter { config, state: Mutex::new((HashMap::new(), Instant::now())) };
+    router.layer(middleware::from_fn_with_state(Arc::new(limiter), limit))
+}
+
+async fn limit(State(limiter): State<Arc<Limiter>>, req: Request, next: Next) -> Response {
+    let key = req.extensions().get::<AuthSubject>().map(|s| s.0.as_str()).unwrap_or_default();
+    match limiter.admit(key, Instant::now()) {
+        Ok(()) => next.run(req).await,
+        Err(wait) => {
+            let mut response = ApiError::new(StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded").into_response();
+            let secs = wait.as_secs_f64().ceil().max(1.0) as u64;
+            response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(secs));
+            response
+        }
+    }
+}
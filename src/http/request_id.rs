@@ -0,0 +1,33 @@
+//! Correlation ids: every request runs in a `request` span carrying its `X-Request-Id`.
+
+use axum::{
+    extract::Request,
+    http::HeaderValue,
+    middleware::{self, Next},
+    response::Response,
+    Router,
+};
+use tracing::Instrument;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Longer caller-supplied ids are replaced rather than logged.
+const MAX_LEN: usize = 128;
+
+/// Keeps the caller's `X-Request-Id` (or makes up a UUID when it is missing or unusable), runs
+/// the request inside a span with that id, and echoes it on the response, so any log line
+/// written while handling the request can be matched to it.
+pub fn apply(router: Router) -> Router {
+    router.layer(middleware::from_fn(with_request_id))
+}
+
+async fn with_request_id(mut req: Request, next: Next) -> Response {
+    let id = req.headers().get(REQUEST_ID_HEADER)
+        .filter(|v| !v.is_empty() && v.len() <= MAX_LEN && v.as_bytes().iter().all(u8::is_ascii_graphic))
+        .cloned()
+        .unwrap_or_else(|| HeaderValue::from_str(&uuid::Uuid::new_v4().to_string()).expect("a UUID is a valid header value"));
+    req.headers_mut().insert(REQUEST_ID_HEADER, id.clone());
+    let span = tracing::info_span!("request", id = id.to_str().unwrap_or_default(), method = %req.method(), path = req.uri().path());
+    let mut response = next.run(req).instrument(span).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER, id);
+    response
+}
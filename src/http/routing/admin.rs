@@ -0,0 +1,49 @@
+use std::time::Instant;
+
+use axum::{extract::State, http::{HeaderMap, StatusCode}, routing::{get, post}, Json, Router};
+
+use crate::domain::repository::TodoRepository;
+
+#[derive(Clone)]
+struct AdminState<R> { repo: R, admin_key: String }
+
+/// Maintenance endpoints; every request must carry `X-Admin-Key` matching `admin_key`.
+pub fn router<R: TodoRepository + Clone>(repo: R, admin_key: String) -> Router {
+    Router::new()
+        .route("/admin/reindex", post(reindex::<R>))
+        .route("/admin/check", get(check::<R>).post(fix::<R>))
+        .with_state(AdminState { repo, admin_key })
+}
+
+async fn reindex<R: TodoRepository>(State(state): State<AdminState<R>>, headers: HeaderMap) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    authorize(&headers, &state.admin_key)?;
+    let started = Instant::now();
+    state.repo.reindex().await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+    tracing::info!(elapsed_ms, "reindex finished");
+    Ok(Json(serde_json::json!({ "status": "ok", "elapsed_ms": elapsed_ms })))
+}
+
+/// Reports todos whose status and `completed_at` disagree, or that are due before they were
+/// created, without changing anything.
+async fn check<R: TodoRepository>(State(state): State<AdminState<R>>, headers: HeaderMap) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    authorize(&headers, &state.admin_key)?;
+    let found = state.repo.find_inconsistencies().await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(serde_json::json!({ "count": found.len(), "items": found })))
+}
+
+/// Corrects what `check` reports and returns how many todos changed.
+async fn fix<R: TodoRepository>(State(state): State<AdminState<R>>, headers: HeaderMap) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    authorize(&headers, &state.admin_key)?;
+    let fixed = state.repo.fix_inconsistencies().await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    tracing::info!(fixed, "consistency fix finished");
+    Ok(Json(serde_json::json!({ "fixed": fixed })))
+}
+
+fn authorize(headers: &HeaderMap, admin_key: &str) -> Result<(), (StatusCode, String)> {
+    let given = headers.get("x-admin-key").and_then(|v| v.to_str().ok()).unwrap_or_default();
+    // Compare every byte so the response time doesn't leak how much of the key matched
+    let matches = given.len() == admin_key.len()
+        && given.bytes().zip(admin_key.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0;
+    if matches { Ok(()) } else { Err((StatusCode::UNAUTHORIZED, "Invalid admin key".into())) }
+}
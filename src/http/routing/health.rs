@@ -0,0 +1,35 @@
+use axum::{extract::State, http::StatusCode, routing::get, Json, Router};
+
+use crate::domain::repository::TodoRepository;
+
+/// `/health` pings the database, so a load balancer stops routing to an instance that lost it.
+/// Liveness (`/health/live`) only says the process is up; readiness (`/health/ready`)
+/// also requires the database schema to match what the code expects.
+pub fn router<R: TodoRepository + Clone>(repo: R) -> Router {
+    Router::new()
+        .route("/health", get(health::<R>))
+        .route("/health/live", get(|| async { Json(serde_json::json!({ "status": "ok" })) }))
+        .route("/health/ready", get(ready::<R>))
+        .with_state(repo)
+}
+
+async fn health<R: TodoRepository>(State(repo): State<R>) -> (StatusCode, Json<serde_json::Value>) {
+    match repo.ping().await {
+        Ok(()) => (StatusCode::OK, Json(serde_json::json!({ "status": "ok" }))),
+        Err(e) => {
+            tracing::warn!(error = %e, "database ping failed");
+            (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({ "status": "unavailable" })))
+        }
+    }
+}
+
+async fn ready<R: TodoRepository>(State(repo): State<R>) -> (StatusCode, Json<serde_json::Value>) {
+    match repo.is_schema_current().await {
+        Ok(true) => (StatusCode::OK, Json(serde_json::json!({ "status": "ready" }))),
+        Ok(false) => (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({ "status": "schema_outdated" }))),
+        Err(e) => {
+            tracing::warn!(error = %e, "readiness check failed");
+            (StatusCode::SERVICE_UNAVAILABLE, Json(serde_json::json!({ "status": "unavailable" })))
+        }
+    }
+}
@@ -1,9 +1,135 @@
+pub mod admin;
+pub mod health;
+pub mod openapi;
+pub mod schema;
 pub mod todos;
+#[cfg(test)]
+mod todos_tests;
 
-use axum::{routing::get, Router};
+use std::time::Instant;
 
+use anyhow::Context;
+use axum::{
+    extract::{MatchedPath, Request},
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware::{self, Next},
+    response::Response,
+    routing::get,
+    Router,
+};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+use tower_http::{cors::{AllowOrigin, CorsLayer}, decompression::RequestDecompressionLayer};
+
+use crate::{
+    application::todo_service::TodoServiceImpl,
+    domain::repository::TodoRepository,
+    http::{auth::{self, AuthConfig, LogSender}, cache::{self, CacheConfig}, deadline, rate_limit::{self, RateLimitConfig}, request_id, write_queue::{self, WriteQueueConfig}},
+};
+
+const REQUESTS_TOTAL: &str = "http_requests_total";
+const REQUEST_DURATION: &str = "http_request_duration_seconds";
+/// Latency buckets in seconds, from a cache-hot read up to a slow batch.
+const DURATION_BUCKETS: [f64; 10] = [0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0];
+
+/// Wraps `router` in the layers every route shares; `/health*` comes from [`health::router`].
 pub fn app(router: Router) -> Router {
-    Router::new()
-        .route("/health", get(|| async { "ok" }))
-        .merge(router)
+    // Inside the metrics layer, so requests cut off by their deadline are counted as 504s
+    let router = deadline::apply(router)
+        .layer(middleware::from_fn(track_metrics))
+        // Transparently inflate `Content-Encoding: gzip` bodies; other encodings get 415
+        .layer(RequestDecompressionLayer::new());
+    // Outermost, so even a rejected body is logged and answered with its request id
+    request_id::apply(router)
+}
+
+/// The whole API for `service`, with the cache, write queue, rate limit, auth, admin and CORS layers
+/// configured from the environment. `/metrics` is only served when given a `metrics` handle,
+/// since the recorder behind it can be installed once per process.
+pub fn api<R: TodoRepository + Clone>(repo: R, service: TodoServiceImpl<R>, metrics: Option<PrometheusHandle>) -> anyhow::Result<Router> {
+    let health_router = health::router(repo.clone());
+    let todos_router = cache::apply(todos::router(todos::AppState { service }), CacheConfig::from_env()?);
+    // Inside the rate limit and auth, so rejected requests never take a place in the queue
+    let todos_router = match WriteQueueConfig::from_env()? {
+        Some(queue) => write_queue::apply(todos_router, queue),
+        None => todos_router,
+    };
+    let todos_router = match RateLimitConfig::from_env()? {
+        Some(limits) => rate_limit::apply(todos_router, limits),
+        None => todos_router,
+    };
+    let todos_router = match AuthConfig::from_env()? {
+        Some(auth_config) => auth::protect(todos_router, auth_config.clone()).merge(auth::router(auth_config, std::sync::Arc::new(LogSender))),
+        None => todos_router,
+    };
+    let mut router = todos_router.merge(health_router).merge(schema::router()).merge(openapi::router());
+    if let Some(handle) = metrics { router = router.merge(metrics_router(handle)); }
+    match std::env::var("ADMIN_KEY") {
+        Ok(key) if !key.is_empty() => router = router.merge(admin::router(repo, key)),
+        _ => tracing::info!("ADMIN_KEY not set; admin endpoints disabled"),
+    }
+    Ok(match CorsOrigins::from_env()? {
+        Some(origins) => with_cors(app(router), origins),
+        None => app(router),
+    })
+}
+
+/// Browser origins allowed to call the API.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CorsOrigins {
+    Any,
+    List(Vec<HeaderValue>),
+}
+
+impl CorsOrigins {
+    /// `ALLOWED_ORIGINS`: `*` or a comma-separated list such as `https://app.example.com,http://localhost:5173`.
+    /// Unset means any origin in debug builds and no CORS at all (`None`) in release builds.
+    pub fn from_env() -> anyhow::Result<Option<Self>> {
+        let Ok(value) = std::env::var("ALLOWED_ORIGINS") else { return Ok(cfg!(debug_assertions).then_some(Self::Any)) };
+        if value.trim() == "*" { return Ok(Some(Self::Any)); }
+        let origins = value.split(',').map(str::trim).filter(|o| !o.is_empty())
+            .map(|o| HeaderValue::from_str(o).with_context(|| format!("invalid origin {:?} in ALLOWED_ORIGINS", o)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Some(Self::List(origins)))
+    }
+}
+
+/// Answers CORS preflights and adds the CORS headers for `origins`. Goes around [`app`] so
+/// preflights never reach auth or the handlers.
+pub fn with_cors(router: Router, origins: CorsOrigins) -> Router {
+    let allow_origin = match origins { CorsOrigins::Any => AllowOrigin::any(), CorsOrigins::List(list) => AllowOrigin::list(list) };
+    router.layer(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::HEAD, Method::POST, Method::PUT, Method::PATCH, Method::DELETE])
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE, header::CONTENT_ENCODING, header::RANGE, header::IF_MATCH, HeaderName::from_static(deadline::DEADLINE_HEADER), HeaderName::from_static(request_id::REQUEST_ID_HEADER)])
+            .expose_headers([header::CONTENT_RANGE, header::ETAG, header::RETRY_AFTER, HeaderName::from_static(request_id::REQUEST_ID_HEADER)]),
+    )
+}
+
+/// Installs the global Prometheus recorder; until this runs, [`app`]'s metrics go nowhere.
+pub fn install_metrics() -> anyhow::Result<PrometheusHandle> {
+    Ok(PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Full(REQUEST_DURATION.to_string()), &DURATION_BUCKETS)?
+        .install_recorder()?)
+}
+
+/// `GET /metrics` in the Prometheus text format.
+pub fn metrics_router(handle: PrometheusHandle) -> Router {
+    Router::new().route("/metrics", get(move || std::future::ready(handle.render())))
+}
+
+/// Counts every request by method, route template and status, and records latency for all but
+/// the health and metrics routes, which are polled often enough to drown out real traffic.
+async fn track_metrics(req: Request, next: Next) -> Response {
+    // The template (`/todos/:id`), not the raw path, so ids don't explode the label set
+    let path = req.extensions().get::<MatchedPath>().map_or("unmatched", MatchedPath::as_str).to_string();
+    let method = req.method().to_string();
+    let start = Instant::now();
+    let response = next.run(req).await;
+    let status = response.status().as_u16().to_string();
+    if !(path == "/metrics" || path == "/health" || path.starts_with("/health/")) {
+        metrics::histogram!(REQUEST_DURATION, "method" => method.clone(), "path" => path.clone()).record(start.elapsed().as_secs_f64());
+    }
+    metrics::counter!(REQUESTS_TOTAL, "method" => method, "path" => path, "status" => status).increment(1);
+    response
 }
@@ -0,0 +1,83 @@
+use axum::{response::Html, routing::get, Json, Router};
+use chrono::{DateTime, Utc};
+use utoipa::{OpenApi, ToSchema};
+
+use crate::{
+    domain::todo::{CreateTodo, Priority, Recurrence, TodoStatus},
+    http::{routing::todos, types::ApiError},
+};
+
+/// The spec for the core todo operations; schemas come from the same types the handlers use.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "gpt5-todo API"),
+    paths(todos::create_todo, todos::list_todos, todos::get_todo, todos::update_todo, todos::delete_todo),
+    components(schemas(Todo, TodoPage, CreateTodo, todos::UpdateBody, TodoStatus, Priority, Recurrence, ApiError)),
+    tags((name = "todos", description = "Create, read, update and delete todos")),
+)]
+struct ApiDoc;
+
+/// `GET /openapi.json` and a Swagger UI for it at `GET /docs`.
+pub fn router() -> Router {
+    Router::new()
+        .route("/openapi.json", get(|| async { Json(ApiDoc::openapi()) }))
+        .route("/docs", get(|| async { Html(SWAGGER_UI) }))
+}
+
+/// Loads Swagger UI from a CDN rather than bundling its assets into the binary.
+const SWAGGER_UI: &str = r##"<!doctype html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>gpt5-todo API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+  <script>window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });</script>
+</body>
+</html>
+"##;
+
+/// A todo as every endpoint returns it; the fields must match `todos::todo_json`. The handler
+/// annotations refer to it (and [`TodoPage`]) by schema name, `body = Todo`.
+#[derive(ToSchema)]
+#[allow(dead_code)] // only describes the JSON
+struct Todo {
+    id: uuid::Uuid,
+    title: String,
+    description: Option<String>,
+    status: TodoStatus,
+    /// `status` for display, e.g. `Pending`.
+    status_label: String,
+    /// Short reference such as `TODO-1A2B`.
+    code: String,
+    estimate_minutes: Option<i64>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    completed_at: Option<DateTime<Utc>>,
+    deleted_at: Option<DateTime<Utc>>,
+    due_at: Option<DateTime<Utc>>,
+    priority: Priority,
+    defer_count: i64,
+    tags: Vec<String>,
+    recurrence: Option<Recurrence>,
+    /// Who a `waiting` todo is blocked on.
+    waiting_on: Option<String>,
+}
+
+/// One page of `GET /todos`.
+#[derive(ToSchema)]
+#[allow(dead_code)] // only describes the JSON
+struct TodoPage {
+    items: Vec<Todo>,
+    /// Todos matching the filter across all pages.
+    total: u64,
+    limit: u32,
+    offset: u32,
+    /// Only without `limit` and `offset`: whether todos past the soft limit were left out.
+    truncated: Option<bool>,
+    /// With `truncated`, the `offset` to ask for next.
+    next: Option<u32>,
+}
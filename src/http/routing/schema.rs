@@ -0,0 +1,32 @@
+use axum::{routing::get, Json, Router};
+use serde_json::json;
+
+use crate::{application::todo_service::{MAX_DESCRIPTION_CHARS, MAX_TITLE_CHARS}, domain::todo::{Priority, Recurrence, TodoStatus}};
+
+/// `GET /schema`: allowed values and field constraints, so clients can build forms
+/// without hardcoding them.
+pub fn router() -> Router {
+    Router::new().route("/schema", get(|| async { Json(schema()) }))
+}
+
+/// Enumerations come from the domain types, so a new variant shows up here automatically.
+fn schema() -> serde_json::Value {
+    json!({
+        "statuses": TodoStatus::ALL.iter().map(TodoStatus::as_str).collect::<Vec<_>>(),
+        "priorities": Priority::ALL.iter().map(Priority::as_str).collect::<Vec<_>>(),
+        "recurrences": Recurrence::ALL.iter().map(Recurrence::as_str).collect::<Vec<_>>(),
+        "fields": {
+            "title": { "type": "string", "required": true, "min_length": 1, "max_length": MAX_TITLE_CHARS },
+            "description": { "type": "string", "nullable": true, "max_length": MAX_DESCRIPTION_CHARS },
+            "status": { "type": "string", "enum": "statuses" },
+            "status_label": { "type": "string", "read_only": true },
+            "estimate_minutes": { "type": "integer", "nullable": true, "minimum": 0 },
+            "due_at": { "type": "string", "format": "date-time", "nullable": true },
+            "priority": { "type": "string", "enum": "priorities", "default": Priority::default().as_str() },
+            "defer_count": { "type": "integer", "minimum": 0, "read_only": true },
+            "tags": { "type": "array", "items": { "type": "string" }, "default": [] },
+            "recurrence": { "type": "string", "enum": "recurrences", "nullable": true },
+            "waiting_on": { "type": "string", "nullable": true },
+        },
+    })
+}
@@ -1,8 +1,19 @@
-use axum::{extract::State, routing::{get, post}, Router, Json};
-use axum::http::StatusCode;
+use axum::{extract::{FromRequestParts, Query, State}, routing::{delete, get, post}, Router, Json};
+use axum::http::{header, request::Parts, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
 use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
 
-use crate::{application::todo_service::TodoService, domain::todo::{CreateTodo, TodoId, UpdateTodo}};
+use crate::http::{auth::AuthSubject, types::ApiError};
+use crate::{application::todo_service::TodoService, domain::todo::{ConflictPolicy, CreateTodo, ListOptions, Priority, Recurrence, SortField, SortOrder, TodoFilter, TodoId, TodoStatus, UniqueViolation, UpdateTodo}};
+
+const DEFAULT_PAGE_LIMIT: u32 = 50;
+const MAX_PAGE_LIMIT: u32 = 200;
+const MAX_LIST_LIMIT: u32 = 500;
+const DEFAULT_EXPORT_LIMIT: u32 = 500;
+const MAX_EXPORT_LIMIT: u32 = 5000;
+const DEFAULT_VELOCITY_WEEKS: u32 = 4;
+const MAX_VELOCITY_WEEKS: u32 = 52;
 
 #[derive(Clone)]
 pub struct AppState<S: TodoService> { pub service: S }
@@ -10,62 +21,705 @@ pub struct AppState<S: TodoService> { pub service: S }
 pub fn router<S: TodoService + Clone + Send + Sync + 'static>(state: AppState<S>) -> Router {
     Router::new()
         .route("/todos", post(create_todo::<S>).get(list_todos::<S>))
-        .route("/todos/:id", get(get_todo::<S>).put(update_todo::<S>).delete(delete_todo::<S>))
+        .route("/todos/bulk", post(create_todos_bulk::<S>))
+        .route("/todos/archived", get(list_archived::<S>))
+        .route("/todos/query", post(query_todos::<S>))
+        .route("/todos/workload", get(workload::<S>))
+        .route("/todos/stats", get(todo_stats::<S>))
+        .route("/todos/latest", get(latest_todo::<S>))
+        .route("/todos/stale", get(stale_todos::<S>))
+        .route("/todos/export", get(export_todos::<S>))
+        .route("/todos/export.csv", get(export_csv::<S>))
+        .route("/todos/import", post(import_todos::<S>))
+        .route("/stats/velocity", get(velocity::<S>))
+        .route("/todos/code/:code", get(get_todo_by_code::<S>))
+        .route("/todos/:id/defer", post(defer_todo::<S>))
+        .route("/todos/:id/restore", post(restore_todo::<S>))
+        .route("/todos/:id/undo-delete", post(undo_delete::<S>))
+        .route("/todos/:id/due", delete(clear_due::<S>))
+        .route("/todos/:id/changes", get(todo_changes::<S>))
+        .route("/todos/:id", get(get_todo::<S>).put(update_todo::<S>).patch(patch_todo::<S>).delete(delete_todo::<S>))
         .with_state(state)
 }
 
-async fn create_todo<S: TodoService>(State(state): State<AppState<S>>, Json(payload): Json<CreateTodo>) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    let todo = state.service.create(payload).await.map_err(internal_error)?;
-    Ok(Json(serde_json::json!({ "id": todo.id.0, "title": todo.title, "description": todo.description, "status": format_status(&todo), "created_at": todo.created_at, "updated_at": todo.updated_at })))
+/// The subject a request acts for, from [`crate::http::auth::protect`]; `None` when auth is off,
+/// which leaves every todo visible. Todos created without auth have no owner, so with auth on
+/// nobody sees them.
+struct Owner(Option<String>);
+
+impl Owner {
+    fn allows(&self, t: &crate::domain::todo::Todo) -> bool { self.0.is_none() || t.owner == self.0 }
+}
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Owner {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(Self(parts.extensions.get::<AuthSubject>().map(|s| s.0.clone())))
+    }
+}
+
+/// 404 unless the owner can see the todo (deleted or not), so someone else's doesn't leak.
+/// Owners never change, so checking ahead of the write is enough.
+async fn check_owner<S: TodoService>(service: &S, id: &TodoId, owner: &Owner) -> Result<(), ApiError> {
+    if owner.0.is_none() { return Ok(()); }
+    match service.get_including_deleted(id.clone(), owner.0.as_deref()).await.map_err(internal_error)? {
+        Some(_) => Ok(()),
+        None => Err(ApiError::not_found()),
+    }
+}
+
+/// `priority` and `recurrence` are taken as strings so an unknown value is a 400 like `status`,
+/// not a JSON error.
+#[derive(Deserialize)]
+struct CreateBody { #[serde(flatten)] todo: CreateTodo, priority: Option<String>, recurrence: Option<String> }
+
+#[utoipa::path(post, path = "/todos", tag = "todos", request_body = CreateTodo, responses(
+    (status = 200, description = "The created todo", body = Todo),
+    (status = 400, description = "Invalid priority, recurrence or estimate", body = ApiError),
+    (status = 422, description = "Input the service refuses, e.g. a blank title", body = ApiError),
+))]
+async fn create_todo<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, Json(payload): Json<CreateBody>) -> Result<Json<serde_json::Value>, ApiError> {
+    validate_estimate(payload.todo.estimate_minutes)?;
+    let input = CreateTodo { priority: parse_priority(payload.priority.as_deref())?, recurrence: parse_recurrence(payload.recurrence.as_deref())?, owner: owner.0, ..payload.todo };
+    let todo = state.service.create(input).await.map_err(service_error)?;
+    Ok(Json(todo_json(&todo)))
+}
+
+/// All or nothing: one invalid item or failed insert and nothing is created.
+async fn create_todos_bulk<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, Json(payload): Json<Vec<CreateBody>>) -> Result<Json<serde_json::Value>, ApiError> {
+    check_batch_size(&state.service, payload.len())?;
+    let inputs = payload.into_iter().map(|item| {
+        validate_estimate(item.todo.estimate_minutes)?;
+        Ok(CreateTodo { priority: parse_priority(item.priority.as_deref())?, recurrence: parse_recurrence(item.recurrence.as_deref())?, owner: owner.0.clone(), ..item.todo })
+    }).collect::<Result<Vec<_>, ApiError>>()?;
+    let todos = state.service.create_many(inputs).await.map_err(service_error)?;
+    Ok(Json(serde_json::json!(todos.iter().map(todo_json).collect::<Vec<_>>())))
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListQuery {
+    #[param(value_type = Option<TodoStatus>)]
+    status: Option<String>,
+    /// RFC3339 timestamp or `YYYY-MM-DD`.
+    completed_after: Option<String>,
+    /// 1 to 500. Without `limit` and `offset`, the first `LIST_SOFT_LIMIT` todos (default 50).
+    limit: Option<u32>,
+    offset: Option<u32>,
+    /// Cut descriptions to this many characters and flag them with `description_truncated`.
+    truncate_description: Option<usize>,
+    include_deleted: Option<bool>,
+    tag: Option<String>,
+    /// `created_at` (default), `updated_at`, `title` or `staleness`.
+    sort: Option<String>,
+    /// `desc` (default) or `asc`.
+    order: Option<String>,
+}
+
+#[utoipa::path(get, path = "/todos", tag = "todos", params(ListQuery), responses(
+    (status = 200, description = "One page of todos", body = TodoPage),
+    (status = 206, description = "The items asked for with `Range: items=<first>-<last>`", body = TodoPage),
+    (status = 400, description = "Invalid filter, sort or Range", body = ApiError),
+    (status = 416, description = "The Range starts past the last item"),
+))]
+async fn list_todos<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, Query(query): Query<ListQuery>, headers: HeaderMap) -> Result<Response, ApiError> {
+    let filter = TodoFilter { include_deleted: query.include_deleted.unwrap_or(false), owner: owner.0, ..parse_filter(query.status.as_deref(), query.completed_after.as_deref(), query.tag)? };
+    let options = ListOptions { sort: parse_sort(query.sort.as_deref(), SortField::CreatedAt)?, order: parse_order(query.order.as_deref())? };
+    let range = headers.get(header::RANGE).map(|v| v.to_str().ok().and_then(parse_items_range).ok_or(ApiError::bad_request("invalid Range"))).transpose()?;
+    // A Range header takes precedence over limit/offset
+    let Some((start, end)) = range else {
+        if query.limit.is_none() && query.offset.is_none() { return list_unpaged(&state.service, filter, options, query.truncate_description).await; }
+        let limit = query.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+        if limit == 0 || limit > MAX_LIST_LIMIT { return Err(ApiError::bad_request(format!("limit must be between 1 and {}", MAX_LIST_LIMIT))); }
+        let offset = query.offset.unwrap_or(0);
+        let page = state.service.list_paged(filter, options, limit, offset).await.map_err(internal_error)?;
+        let items: Vec<_> = page.items.iter().map(|t| todo_list_json(t, query.truncate_description)).collect();
+        return Ok(([(header::ACCEPT_RANGES, "items")], Json(serde_json::json!({ "items": items, "total": page.total, "limit": limit, "offset": offset }))).into_response());
+    };
+    // Like `limit`, a range gets at most MAX_LIST_LIMIT items; Content-Range says where it stopped
+    let limit = u32::try_from(end - start).map_or(MAX_LIST_LIMIT, |n| n.saturating_add(1).min(MAX_LIST_LIMIT));
+    let page = state.service.list_paged(filter, options, limit, u32::try_from(start).unwrap_or(u32::MAX)).await.map_err(internal_error)?;
+    if page.items.is_empty() {
+        return Ok((StatusCode::RANGE_NOT_SATISFIABLE, [(header::CONTENT_RANGE, format!("items */{}", page.total))]).into_response());
+    }
+    let end = start + page.items.len() - 1;
+    let items: Vec<_> = page.items.iter().map(|t| todo_list_json(t, query.truncate_description)).collect();
+    Ok((StatusCode::PARTIAL_CONTENT, [(header::CONTENT_RANGE, format!("items {}-{}/{}", start, end, page.total))], Json(serde_json::json!({ "items": items, "total": page.total }))).into_response())
+}
+
+/// A list request with no `limit`, `offset` or `Range`, which older clients send expecting
+/// every todo: it gets at most the service's soft limit, with `truncated` and the `next` offset
+/// to continue from when there were more.
+async fn list_unpaged<S: TodoService>(service: &S, filter: TodoFilter, options: ListOptions, truncate_description: Option<usize>) -> Result<Response, ApiError> {
+    let limit = service.list_soft_limit();
+    let page = service.list_paged(filter, options, limit, 0).await.map_err(internal_error)?;
+    let truncated = page.total > page.items.len() as u64;
+    if truncated { tracing::warn!(total = page.total, limit, "unpaged GET /todos truncated to the soft limit"); }
+    let items: Vec<_> = page.items.iter().map(|t| todo_list_json(t, truncate_description)).collect();
+    let next = truncated.then_some(limit);
+    Ok(([(header::ACCEPT_RANGES, "items")], Json(serde_json::json!({ "items": items, "total": page.total, "limit": limit, "offset": 0, "truncated": truncated, "next": next }))).into_response())
+}
+
+/// Parses `items=<first>-<last>` (inclusive, zero-based); an open end (`items=50-`) means "to the end".
+fn parse_items_range(value: &str) -> Option<(usize, usize)> {
+    let (first, last) = value.trim().strip_prefix("items=")?.split_once('-')?;
+    let first: usize = first.trim().parse().ok()?;
+    let last: usize = if last.trim().is_empty() { usize::MAX } else { last.trim().parse().ok()? };
+    (first <= last).then_some((first, last))
 }
 
-async fn list_todos<S: TodoService>(State(state): State<AppState<S>>) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
-    let todos = state.service.list().await.map_err(internal_error)?;
-    Ok(Json(serde_json::json!({ "items": todos.into_iter().map(|t| serde_json::json!({
-        "id": t.id.0,
-        "title": t.title,
-        "description": t.description,
-        "status": format_status(&t),
-        "created_at": t.created_at,
-        "updated_at": t.updated_at,
-    })).collect::<Vec<_>>() })))
+#[derive(Deserialize)]
+struct PageQuery { limit: Option<u32>, offset: Option<u32>, sort: Option<String>, order: Option<String> }
+
+async fn list_archived<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, Query(query): Query<PageQuery>) -> Result<Json<serde_json::Value>, ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+    if limit == 0 || limit > MAX_PAGE_LIMIT { return Err(ApiError::bad_request(format!("limit must be between 1 and {}", MAX_PAGE_LIMIT))); }
+    let offset = query.offset.unwrap_or(0);
+    let sort = parse_sort(query.sort.as_deref(), SortField::UpdatedAt)?;
+    let order = parse_order(query.order.as_deref())?;
+    // One extra row tells us whether another page exists
+    let mut todos = state.service.list_archived_paged(sort, order, limit + 1, offset, owner.0.as_deref()).await.map_err(internal_error)?;
+    let next_offset = (todos.len() > limit as usize).then(|| offset + limit);
+    todos.truncate(limit as usize);
+    Ok(Json(serde_json::json!({ "items": todos.iter().map(todo_json).collect::<Vec<_>>(), "next_offset": next_offset })))
 }
 
-async fn get_todo<S: TodoService>(State(state): State<AppState<S>>, axum::extract::Path(id): axum::extract::Path<String>) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct GetQuery { include_deleted: Option<bool> }
+
+/// With `include_deleted`, a deleted todo answers 410 with its tombstone instead of 404.
+#[utoipa::path(get, path = "/todos/{id}", tag = "todos", params(("id" = uuid::Uuid, Path, description = "Todo id"), GetQuery), responses(
+    (status = 200, description = "The todo", body = Todo),
+    (status = 400, description = "Invalid id", body = ApiError),
+    (status = 404, description = "No such todo", body = ApiError),
+    (status = 410, description = "Deleted, with `include_deleted`; the body is the tombstone", body = Todo),
+))]
+async fn get_todo<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, axum::extract::Path(id): axum::extract::Path<String>, Query(query): Query<GetQuery>) -> Result<Response, ApiError> {
     let id = parse_id(&id)?;
-    let todo = state.service.get(id).await.map_err(internal_error)?;
+    let todo = if query.include_deleted.unwrap_or_else(|| state.service.include_deleted_by_default()) {
+        state.service.get_including_deleted(id, owner.0.as_deref()).await
+    } else {
+        state.service.get(id, owner.0.as_deref()).await
+    }.map_err(internal_error)?;
     match todo {
-        Some(t) => Ok(Json(serde_json::json!({ "id": t.id.0, "title": t.title, "description": t.description, "status": format_status(&t), "created_at": t.created_at, "updated_at": t.updated_at }))),
-        None => Err((StatusCode::NOT_FOUND, "Not found".into()))
+        Some(t) if t.deleted_at.is_some() => Ok((StatusCode::GONE, Json(todo_json(&t))).into_response()),
+        Some(t) => Ok(([(header::ETAG, etag(&t))], Json(todo_json(&t))).into_response()),
+        None => Err(ApiError::not_found())
+    }
+}
+
+async fn get_todo_by_code<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, axum::extract::Path(code): axum::extract::Path<String>) -> Result<Json<serde_json::Value>, ApiError> {
+    match state.service.get_by_code(&code, owner.0.as_deref()).await.map_err(internal_error)? {
+        Some(t) => Ok(Json(todo_json(&t))),
+        None => Err(ApiError::not_found())
+    }
+}
+
+/// The most recently updated todo, for "last activity" displays.
+async fn latest_todo<S: TodoService>(State(state): State<AppState<S>>, owner: Owner) -> Result<Json<serde_json::Value>, ApiError> {
+    match state.service.latest_updated(owner.0.as_deref()).await.map_err(internal_error)? {
+        Some(t) => Ok(Json(todo_json(&t))),
+        None => Err(ApiError::not_found())
+    }
+}
+
+#[derive(Deserialize)]
+struct StaleQuery { before: Option<String>, status: Option<String> }
+
+/// Todos untouched since `before`, oldest first, for finding abandoned work.
+async fn stale_todos<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, Query(query): Query<StaleQuery>) -> Result<Json<serde_json::Value>, ApiError> {
+    let before = parse_datetime("before", query.before.as_deref().ok_or(ApiError::bad_request("before is required"))?)?;
+    let status = parse_status(query.status.as_deref())?;
+    let todos = state.service.stale(before, status, owner.0.as_deref()).await.map_err(internal_error)?;
+    Ok(Json(serde_json::json!({ "items": todos.iter().map(todo_json).collect::<Vec<_>>() })))
+}
+
+/// The list filters (`status`, `completed_after`, `tag`) the exports honour.
+#[derive(Deserialize)]
+struct FilterQuery { status: Option<String>, completed_after: Option<String>, tag: Option<String> }
+
+#[derive(Deserialize)]
+struct ExportQuery { after: Option<String>, limit: Option<u32>, status: Option<String>, completed_after: Option<String>, tag: Option<String> }
+
+/// Every todo that isn't deleted and matches the list filters as one JSON array, streamed a
+/// chunk at a time; with `after` or `limit`, a single chunk (see [`export_chunk`]). Without a
+/// `status` every status is exported, archived included. Todos are in the admin `export` form,
+/// all fields included, which is what `POST /todos/import` and `admin import` read.
+async fn export_todos<S: TodoService + Clone>(State(state): State<AppState<S>>, owner: Owner, Query(query): Query<ExportQuery>) -> Result<Response, ApiError> {
+    let filter = TodoFilter { owner: owner.0, ..parse_filter(query.status.as_deref(), query.completed_after.as_deref(), query.tag.clone())? };
+    if query.after.is_some() || query.limit.is_some() { return export_chunk(state, filter, query).await.map(IntoResponse::into_response); }
+    let body = axum::body::Body::from_stream(export_stream(state.service, filter));
+    Ok(([(header::CONTENT_TYPE, "application/json")], body).into_response())
+}
+
+/// The JSON array text of an export, one [`TodoService::export_page`] at a time so memory
+/// stays bounded however many todos there are.
+fn export_stream<S: TodoService + Clone>(service: S, filter: TodoFilter) -> impl futures_util::Stream<Item = anyhow::Result<axum::body::Bytes>> {
+    // `None` once the closing bracket has been sent
+    futures_util::stream::unfold(Some((service, filter, None, true)), |state| async move {
+        let (service, filter, after, first) = state?;
+        let todos = match service.export_page(filter.clone(), after, DEFAULT_EXPORT_LIMIT).await {
+            Ok(todos) => todos,
+            Err(e) => return Some((Err(e), None)),
+        };
+        let mut text = String::from(if first { "[" } else { "" });
+        for (i, todo) in todos.iter().enumerate() {
+            if !(first && i == 0) { text.push(','); }
+            match serde_json::to_string(todo) { Ok(json) => text.push_str(&json), Err(e) => return Some((Err(e.into()), None)) }
+        }
+        let next = match todos.last() {
+            Some(last) if todos.len() == DEFAULT_EXPORT_LIMIT as usize => Some((service, filter, Some(last.id.clone()), false)),
+            _ => { text.push(']'); None }
+        };
+        Some((Ok(text.into()), next))
+    })
+}
+
+/// The todos [`export_todos`] would send for the same filters, in the same order, as a CSV
+/// download for spreadsheets.
+async fn export_csv<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, Query(query): Query<FilterQuery>) -> Result<Response, ApiError> {
+    let filter = TodoFilter { owner: owner.0, ..parse_filter(query.status.as_deref(), query.completed_after.as_deref(), query.tag)? };
+    let mut todos = Vec::new();
+    loop {
+        let page = state.service.export_page(filter.clone(), todos.last().map(|t: &crate::domain::todo::Todo| t.id.clone()), DEFAULT_EXPORT_LIMIT).await.map_err(internal_error)?;
+        let done = page.len() < DEFAULT_EXPORT_LIMIT as usize;
+        todos.extend(page);
+        if done { break; }
     }
+    let body = todos_csv(&todos).map_err(internal_error)?;
+    Ok(([(header::CONTENT_TYPE, "text/csv"), (header::CONTENT_DISPOSITION, "attachment; filename=\"todos.csv\"")], body).into_response())
+}
+
+/// `todos` as CSV with a header row; the writer quotes fields containing commas, quotes or
+/// newlines. A missing description is an empty field.
+pub(crate) fn todos_csv(todos: &[crate::domain::todo::Todo]) -> anyhow::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(["id", "title", "description", "status", "created_at", "updated_at"])?;
+    for t in todos {
+        writer.write_record([&t.id.0.to_string(), &t.title, t.description.as_deref().unwrap_or(""), t.status.as_str(), &t.created_at.to_rfc3339(), &t.updated_at.to_rfc3339()])?;
+    }
+    Ok(writer.into_inner()?)
+}
+
+/// One chunk of an export, in id order. `next_cursor` (the last id) goes into `after`, along
+/// with the same filters, for the next chunk and is null on the last one.
+async fn export_chunk<S: TodoService>(state: AppState<S>, filter: TodoFilter, query: ExportQuery) -> Result<Json<serde_json::Value>, ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_EXPORT_LIMIT);
+    if limit == 0 || limit > MAX_EXPORT_LIMIT { return Err(ApiError::bad_request(format!("limit must be between 1 and {}", MAX_EXPORT_LIMIT))); }
+    let after = query.after.as_deref().map(|s| uuid::Uuid::parse_str(s).map(TodoId).map_err(|_| ApiError::bad_request("invalid cursor"))).transpose()?;
+    // One extra row tells us whether another chunk exists
+    let mut todos = state.service.export_page(filter, after, limit + 1).await.map_err(internal_error)?;
+    let more = todos.len() > limit as usize;
+    todos.truncate(limit as usize);
+    let next_cursor = if more { todos.last().map(|t| t.id.0) } else { None };
+    Ok(Json(serde_json::json!({ "items": todos, "next_cursor": next_cursor })))
 }
 
 #[derive(Deserialize)]
-struct UpdateBody { title: Option<String>, description: Option<String>, status: Option<String> }
+struct ImportQuery { on_conflict: Option<String> }
+
+/// Restores an export: ids, timestamps and every other field are kept as given. A todo whose
+/// id exists already replaces it (`on_conflict=overwrite`, the default, also spelled `upsert`),
+/// is left out (`skip`) or fails the import with a 409 (`fail`), like the admin CLI's
+/// `--on-conflict`. All or nothing: the whole array is written in one transaction. With
+/// auth on, imported todos belong to the caller, and an id of someone else's todo is a 409.
+async fn import_todos<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, Query(query): Query<ImportQuery>, Json(mut todos): Json<Vec<crate::domain::todo::Todo>>) -> Result<Json<serde_json::Value>, ApiError> {
+    let policy = match query.on_conflict.as_deref() {
+        None | Some("upsert") => ConflictPolicy::Overwrite,
+        Some(policy) => policy.parse::<ConflictPolicy>().map_err(|e| ApiError::bad_request(format!("on_conflict: {}", e)))?,
+    };
+    check_batch_size(&state.service, todos.len())?;
+    if owner.0.is_some() {
+        for todo in &mut todos {
+            if let Some(existing) = state.service.get_including_deleted(todo.id.clone(), None).await.map_err(internal_error)?
+                && !owner.allows(&existing)
+            {
+                return Err(ApiError::new(StatusCode::CONFLICT, UniqueViolation { field: "id" }.to_string()));
+            }
+            todo.owner = owner.0.clone();
+        }
+    }
+    let report = state.service.import(todos, policy).await.map_err(service_error)?;
+    Ok(Json(serde_json::json!(report)))
+}
+
+async fn defer_todo<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, axum::extract::Path(id): axum::extract::Path<String>) -> Result<Json<serde_json::Value>, ApiError> {
+    let id = parse_id(&id)?;
+    check_owner(&state.service, &id, &owner).await?;
+    match state.service.defer(id.clone()).await.map_err(internal_error)? {
+        Some(count) => Ok(Json(serde_json::json!({ "id": id.0, "defer_count": count }))),
+        None => Err(ApiError::not_found()),
+    }
+}
 
-async fn update_todo<S: TodoService>(State(state): State<AppState<S>>, axum::extract::Path(id): axum::extract::Path<String>, Json(payload): Json<UpdateBody>) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+async fn clear_due<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, axum::extract::Path(id): axum::extract::Path<String>) -> Result<Json<serde_json::Value>, ApiError> {
     let id = parse_id(&id)?;
-    let status = match payload.status.as_deref() {
-        Some("pending") => Some(crate::domain::todo::TodoStatus::Pending),
-        Some("done") => Some(crate::domain::todo::TodoStatus::Done),
-        Some(_) => return Err((StatusCode::BAD_REQUEST, "invalid status".into())),
+    check_owner(&state.service, &id, &owner).await?;
+    match state.service.clear_due(id).await.map_err(internal_error)? {
+        Some(t) => Ok(Json(todo_json(&t))),
+        None => Err(ApiError::not_found()),
+    }
+}
+
+async fn todo_changes<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, axum::extract::Path(id): axum::extract::Path<String>) -> Result<Json<serde_json::Value>, ApiError> {
+    let id = parse_id(&id)?;
+    check_owner(&state.service, &id, &owner).await?;
+    match state.service.changes(id).await.map_err(internal_error)? {
+        Some(changes) => Ok(Json(serde_json::json!({ "items": changes }))),
+        None => Err(ApiError::not_found()),
+    }
+}
+
+async fn restore_todo<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, axum::extract::Path(id): axum::extract::Path<String>) -> Result<Json<serde_json::Value>, ApiError> {
+    let id = parse_id(&id)?;
+    check_owner(&state.service, &id, &owner).await?;
+    match state.service.restore(id).await.map_err(internal_error)? {
+        Some(t) => Ok(Json(todo_json(&t))),
+        None => Err(ApiError::not_found()),
+    }
+}
+
+async fn undo_delete<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, axum::extract::Path(id): axum::extract::Path<String>) -> Result<Json<serde_json::Value>, ApiError> {
+    let id = parse_id(&id)?;
+    check_owner(&state.service, &id, &owner).await?;
+    match state.service.undo_delete(id).await.map_err(service_error)? {
+        Some(t) => Ok(Json(todo_json(&t))),
+        None => Err(ApiError::not_found()),
+    }
+}
+
+#[derive(Deserialize)]
+struct QueryBody { ids: Vec<String>, status: Option<String> }
+
+async fn query_todos<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, Json(payload): Json<QueryBody>) -> Result<Json<serde_json::Value>, ApiError> {
+    check_batch_size(&state.service, payload.ids.len())?;
+    let ids = payload.ids.iter().map(|s| parse_id(s)).collect::<Result<Vec<_>, _>>()?;
+    let status = parse_status(payload.status.as_deref())?;
+    let todos = state.service.get_many(ids, owner.0.as_deref()).await.map_err(internal_error)?;
+    Ok(Json(serde_json::json!({ "items": todos.iter().filter(|t| status.as_ref().is_none_or(|s| &t.status == s)).map(todo_json).collect::<Vec<_>>() })))
+}
+
+#[derive(Deserialize)]
+struct WorkloadQuery { status: Option<String> }
+
+async fn workload<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, Query(query): Query<WorkloadQuery>) -> Result<Json<serde_json::Value>, ApiError> {
+    let status = parse_status(query.status.as_deref())?;
+    let workload = state.service.workload(status, owner.0.as_deref()).await.map_err(internal_error)?;
+    Ok(Json(serde_json::json!({ "total_minutes": workload.total_minutes, "count": workload.count })))
+}
+
+/// Totals for dashboards; archived todos are left out, like the default list.
+async fn todo_stats<S: TodoService>(State(state): State<AppState<S>>, owner: Owner) -> Result<Json<serde_json::Value>, ApiError> {
+    let counts = state.service.count_by_status(owner.0.as_deref()).await.map_err(internal_error)?;
+    let total = counts.pending + counts.in_progress + counts.waiting + counts.done + counts.cancelled;
+    Ok(Json(serde_json::json!({ "pending": counts.pending, "in_progress": counts.in_progress, "waiting": counts.waiting, "done": counts.done, "cancelled": counts.cancelled, "total": total })))
+}
+
+#[derive(Deserialize)]
+struct VelocityQuery { weeks: Option<u32> }
+
+async fn velocity<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, Query(query): Query<VelocityQuery>) -> Result<Json<serde_json::Value>, ApiError> {
+    let weeks = query.weeks.unwrap_or(DEFAULT_VELOCITY_WEEKS);
+    if weeks == 0 || weeks > MAX_VELOCITY_WEEKS { return Err(ApiError::bad_request(format!("weeks must be between 1 and {}", MAX_VELOCITY_WEEKS))); }
+    let velocity = state.service.velocity(weeks, owner.0.as_deref()).await.map_err(internal_error)?;
+    Ok(Json(serde_json::json!(velocity)))
+}
+
+/// Omitted fields are left as they are; `tags` replaces all of them.
+#[derive(Deserialize, ToSchema)]
+pub(crate) struct UpdateBody {
+    title: Option<String>,
+    description: Option<String>,
+    #[schema(value_type = Option<TodoStatus>)]
+    status: Option<String>,
+    estimate_minutes: Option<i64>,
+    /// RFC3339 timestamp or `YYYY-MM-DD`.
+    #[schema(value_type = Option<chrono::DateTime<chrono::Utc>>)]
+    due_at: Option<String>,
+    #[schema(value_type = Option<Priority>)]
+    priority: Option<String>,
+    tags: Option<Vec<String>>,
+    #[schema(value_type = Option<Recurrence>)]
+    recurrence: Option<String>,
+    waiting_on: Option<String>,
+}
+
+#[utoipa::path(put, path = "/todos/{id}", tag = "todos", params(("id" = uuid::Uuid, Path, description = "Todo id")), request_body = UpdateBody, responses(
+    (status = 200, description = "The updated todo", body = Todo),
+    (status = 400, description = "Invalid id, status, priority, recurrence, due_at or estimate", body = ApiError),
+    (status = 404, description = "No such todo", body = ApiError),
+    (status = 412, description = "`If-Match` doesn't match the todo's current ETag", body = ApiError),
+    (status = 422, description = "Input the service refuses, e.g. a blank title", body = ApiError),
+))]
+async fn update_todo<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, axum::extract::Path(id): axum::extract::Path<String>, headers: HeaderMap, Json(payload): Json<UpdateBody>) -> Result<Response, ApiError> {
+    let id = parse_id(&id)?;
+    check_owner(&state.service, &id, &owner).await?;
+    let if_updated_at = parse_if_match(&headers)?;
+    let status = parse_status(payload.status.as_deref())?;
+    validate_estimate(payload.estimate_minutes)?;
+    let due_at = payload.due_at.as_deref().map(|s| parse_datetime("due_at", s)).transpose()?;
+    let priority = parse_priority(payload.priority.as_deref())?;
+    let recurrence = parse_recurrence(payload.recurrence.as_deref())?.map(Some);
+    let updated = state.service.update(id, UpdateTodo { title: payload.title, description: payload.description.map(Some), status, estimate_minutes: payload.estimate_minutes, due_at, priority, tags: payload.tags, recurrence, waiting_on: payload.waiting_on.map(Some), if_updated_at }).await.map_err(service_error)?;
+    match updated {
+        Some(t) => Ok(([(header::ETAG, etag(&t))], Json(todo_json(&t))).into_response()),
+        None => Err(ApiError::not_found())
+    }
+}
+
+/// A todo's version: its `updated_at` in microseconds, the precision every repo keeps.
+fn etag(t: &crate::domain::todo::Todo) -> String {
+    format!("\"{}\"", t.updated_at.timestamp_micros())
+}
+
+/// The `updated_at` an `If-Match` header requires; `None` without the header or for `*`. Only
+/// a single strong ETag as [`etag`] makes can match, so anything else fails the precondition.
+fn parse_if_match(headers: &HeaderMap) -> Result<Option<chrono::DateTime<chrono::Utc>>, ApiError> {
+    let Some(value) = headers.get(header::IF_MATCH) else { return Ok(None) };
+    let value = value.to_str().unwrap_or_default().trim();
+    if value == "*" { return Ok(None); }
+    value.strip_prefix('"').and_then(|v| v.strip_suffix('"'))
+        .and_then(|micros| micros.parse::<i64>().ok())
+        .and_then(chrono::DateTime::from_timestamp_micros)
+        .map(Some)
+        .ok_or_else(precondition_failed)
+}
+
+fn precondition_failed() -> ApiError {
+    ApiError::new(StatusCode::PRECONDITION_FAILED, crate::domain::todo::VersionMismatch.to_string())
+}
+
+/// One RFC 6902 operation; `move` and `copy` parse but are refused.
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum PatchOp {
+    Add { path: String, value: serde_json::Value },
+    Remove { path: String },
+    Replace { path: String, value: serde_json::Value },
+    Test { path: String, value: serde_json::Value },
+    #[serde(other)]
+    Unsupported,
+}
+
+/// Fields a patch may change; `test` may check any top-level field.
+const PATCHABLE: [&str; 3] = ["title", "description", "status"];
+
+/// Like [`UpdateBody`], except that `"description": null` clears the description (and
+/// `"recurrence"` and `"waiting_on"` likewise) while omitting it leaves it unchanged.
+#[derive(Deserialize)]
+struct PatchBody {
+    title: Option<String>,
+    #[serde(default, deserialize_with = "crate::domain::todo::nullable")]
+    description: Option<Option<String>>,
+    status: Option<String>,
+    estimate_minutes: Option<i64>,
+    due_at: Option<String>,
+    priority: Option<String>,
+    tags: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "crate::domain::todo::nullable")]
+    recurrence: Option<Option<String>>,
+    #[serde(default, deserialize_with = "crate::domain::todo::nullable")]
+    waiting_on: Option<Option<String>>,
+}
+
+/// `PATCH /todos/:id`, dispatched on the content type: a JSON Patch document with
+/// `application/json-patch+json`, a [`PatchBody`] with `application/json`, 415 otherwise.
+/// Either honours `If-Match` and answers with the new `ETag`, like `PUT`.
+async fn patch_todo<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, axum::extract::Path(id): axum::extract::Path<String>, headers: HeaderMap, body: axum::body::Bytes) -> Result<Response, ApiError> {
+    let id = parse_id(&id)?;
+    check_owner(&state.service, &id, &owner).await?;
+    let if_updated_at = parse_if_match(&headers)?;
+    let content_type = headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    if content_type.starts_with("application/json-patch+json") { return json_patch_todo(state, owner, id, if_updated_at, body).await; }
+    if !content_type.starts_with("application/json") {
+        return Err(ApiError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "expected application/json or application/json-patch+json"));
+    }
+    let Json(payload) = Json::<PatchBody>::from_bytes(&body).map_err(|e| ApiError::new(e.status(), e.body_text()))?;
+    let status = parse_status(payload.status.as_deref())?;
+    validate_estimate(payload.estimate_minutes)?;
+    let due_at = payload.due_at.as_deref().map(|s| parse_datetime("due_at", s)).transpose()?;
+    let priority = parse_priority(payload.priority.as_deref())?;
+    let recurrence = payload.recurrence.map(|r| parse_recurrence(r.as_deref())).transpose()?;
+    let updated = state.service.update(id, UpdateTodo { title: payload.title, description: payload.description, status, estimate_minutes: payload.estimate_minutes, due_at, priority, tags: payload.tags, recurrence, waiting_on: payload.waiting_on, if_updated_at }).await.map_err(service_error)?;
+    match updated {
+        Some(t) => Ok(([(header::ETAG, etag(&t))], Json(todo_json(&t))).into_response()),
+        None => Err(ApiError::not_found())
+    }
+}
+
+/// The operations run against the todo's JSON form and are all-or-nothing: a failed `test`
+/// answers 409 and anything outside `PATCHABLE` 422, without writing. The write only applies
+/// to the version the operations saw, so a change made in between is a 409 as well; an
+/// `If-Match` naming another version than the one read is a 412 before any operation runs.
+async fn json_patch_todo<S: TodoService>(state: AppState<S>, owner: Owner, id: TodoId, if_updated_at: Option<chrono::DateTime<chrono::Utc>>, body: axum::body::Bytes) -> Result<Response, ApiError> {
+    let ops: Vec<PatchOp> = serde_json::from_slice(&body).map_err(|e| ApiError::bad_request(format!("invalid patch: {}", e)))?;
+    check_batch_size(&state.service, ops.len())?;
+    let Some(todo) = state.service.get(id.clone(), owner.0.as_deref()).await.map_err(internal_error)? else { return Err(ApiError::not_found()) };
+    if !(UpdateTodo { if_updated_at, ..Default::default() }).version_matches(todo.updated_at) { return Err(precondition_failed()); }
+
+    let original = todo_json(&todo);
+    let mut doc = original.clone();
+    for op in ops { apply_patch_op(&mut doc, op)?; }
+
+    let changed = |field: &str| (doc[field] != original[field]).then(|| doc[field].clone());
+    let title = changed("title").map(|v| v.as_str().map(str::to_string).ok_or(ApiError::unprocessable("title must be a string"))).transpose()?;
+    let description = changed("description").map(|v| match v {
+        serde_json::Value::String(s) => Ok(Some(s)),
+        serde_json::Value::Null => Ok(None),
+        _ => Err(ApiError::unprocessable("description must be a string")),
+    }).transpose()?;
+    let status = match changed("status") {
+        Some(v) => parse_status(v.as_str()).map_err(|_| ApiError::unprocessable("invalid status"))?,
         None => None,
     };
-    let updated = state.service.update(id, UpdateTodo { title: payload.title, description: payload.description, status }).await.map_err(internal_error)?;
+    if title.is_none() && description.is_none() && status.is_none() { return Ok(([(header::ETAG, etag(&todo))], Json(original)).into_response()); }
+    let if_updated_at = Some(todo.updated_at);
+    let updated = state.service.update(id, UpdateTodo { title, description, status, if_updated_at, ..Default::default() }).await.map_err(|e| {
+        if e.is::<crate::domain::todo::VersionMismatch>() { ApiError::new(StatusCode::CONFLICT, e.to_string()) } else { service_error(e) }
+    })?;
     match updated {
-        Some(t) => Ok(Json(serde_json::json!({ "id": t.id.0, "title": t.title, "description": t.description, "status": format_status(&t), "created_at": t.created_at, "updated_at": t.updated_at }))),
-        None => Err((StatusCode::NOT_FOUND, "Not found".into()))
+        Some(t) => Ok(([(header::ETAG, etag(&t))], Json(todo_json(&t))).into_response()),
+        None => Err(ApiError::not_found())
     }
 }
 
-async fn delete_todo<S: TodoService>(State(state): State<AppState<S>>, axum::extract::Path(id): axum::extract::Path<String>) -> Result<StatusCode, (StatusCode, String)> {
+fn apply_patch_op(doc: &mut serde_json::Value, op: PatchOp) -> Result<(), ApiError> {
+    // Only top-level members exist in a todo, so a pointer is just "/<name>"
+    let field = |path: &str| path.strip_prefix('/').filter(|f| !f.contains('/')).map(str::to_string).ok_or_else(|| ApiError::unprocessable(format!("unsupported path {:?}", path)));
+    let writable = |path: &str| field(path).and_then(|f| if PATCHABLE.contains(&f.as_str()) { Ok(f) } else { Err(ApiError::unprocessable(format!("unsupported path {:?}", path))) });
+    match op {
+        PatchOp::Add { path, value } | PatchOp::Replace { path, value } => { doc[writable(&path)?] = value; }
+        PatchOp::Remove { path } => {
+            let f = writable(&path)?;
+            if f != "description" { return Err(ApiError::unprocessable(format!("{} cannot be removed", f))); }
+            doc[f] = serde_json::Value::Null;
+        }
+        PatchOp::Test { path, value } => {
+            let f = field(&path)?;
+            let current = doc.get(&f).ok_or_else(|| ApiError::unprocessable(format!("unsupported path {:?}", path)))?;
+            if *current != value { return Err(ApiError::new(StatusCode::CONFLICT, format!("test failed for {}", path))); }
+        }
+        PatchOp::Unsupported => return Err(ApiError::unprocessable("unsupported op (expected add, remove, replace or test)")),
+    }
+    Ok(())
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct DeleteQuery { purge: Option<bool> }
+
+/// Soft delete by default; `?purge=true` removes the row for good (also for deleted todos).
+#[utoipa::path(delete, path = "/todos/{id}", tag = "todos", params(("id" = uuid::Uuid, Path, description = "Todo id"), DeleteQuery), responses(
+    (status = 204, description = "Deleted"),
+    (status = 400, description = "Invalid id", body = ApiError),
+    (status = 404, description = "No such todo", body = ApiError),
+))]
+async fn delete_todo<S: TodoService>(State(state): State<AppState<S>>, owner: Owner, axum::extract::Path(id): axum::extract::Path<String>, Query(query): Query<DeleteQuery>) -> Result<StatusCode, ApiError> {
     let id = parse_id(&id)?;
-    let deleted = state.service.delete(id).await.map_err(internal_error)?;
-    if deleted { Ok(StatusCode::NO_CONTENT) } else { Err((StatusCode::NOT_FOUND, "Not found".into())) }
+    check_owner(&state.service, &id, &owner).await?;
+    let deleted = if query.purge.unwrap_or(false) { state.service.purge(id).await } else { state.service.delete(id).await }.map_err(internal_error)?;
+    if deleted { Ok(StatusCode::NO_CONTENT) } else { Err(ApiError::not_found()) }
 }
 
-fn parse_id(s: &str) -> Result<TodoId, (StatusCode, String)> { uuid::Uuid::parse_str(s).map(|u| TodoId(u)).map_err(|_| (StatusCode::BAD_REQUEST, "invalid id".into())) }
+/// Accepts any form `Uuid` parses (uppercase, braced `{...}`, `urn:uuid:`, no hyphens). Repos
+/// bind ids in the canonical lowercase hyphenated form they are stored in, so every variant
+/// finds the same row.
+fn parse_id(s: &str) -> Result<TodoId, ApiError> { uuid::Uuid::parse_str(s).map(TodoId).map_err(|_| ApiError::bad_request("invalid id")) }
 
-fn format_status(t: &crate::domain::todo::Todo) -> &'static str { match t.status { crate::domain::todo::TodoStatus::Pending => "pending", crate::domain::todo::TodoStatus::Done => "done" } }
+/// The filters `GET /todos` and the exports share; `include_deleted` is left to the caller.
+fn parse_filter(status: Option<&str>, completed_after: Option<&str>, tag: Option<String>) -> Result<TodoFilter, ApiError> {
+    Ok(TodoFilter {
+        status: parse_status(status)?,
+        completed_after: completed_after.map(|s| parse_datetime("completed_after", s)).transpose()?,
+        tag,
+        ..Default::default()
+    })
+}
+
+fn parse_status(s: Option<&str>) -> Result<Option<TodoStatus>, ApiError> {
+    s.map(|s| TodoStatus::ALL.into_iter().find(|status| status.as_str() == s).ok_or(ApiError::bad_request("invalid status"))).transpose()
+}
+
+fn parse_priority(s: Option<&str>) -> Result<Option<Priority>, ApiError> {
+    s.map(|s| Priority::ALL.into_iter().find(|p| p.as_str() == s).ok_or(ApiError::bad_request("invalid priority"))).transpose()
+}
+
+fn parse_recurrence(s: Option<&str>) -> Result<Option<Recurrence>, ApiError> {
+    s.map(|s| Recurrence::ALL.into_iter().find(|r| r.as_str() == s).ok_or(ApiError::bad_request("invalid recurrence"))).transpose()
+}
+
+/// `sort` values map onto [`SortField`] here, so nothing from the query reaches the SQL.
+fn parse_sort(s: Option<&str>, default: SortField) -> Result<SortField, ApiError> {
+    match s {
+        None => Ok(default),
+        Some("updated_at") => Ok(SortField::UpdatedAt),
+        Some("created_at") => Ok(SortField::CreatedAt),
+        Some("title") => Ok(SortField::Title),
+        Some("staleness") => Ok(SortField::Staleness),
+        Some("priority") => Ok(SortField::Priority),
+        Some(_) => Err(ApiError::bad_request("invalid sort")),
+    }
+}
 
-fn internal_error<E: std::fmt::Display>(e: E) -> (StatusCode, String) { (StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e)) }
+fn parse_order(s: Option<&str>) -> Result<SortOrder, ApiError> {
+    match s {
+        None | Some("desc") => Ok(SortOrder::Desc),
+        Some("asc") => Ok(SortOrder::Asc),
+        Some(_) => Err(ApiError::bad_request("invalid order")),
+    }
+}
+
+/// Accepts an RFC3339 timestamp or a plain `YYYY-MM-DD` date (midnight UTC).
+fn parse_datetime(field: &str, s: &str) -> Result<chrono::DateTime<chrono::Utc>, ApiError> {
+    chrono::DateTime::parse_from_rfc3339(s)
+        .map(|d| d.with_timezone(&chrono::Utc))
+        .or_else(|_| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").map(|d| d.and_time(chrono::NaiveTime::MIN).and_utc()))
+        .map_err(|_| ApiError::bad_request(format!("invalid {}", field)))
+}
+
+/// Every endpoint taking a list of items goes through this before doing any work.
+fn check_batch_size<S: TodoService>(service: &S, len: usize) -> Result<(), ApiError> {
+    let max = service.max_batch_size();
+    if len > max { Err(ApiError::unprocessable(format!("batch of {} items exceeds the limit of {}", len, max))) } else { Ok(()) }
+}
+
+fn validate_estimate(minutes: Option<i64>) -> Result<(), ApiError> {
+    if minutes.is_some_and(|m| m < 0) { Err(ApiError::bad_request("invalid estimate_minutes")) } else { Ok(()) }
+}
+
+fn format_status(t: &crate::domain::todo::Todo) -> &'static str { t.status.as_str() }
+
+/// serde_json objects are `BTreeMap`s, so the keys come out sorted and the bytes are stable.
+fn todo_json(t: &crate::domain::todo::Todo) -> serde_json::Value {
+    serde_json::json!({ "id": t.id.0, "title": t.title, "description": t.description, "status": format_status(t), "status_label": t.status.to_string(), "code": t.code, "estimate_minutes": t.estimate_minutes, "created_at": t.created_at, "updated_at": t.updated_at, "completed_at": t.completed_at, "deleted_at": t.deleted_at, "due_at": t.due_at, "priority": t.priority.as_str(), "defer_count": t.defer_count, "tags": t.tags, "recurrence": t.recurrence.map(|r| r.as_str()), "waiting_on": t.waiting_on })
+}
+
+/// A todo as a list item: with `truncate`, descriptions longer than that many characters are
+/// cut there and flagged with `description_truncated`, so clients know to show an ellipsis.
+fn todo_list_json(t: &crate::domain::todo::Todo, truncate: Option<usize>) -> serde_json::Value {
+    let mut json = todo_json(t);
+    if let (Some(max), Some(description)) = (truncate, &t.description) {
+        let cut = description.char_indices().nth(max).map(|(i, _)| i);
+        json["description"] = serde_json::json!(&description[..cut.unwrap_or(description.len())]);
+        json["description_truncated"] = serde_json::json!(cut.is_some());
+    }
+    json
+}
+
+/// Logged here, inside the request span, so the failure carries the request id.
+fn internal_error<E: std::fmt::Display>(e: E) -> ApiError {
+    tracing::error!(error = %e, "request failed");
+    ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{}", e))
+}
+
+/// Like [`internal_error`], but input the service refused is the caller's fault (422).
+fn service_error(e: anyhow::Error) -> ApiError {
+    if let Some(invalid) = e.downcast_ref::<crate::domain::todo::ValidationError>() { return ApiError::unprocessable(invalid.to_string()); }
+    if let Some(expired) = e.downcast_ref::<crate::domain::todo::UndoWindowExpired>() { return ApiError::new(StatusCode::GONE, expired.to_string()); }
+    if e.is::<crate::domain::todo::VersionMismatch>() { return precondition_failed(); }
+    if let Some(duplicate) = e.downcast_ref::<crate::domain::todo::UniqueViolation>() { return ApiError::new(StatusCode::CONFLICT, duplicate.to_string()); }
+    internal_error(e)
+}
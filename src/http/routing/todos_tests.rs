@@ -0,0 +1,25 @@
+#[cfg(test)]
+mod tests {
+    use super::super::todos::todos_csv;
+    use crate::domain::todo::{Todo, TodoId, TodoStatus};
+    use chrono::{TimeZone, Utc};
+
+    fn todo(title: &str, description: Option<&str>) -> Todo {
+        let at = Utc.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap();
+        Todo {
+            id: TodoId(uuid::Uuid::nil()), title: title.into(), description: description.map(Into::into), status: TodoStatus::Pending, code: "TODO-0000".into(),
+            estimate_minutes: None, created_at: at, updated_at: at, completed_at: None, deleted_at: None, due_at: None, priority: Default::default(),
+            defer_count: 0, tags: vec![], recurrence: None, waiting_on: None, owner: None,
+        }
+    }
+
+    #[test]
+    fn unit_csv_quotes_descriptions_with_commas_and_newlines() {
+        let csv = String::from_utf8(todos_csv(&[todo("Shop", Some("milk, eggs")), todo("Call", Some("line one\nline two")), todo("Plain", None)]).unwrap()).unwrap();
+        let id = uuid::Uuid::nil();
+        let at = "2024-05-06T07:08:09+00:00";
+        assert_eq!(csv, format!(
+            "id,title,description,status,created_at,updated_at\n{id},Shop,\"milk, eggs\",pending,{at},{at}\n{id},Call,\"line one\nline two\",pending,{at},{at}\n{id},Plain,,pending,{at},{at}\n"
+        ));
+    }
+}
@@ -2,9 +2,29 @@ use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ApiError { pub message: String }
+/// Error body every todo handler answers with, e.g. `{"message":"Not found","code":"not_found"}`.
+/// `code` is the snake-cased reason phrase of `status`, so clients can match on it.
+#[derive(Debug, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: StatusCode,
+    pub message: String,
+    pub code: String,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        let code = status.canonical_reason().unwrap_or("error").to_ascii_lowercase().replace([' ', '-'], "_");
+        Self { status, message: message.into(), code }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self { Self::new(StatusCode::BAD_REQUEST, message) }
+
+    pub fn unprocessable(message: impl Into<String>) -> Self { Self::new(StatusCode::UNPROCESSABLE_ENTITY, message) }
+
+    pub fn not_found() -> Self { Self::new(StatusCode::NOT_FOUND, "Not found") }
+}
 
 impl IntoResponse for ApiError {
-    fn into_response(self) -> Response { (StatusCode::BAD_REQUEST, axum::Json(self)).into_response() }
+    fn into_response(self) -> Response { (self.status, axum::Json(self)).into_response() }
 }
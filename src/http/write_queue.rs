@@ -0,0 +1,68 @@
+//! One-at-a-time writes: requests that change data wait their turn in a bounded queue.
+
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Router,
+};
+use tokio::sync::Mutex;
+
+use crate::http::types::ApiError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteQueueConfig {
+    /// Writes that may be running or waiting at once; the next one gets 503.
+    pub depth: usize,
+}
+
+impl WriteQueueConfig {
+    /// `None` unless `WRITE_QUEUE_DEPTH` is set.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(depth) = std::env::var("WRITE_QUEUE_DEPTH").ok().filter(|s| !s.trim().is_empty()) else { return Ok(None) };
+        let depth = depth.trim().parse::<usize>().ok().filter(|&d| d > 0).with_context(|| format!("invalid WRITE_QUEUE_DEPTH {:?}", depth))?;
+        Ok(Some(Self { depth }))
+    }
+}
+
+struct WriteQueue {
+    depth: usize,
+    /// Writes admitted and not yet finished, the running one included.
+    queued: AtomicUsize,
+    /// Held by the running write; Tokio's mutex is fair, so waiting writes run in arrival order.
+    turn: Mutex<()>,
+}
+
+/// Gives back a queue slot however the request ends, cancellation included.
+struct Slot<'a>(&'a AtomicUsize);
+
+impl Drop for Slot<'_> {
+    fn drop(&mut self) { self.0.fetch_sub(1, Ordering::AcqRel); }
+}
+
+/// Runs the writes (anything but GET, HEAD and OPTIONS) sent to `router` one at a time, so a
+/// burst queues up in the process instead of contending for the SQLite write lock. Reads skip
+/// the queue. A write arriving while `depth` are already running or waiting gets 503.
+pub fn apply(router: Router, config: WriteQueueConfig) -> Router {
+    let queue = WriteQueue { depth: config.depth, queued: AtomicUsize::new(0), turn: Mutex::new(()) };
+    router.layer(middleware::from_fn_with_state(Arc::new(queue), serialize_writes))
+}
+
+async fn serialize_writes(State(queue): State<Arc<WriteQueue>>, req: Request, next: Next) -> Response {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) { return next.run(req).await; }
+    if queue.queued.fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| (n < queue.depth).then_some(n + 1)).is_err() {
+        let mut response = ApiError::new(StatusCode::SERVICE_UNAVAILABLE, "Write queue is full").into_response();
+        response.headers_mut().insert(header::RETRY_AFTER, HeaderValue::from(1));
+        return response;
+    }
+    let _slot = Slot(&queue.queued);
+    let _turn = queue.turn.lock().await;
+    next.run(req).await
+}
@@ -0,0 +1,84 @@
+//! Periodic copies of the SQLite database to timestamped files.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+
+use super::sqlite_repo::SqliteTodoRepository;
+
+const FILE_PREFIX: &str = "todos-";
+const FILE_SUFFIX: &str = ".db";
+
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    pub dir: PathBuf,
+    pub interval: Duration,
+    /// Newest backups to keep; older ones are removed after each run.
+    pub keep: usize,
+}
+
+impl BackupConfig {
+    /// `None` unless `BACKUP_DIR` is set; `BACKUP_INTERVAL_SECS` defaults to an hour and `BACKUP_KEEP` to 7.
+    pub fn from_env() -> Result<Option<Self>> {
+        let Some(dir) = std::env::var_os("BACKUP_DIR").filter(|d| !d.is_empty()) else { return Ok(None) };
+        let number = |name: &str, default: u64| -> Result<u64> {
+            match std::env::var(name) {
+                Ok(v) => match v.trim().parse::<u64>() {
+                    Ok(n) if n > 0 => Ok(n),
+                    _ => anyhow::bail!("invalid {} {:?}", name, v),
+                },
+                Err(_) => Ok(default),
+            }
+        };
+        Ok(Some(Self {
+            dir: PathBuf::from(dir),
+            interval: Duration::from_secs(number("BACKUP_INTERVAL_SECS", 3600)?),
+            keep: number("BACKUP_KEEP", 7)? as usize,
+        }))
+    }
+}
+
+/// Writes one consistent snapshot into `config.dir`, then prunes to `config.keep` files.
+pub async fn backup_once(repo: &SqliteTodoRepository, config: &BackupConfig) -> Result<PathBuf> {
+    std::fs::create_dir_all(&config.dir).with_context(|| format!("cannot create {}", config.dir.display()))?;
+    // Fixed-width UTC timestamps sort by name in creation order
+    let name = format!("{}{}{}", FILE_PREFIX, Utc::now().format("%Y%m%dT%H%M%S%.3fZ"), FILE_SUFFIX);
+    let path = config.dir.join(name);
+    repo.backup_to(&path).await?;
+    prune(&config.dir, config.keep)?;
+    Ok(path)
+}
+
+fn prune(dir: &Path, keep: usize) -> Result<()> {
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with(FILE_PREFIX) && n.ends_with(FILE_SUFFIX)))
+        .collect();
+    backups.sort();
+    let excess = backups.len().saturating_sub(keep);
+    for old in &backups[..excess] {
+        std::fs::remove_file(old).with_context(|| format!("cannot remove old backup {}", old.display()))?;
+    }
+    Ok(())
+}
+
+/// Backs up every `config.interval` on a background task; `None` for in-memory databases,
+/// which have nothing on disk worth keeping.
+pub fn spawn(repo: SqliteTodoRepository, database_url: &str, config: BackupConfig) -> Option<tokio::task::JoinHandle<()>> {
+    if database_url.contains(":memory:") || database_url.contains("mode=memory") {
+        tracing::info!("in-memory database; backups disabled");
+        return None;
+    }
+    Some(tokio::spawn(async move {
+        let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + config.interval, config.interval);
+        loop {
+            ticks.tick().await;
+            match backup_once(&repo, &config).await {
+                Ok(path) => tracing::info!(path = %path.display(), "database backed up"),
+                Err(e) => tracing::warn!(error = %e, "database backup failed"),
+            }
+        }
+    }))
+}
@@ -0,0 +1,309 @@
+//! A [`TodoRepository`] held entirely in memory.
+
+use std::{collections::{BTreeMap, HashMap}, sync::{Arc, Mutex}};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::{stream::{self, BoxStream}, StreamExt};
+
+use crate::domain::{
+    repository::TodoRepository,
+    todo::{new_code, ConflictPolicy, CreateTodo, ImportReport, Inconsistency, InconsistencyKind, ListOptions, Page, SortField, SortOrder, StatusCounts, Todo, TodoChange, TodoFilter, TodoId, TodoStatus, UniqueViolation, UpdateTodo, UpdatedTodo, VersionMismatch, Workload},
+};
+
+/// Keeps todos in a map shared between clones. Nothing is persisted, so it suits tests and
+/// throwaway servers; deletes are soft, like in SQLite.
+#[derive(Clone, Default)]
+pub struct InMemoryTodoRepository {
+    items: Arc<Mutex<HashMap<String, Todo>>>,
+    changes: Arc<Mutex<HashMap<String, Vec<TodoChange>>>>,
+}
+
+impl InMemoryTodoRepository {
+    /// Snapshot of the todos that aren't deleted.
+    fn live(&self) -> HashMap<String, Todo> {
+        self.items.lock().unwrap().iter().filter(|(_, t)| t.deleted_at.is_none()).map(|(k, t)| (k.clone(), t.clone())).collect()
+    }
+
+    /// Logs a write like the SQL repos do; called with `items` still locked, so entries land in
+    /// the order the writes did.
+    fn record_change(&self, before: &Todo, after: &Todo) {
+        if let Some(change) = TodoChange::between(before, after) {
+            self.changes.lock().unwrap().entry(after.id.0.to_string()).or_default().push(change);
+        }
+    }
+}
+
+/// A fresh pending todo for `input`.
+fn new_todo(input: CreateTodo) -> Todo {
+    let now = Utc::now();
+    Todo { id: TodoId(uuid::Uuid::new_v4()), title: input.title, description: input.description.flatten(), status: TodoStatus::Pending, code: new_code(), estimate_minutes: input.estimate_minutes, created_at: now, updated_at: now, completed_at: None, deleted_at: None, due_at: input.due_at, priority: input.priority.unwrap_or_default(), defer_count: 0, tags: input.tags, recurrence: input.recurrence, waiting_on: input.waiting_on, owner: input.owner }
+}
+
+/// What `stream` and `export_page` match: `filter` without `include_deleted`, every status
+/// (archived included) when it names none.
+fn export_matches(filter: &TodoFilter, t: &Todo) -> bool {
+    filter.status.as_ref().is_none_or(|s| &t.status == s)
+        && filter.completed_after.is_none_or(|after| t.completed_at.is_some_and(|c| c >= after))
+        && filter.tag.as_deref().is_none_or(|tag| t.tags.iter().any(|own| own.to_lowercase() == tag.trim().to_lowercase()))
+        && owned_by(filter.owner.as_deref(), t)
+}
+
+/// Whether `t` counts for an `owner` argument; `None` matches every todo.
+fn owned_by(owner: Option<&str>, t: &Todo) -> bool {
+    owner.is_none_or(|owner| t.owner.as_deref() == Some(owner))
+}
+
+/// Orders like SQLite's `order_by`, `id` breaking ties in the same direction.
+fn sort_todos(todos: &mut [Todo], sort: SortField, order: SortOrder) {
+    todos.sort_by(|a, b| {
+        let by_field = match sort {
+            SortField::UpdatedAt => a.updated_at.cmp(&b.updated_at),
+            SortField::CreatedAt => a.created_at.cmp(&b.created_at),
+            SortField::Title => a.title.cmp(&b.title),
+            // Ascending: low first, then undated, then the latest due date
+            SortField::Priority => a.priority.cmp(&b.priority).then_with(|| match (a.due_at, b.due_at) {
+                (Some(a), Some(b)) => b.cmp(&a),
+                (a, b) => a.is_some().cmp(&b.is_some()),
+            }),
+            SortField::Staleness => {
+                let ordering = b.updated_at.cmp(&a.updated_at).then_with(|| b.id.0.cmp(&a.id.0));
+                let ordering = if order == SortOrder::Desc { ordering.reverse() } else { ordering };
+                let pending = |t: &Todo| t.status == TodoStatus::Pending;
+                return pending(b).cmp(&pending(a)).then(ordering);
+            }
+        };
+        let ordering = by_field.then_with(|| a.id.0.cmp(&b.id.0));
+        if order == SortOrder::Desc { ordering.reverse() } else { ordering }
+    });
+}
+
+#[async_trait]
+impl TodoRepository for InMemoryTodoRepository {
+    async fn init(&self) -> Result<()> { Ok(()) }
+    async fn ping(&self) -> Result<()> { Ok(()) }
+    async fn is_schema_current(&self) -> Result<bool> { Ok(true) }
+    async fn reindex(&self) -> Result<()> { Ok(()) }
+    async fn vacuum(&self) -> Result<()> { Ok(()) }
+    async fn purge_deleted(&self) -> Result<u64> {
+        let mut map = self.items.lock().unwrap();
+        let before = map.len();
+        map.retain(|_, t| t.deleted_at.is_none());
+        Ok((before - map.len()) as u64)
+    }
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let mut map = self.items.lock().unwrap();
+        let before = map.len();
+        map.retain(|_, t| t.deleted_at.is_none_or(|d| d >= cutoff));
+        Ok((before - map.len()) as u64)
+    }
+    async fn import_todos(&self, todos: Vec<Todo>, policy: ConflictPolicy) -> Result<ImportReport> {
+        let mut map = self.items.lock().unwrap();
+        if policy == ConflictPolicy::Fail && todos.iter().any(|t| map.contains_key(&t.id.0.to_string())) {
+            return Err(UniqueViolation { field: "id" }.into());
+        }
+        let code_taken = |map: &HashMap<String, Todo>, t: &Todo| map.values().any(|other| other.code == t.code && other.id != t.id);
+        if policy != ConflictPolicy::Skip && todos.iter().any(|t| code_taken(&map, t)) {
+            return Err(UniqueViolation { field: "code" }.into());
+        }
+        let mut report = ImportReport::default();
+        for todo in todos {
+            let key = todo.id.0.to_string();
+            // Like SQL's `ON CONFLICT DO NOTHING`, skip also covers a clash on the code
+            match (map.contains_key(&key) || code_taken(&map, &todo), policy) {
+                (true, ConflictPolicy::Skip) => { report.skipped += 1; continue; }
+                (true, _) => report.overwritten += 1,
+                (false, _) => report.inserted += 1,
+            }
+            map.insert(key, todo);
+        }
+        Ok(report)
+    }
+    async fn find_inconsistencies(&self) -> Result<Vec<Inconsistency>> {
+        // Deleted todos included, like the SQL scans
+        let mut found: Vec<Inconsistency> = self.items.lock().unwrap().values().flat_map(|t| {
+            let kinds = [
+                (t.status == TodoStatus::Done && t.completed_at.is_none()).then_some(InconsistencyKind::DoneWithoutCompletedAt),
+                (t.status != TodoStatus::Done && t.completed_at.is_some()).then_some(InconsistencyKind::CompletedAtWhileNotDone),
+                t.due_at.is_some_and(|due| due < t.created_at).then_some(InconsistencyKind::DueBeforeCreated),
+            ];
+            kinds.into_iter().flatten().map(|kind| Inconsistency { id: t.id.clone(), kind }).collect::<Vec<_>>()
+        }).collect();
+        found.sort_by_key(|i| i.id.0);
+        Ok(found)
+    }
+    async fn fix_inconsistencies(&self) -> Result<u64> {
+        let mut fixed = 0;
+        for todo in self.items.lock().unwrap().values_mut() {
+            match (&todo.status, todo.completed_at) {
+                // The last update is the best available guess for when it was completed
+                (TodoStatus::Done, None) => todo.completed_at = Some(todo.updated_at),
+                (status, Some(_)) if *status != TodoStatus::Done => todo.completed_at = None,
+                _ => continue,
+            }
+            fixed += 1;
+        }
+        Ok(fixed)
+    }
+    async fn create(&self, input: CreateTodo) -> Result<Todo> {
+        let todo = new_todo(input);
+        self.items.lock().unwrap().insert(todo.id.0.to_string(), todo.clone());
+        Ok(todo)
+    }
+    async fn create_many(&self, inputs: Vec<CreateTodo>) -> Result<Vec<Todo>> {
+        // Nothing here can fail halfway, so inserting one by one is already all-or-nothing
+        let mut todos = Vec::with_capacity(inputs.len());
+        for input in inputs { todos.push(self.create(input).await?); }
+        Ok(todos)
+    }
+    async fn get(&self, id: TodoId, owner: Option<&str>) -> Result<Option<Todo>> {
+        Ok(self.live().get(&id.0.to_string()).filter(|t| owned_by(owner, t)).cloned())
+    }
+    async fn get_including_deleted(&self, id: TodoId, owner: Option<&str>) -> Result<Option<Todo>> {
+        Ok(self.items.lock().unwrap().get(&id.0.to_string()).filter(|t| owned_by(owner, t)).cloned())
+    }
+    async fn get_by_code(&self, code: &str, owner: Option<&str>) -> Result<Option<Todo>> {
+        Ok(self.live().into_values().find(|t| t.code.eq_ignore_ascii_case(code) && owned_by(owner, t)))
+    }
+    async fn get_many(&self, ids: Vec<TodoId>, owner: Option<&str>) -> Result<Vec<Todo>> {
+        let map = self.live();
+        let mut todos: Vec<Todo> = ids.iter().filter_map(|id| map.get(&id.0.to_string()).filter(|t| owned_by(owner, t)).cloned()).collect();
+        todos.sort_by_key(|t| std::cmp::Reverse(t.created_at));
+        Ok(todos)
+    }
+    async fn latest_updated(&self, owner: Option<&str>) -> Result<Option<Todo>> {
+        Ok(self.live().into_values().filter(|t| owned_by(owner, t)).max_by_key(|t| t.updated_at))
+    }
+    async fn list(&self, filter: TodoFilter, options: ListOptions) -> Result<Vec<Todo>> {
+        let todos = if filter.include_deleted { self.items.lock().unwrap().clone() } else { self.live() };
+        let mut todos: Vec<Todo> = todos.into_values()
+            .filter(|t| match &filter.status { Some(s) => &t.status == s, None => t.status != TodoStatus::Archived })
+            .filter(|t| filter.completed_after.is_none_or(|after| t.completed_at.is_some_and(|c| c >= after)))
+            .filter(|t| filter.tag.as_deref().is_none_or(|tag| t.tags.iter().any(|own| own.to_lowercase() == tag.trim().to_lowercase())))
+            .filter(|t| owned_by(filter.owner.as_deref(), t))
+            .collect();
+        sort_todos(&mut todos, options.sort, options.order);
+        Ok(todos)
+    }
+    fn stream(&self, filter: TodoFilter) -> BoxStream<'_, Result<Todo>> {
+        // A snapshot; nothing here is bigger than memory to begin with
+        let mut todos: Vec<Todo> = self.live().into_values().filter(|t| export_matches(&filter, t)).collect();
+        todos.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.id.0.cmp(&b.id.0)));
+        stream::iter(todos).map(Ok).boxed()
+    }
+    async fn list_paged(&self, filter: TodoFilter, options: ListOptions, limit: u32, offset: u32) -> Result<Page<Todo>> {
+        let todos = self.list(filter, options).await?;
+        let total = todos.len() as u64;
+        Ok(Page { items: todos.into_iter().skip(offset as usize).take(limit as usize).collect(), total })
+    }
+    async fn list_archived_paged(&self, sort: SortField, order: SortOrder, limit: u32, offset: u32, owner: Option<&str>) -> Result<Vec<Todo>> {
+        let mut archived: Vec<Todo> = self.live().into_values().filter(|t| t.status == TodoStatus::Archived && owned_by(owner, t)).collect();
+        sort_todos(&mut archived, sort, order);
+        Ok(archived.into_iter().skip(offset as usize).take(limit as usize).collect())
+    }
+    async fn list_stale(&self, cutoff: DateTime<Utc>, status: Option<TodoStatus>, owner: Option<&str>) -> Result<Vec<Todo>> {
+        let mut stale = self.list(TodoFilter { status, owner: owner.map(str::to_string), ..Default::default() }, ListOptions { sort: SortField::UpdatedAt, order: SortOrder::Asc }).await?;
+        stale.retain(|t| t.updated_at < cutoff);
+        Ok(stale)
+    }
+    async fn export_page(&self, filter: TodoFilter, after: Option<TodoId>, limit: u32) -> Result<Vec<Todo>> {
+        let mut todos: Vec<Todo> = self.live().into_values()
+            .filter(|t| export_matches(&filter, t) && after.as_ref().is_none_or(|after| t.id.0 > after.0))
+            .collect();
+        todos.sort_by_key(|t| t.id.0);
+        todos.truncate(limit as usize);
+        Ok(todos)
+    }
+    async fn update(&self, id: TodoId, input: UpdateTodo) -> Result<Option<UpdatedTodo>> {
+        // One lock for the read and the write, so a conditional update can't race another one
+        let mut map = self.items.lock().unwrap();
+        let Some(mut todo) = map.get(&id.0.to_string()).filter(|t| t.deleted_at.is_none()).cloned() else { return Ok(None) };
+        if !input.version_matches(todo.updated_at) { return Err(VersionMismatch.into()); }
+        let before = todo.clone();
+        if let Some(t) = input.title { todo.title = t; }
+        if let Some(d) = input.description { todo.description = d; }
+        if let Some(s) = input.status {
+            todo.completed_at = match (&todo.status, &s) { (TodoStatus::Done, TodoStatus::Done) => todo.completed_at, (_, TodoStatus::Done) => Some(Utc::now()), _ => None };
+            todo.status = s;
+        }
+        if let Some(e) = input.estimate_minutes { todo.estimate_minutes = Some(e); }
+        if let Some(due) = input.due_at { todo.due_at = Some(due); }
+        if let Some(p) = input.priority { todo.priority = p; }
+        if let Some(tags) = input.tags { todo.tags = tags; }
+        if let Some(r) = input.recurrence { todo.recurrence = r; }
+        if let Some(w) = input.waiting_on { todo.waiting_on = w; }
+        todo.updated_at = Utc::now();
+        map.insert(id.0.to_string(), todo.clone());
+        self.record_change(&before, &todo);
+        let next = match todo.next_occurrence(todo.updated_at) {
+            Some(input) if before.status != TodoStatus::Done && todo.status == TodoStatus::Done => Some(new_todo(input)),
+            _ => None,
+        };
+        if let Some(next) = &next { map.insert(next.id.0.to_string(), next.clone()); }
+        Ok(Some(UpdatedTodo { todo, next }))
+    }
+    async fn increment_defer_count(&self, id: TodoId) -> Result<Option<i64>> {
+        let mut map = self.items.lock().unwrap();
+        match map.get_mut(&id.0.to_string()) {
+            Some(todo) if todo.deleted_at.is_none() => {
+                todo.defer_count += 1;
+                todo.updated_at = Utc::now();
+                Ok(Some(todo.defer_count))
+            }
+            _ => Ok(None),
+        }
+    }
+    async fn list_changes(&self, id: TodoId) -> Result<Vec<TodoChange>> {
+        Ok(self.changes.lock().unwrap().get(&id.0.to_string()).cloned().unwrap_or_default())
+    }
+    async fn clear_due(&self, id: TodoId) -> Result<Option<Todo>> {
+        let mut map = self.items.lock().unwrap();
+        match map.get_mut(&id.0.to_string()) {
+            Some(todo) if todo.deleted_at.is_none() => {
+                let before = todo.clone();
+                todo.due_at = None;
+                todo.updated_at = Utc::now();
+                self.record_change(&before, todo);
+                Ok(Some(todo.clone()))
+            }
+            _ => Ok(None),
+        }
+    }
+    async fn restore(&self, id: TodoId) -> Result<Option<Todo>> {
+        let mut map = self.items.lock().unwrap();
+        let Some(todo) = map.get_mut(&id.0.to_string()) else { return Ok(None) };
+        if todo.deleted_at.take().is_some() { todo.updated_at = Utc::now(); }
+        Ok(Some(todo.clone()))
+    }
+    async fn purge(&self, id: TodoId) -> Result<bool> {
+        self.changes.lock().unwrap().remove(&id.0.to_string());
+        Ok(self.items.lock().unwrap().remove(&id.0.to_string()).is_some())
+    }
+    async fn delete(&self, id: TodoId) -> Result<bool> {
+        let mut map = self.items.lock().unwrap();
+        match map.get_mut(&id.0.to_string()) {
+            Some(todo) if todo.deleted_at.is_none() => { todo.deleted_at = Some(Utc::now()); Ok(true) }
+            _ => Ok(false),
+        }
+    }
+    async fn workload(&self, status: Option<TodoStatus>, owner: Option<&str>) -> Result<Workload> {
+        let map = self.live();
+        let matching = map.values().filter(|t| status.as_ref().is_none_or(|s| &t.status == s) && owned_by(owner, t));
+        Ok(matching.fold(Workload::default(), |w, t| Workload { total_minutes: w.total_minutes + t.estimate_minutes.unwrap_or(0), count: w.count + 1 }))
+    }
+    async fn count_by_status(&self, owner: Option<&str>) -> Result<StatusCounts> {
+        let mut counts = StatusCounts::default();
+        for todo in self.live().values().filter(|t| owned_by(owner, t)) { counts.add(&todo.status, 1); }
+        Ok(counts)
+    }
+    async fn completions_by_week(&self, now: DateTime<Utc>, weeks: u32, owner: Option<&str>) -> Result<Vec<(u32, i64)>> {
+        let map = self.live();
+        let mut counts = BTreeMap::new();
+        for done in map.values().filter(|t| owned_by(owner, t)).filter_map(|t| t.completed_at) {
+            let ago = (now - done).num_weeks();
+            if (0..i64::from(weeks)).contains(&ago) { *counts.entry(ago as u32).or_insert(0) += 1; }
+        }
+        Ok(counts.into_iter().collect())
+    }
+}
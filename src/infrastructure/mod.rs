@@ -1 +1,8 @@
+pub mod backup;
+pub mod memory_repo;
+#[cfg(feature = "postgres")]
+pub mod postgres_repo;
+pub mod query_builder;
+#[cfg(test)]
+mod query_builder_tests;
 pub mod sqlite_repo;
@@ -0,0 +1,584 @@
+//! PostgreSQL implementation of [`TodoRepository`], built with the `postgres` feature.
+//! Ids are native `uuid`s and timestamps `timestamptz`; otherwise it mirrors the SQLite one.
+
+use std::sync::{Arc, LazyLock};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::{stream::BoxStream, StreamExt};
+use sqlx::{postgres::{PgConnection, PgPoolOptions, PgRow}, Acquire, Executor, PgPool, Postgres, Row};
+use uuid::Uuid;
+
+use super::query_builder::{interned, order_by};
+use super::sqlite_repo::{completed_at_after, status_str, unique_violation, with_unique_code};
+use crate::domain::{
+    repository::TodoRepository,
+    todo::{ConflictPolicy, CreateTodo, ImportReport, Inconsistency, InconsistencyKind, ListOptions, Page, Priority, Recurrence, SortField, SortOrder, StatusCounts, Todo, TodoChange, TodoFilter, TodoId, TodoStatus, UpdateTodo, UpdatedTodo, VersionMismatch, Workload},
+};
+
+const TODO_COLUMNS: &str = "id, title, description, status, code, estimate_minutes, created_at, updated_at, completed_at, deleted_at, due_at, priority, defer_count, recurrence, waiting_on, owner";
+/// `TODO_COLUMNS` plus the todo's tags as an array, for reads.
+static SELECT_COLUMNS: LazyLock<String> = LazyLock::new(|| {
+    format!("{}, ARRAY(SELECT tag FROM todo_tags WHERE todo_id = todos.id ORDER BY lower(tag)) AS tags", TODO_COLUMNS)
+});
+
+#[derive(Clone)]
+pub struct PostgresTodoRepository {
+    pool: Arc<PgPool>,
+}
+
+impl PostgresTodoRepository {
+    pub async fn connect(database_url: &str) -> Result<Self> {
+        let pool = PgPoolOptions::new().max_connections(10).connect(database_url).await?;
+        Ok(Self { pool: Arc::new(pool) })
+    }
+}
+
+/// What `WhereClause::from_filter` builds for SQLite, with `$n` placeholders numbered from
+/// `first`. Arguments are text; timestamps are cast on the server.
+fn filter_clause(filter: &TodoFilter, first: usize) -> (String, Vec<String>) { matching(filter, false, first) }
+
+/// What `WhereClause::for_export` builds for SQLite: [`filter_clause`], except that without a
+/// status every status matches, archived included.
+fn export_clause(filter: &TodoFilter, first: usize) -> (String, Vec<String>) { matching(filter, true, first) }
+
+fn matching(filter: &TodoFilter, every_status: bool, first: usize) -> (String, Vec<String>) {
+    let mut predicates = Vec::new();
+    let mut args = Vec::new();
+    let mut push = |predicate: &str, arg: String| {
+        predicates.push(predicate.replace('$', &format!("${}", first + args.len())));
+        args.push(arg);
+    };
+    match &filter.status {
+        Some(status) => push("status = $", status_str(status).to_string()),
+        None if every_status => {}
+        None => push("status != $", status_str(&TodoStatus::Archived).to_string()),
+    }
+    if let Some(after) = filter.completed_after { push("completed_at >= $::timestamptz", after.to_rfc3339()); }
+    if let Some(tag) = &filter.tag { push("id IN (SELECT todo_id FROM todo_tags WHERE lower(tag) = lower($))", tag.trim().to_string()); }
+    if let Some(owner) = &filter.owner { push("owner = $", owner.clone()); }
+    if !filter.include_deleted { predicates.insert(0, "deleted_at IS NULL".to_string()); }
+    (format!("WHERE {}", predicates.join(" AND ")), args)
+}
+
+#[async_trait]
+impl TodoRepository for PostgresTodoRepository {
+    async fn init(&self) -> Result<()> {
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS todos (
+                id UUID PRIMARY KEY,
+                title TEXT NOT NULL,
+                description TEXT,
+                status TEXT NOT NULL,
+                code TEXT,
+                estimate_minutes BIGINT,
+                created_at TIMESTAMPTZ NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL,
+                completed_at TIMESTAMPTZ,
+                deleted_at TIMESTAMPTZ,
+                due_at TIMESTAMPTZ,
+                priority TEXT NOT NULL DEFAULT 'medium',
+                defer_count BIGINT NOT NULL DEFAULT 0,
+                recurrence TEXT,
+                waiting_on TEXT,
+                owner TEXT
+            )",
+        )
+        .execute(&*self.pool)
+        .await?;
+        // For tables created before the column existed
+        sqlx::query("ALTER TABLE todos ADD COLUMN IF NOT EXISTS owner TEXT").execute(&*self.pool).await?;
+        sqlx::query("CREATE TABLE IF NOT EXISTS todo_tags (todo_id UUID NOT NULL REFERENCES todos(id) ON DELETE CASCADE, tag TEXT NOT NULL)")
+            .execute(&*self.pool)
+            .await?;
+        sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_todo_tags_todo_tag ON todo_tags(todo_id, lower(tag))").execute(&*self.pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_todo_tags_tag ON todo_tags(lower(tag))").execute(&*self.pool).await?;
+        sqlx::query("CREATE TABLE IF NOT EXISTS todo_changes (id BIGSERIAL PRIMARY KEY, todo_id UUID NOT NULL REFERENCES todos(id) ON DELETE CASCADE, changed_at TIMESTAMPTZ NOT NULL, fields TEXT NOT NULL)")
+            .execute(&*self.pool)
+            .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_todo_changes_todo_id ON todo_changes(todo_id)").execute(&*self.pool).await?;
+        sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_todos_code ON todos(code)").execute(&*self.pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_todos_updated_at ON todos(updated_at)").execute(&*self.pool).await?;
+        Ok(())
+    }
+
+    async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&*self.pool).await?;
+        Ok(())
+    }
+
+    async fn is_schema_current(&self) -> Result<bool> {
+        let rows = sqlx::query("SELECT column_name::text AS name FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = 'todos'")
+            .fetch_all(&*self.pool)
+            .await?;
+        let present: Vec<String> = rows.iter().map(|r| r.get("name")).collect();
+        Ok(TODO_COLUMNS.split(", ").all(|c| present.iter().any(|p| p == c)))
+    }
+
+    async fn reindex(&self) -> Result<()> {
+        sqlx::query("REINDEX TABLE todos").execute(&*self.pool).await?;
+        sqlx::query("ANALYZE todos").execute(&*self.pool).await?;
+        Ok(())
+    }
+
+    async fn vacuum(&self) -> Result<()> {
+        // VACUUM refuses to run in a transaction block, so send it without bind parameters
+        (&*self.pool).execute("VACUUM todos").await?;
+        Ok(())
+    }
+
+    async fn purge_deleted(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM todos WHERE deleted_at IS NOT NULL").execute(&*self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM todos WHERE deleted_at < $1").bind(cutoff).execute(&*self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn import_todos(&self, todos: Vec<Todo>, policy: ConflictPolicy) -> Result<ImportReport> {
+        let on_conflict = match policy {
+            ConflictPolicy::Skip => "ON CONFLICT DO NOTHING",
+            ConflictPolicy::Overwrite => "ON CONFLICT(id) DO UPDATE SET title = excluded.title, description = excluded.description, status = excluded.status,
+                code = excluded.code, estimate_minutes = excluded.estimate_minutes, created_at = excluded.created_at, updated_at = excluded.updated_at,
+                completed_at = excluded.completed_at, deleted_at = excluded.deleted_at, due_at = excluded.due_at, priority = excluded.priority,
+                defer_count = excluded.defer_count, recurrence = excluded.recurrence, waiting_on = excluded.waiting_on,
+                owner = excluded.owner",
+            ConflictPolicy::Fail => "",
+        };
+        let sql = format!("INSERT INTO todos ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16) {}", TODO_COLUMNS, on_conflict);
+        let mut report = ImportReport::default();
+        let mut tx = self.pool.begin().await?;
+        for todo in &todos {
+            let exists = sqlx::query("SELECT 1 FROM todos WHERE id = $1").bind(todo.id.0).fetch_optional(&mut *tx).await?.is_some();
+            let result = sqlx::query(&sql)
+                .bind(todo.id.0)
+                .bind(&todo.title)
+                .bind(&todo.description)
+                .bind(status_str(&todo.status))
+                .bind(&todo.code)
+                .bind(todo.estimate_minutes)
+                .bind(todo.created_at)
+                .bind(todo.updated_at)
+                .bind(todo.completed_at)
+                .bind(todo.deleted_at)
+                .bind(todo.due_at)
+                .bind(todo.priority.as_str())
+                .bind(todo.defer_count)
+                .bind(todo.recurrence.map(|r| r.as_str()))
+                .bind(&todo.waiting_on)
+                .bind(&todo.owner)
+                .execute(&mut *tx)
+                .await
+                .map_err(unique_violation)?;
+            if result.rows_affected() > 0 { replace_tags(&mut tx, &todo.id, &todo.tags).await?; }
+            match (result.rows_affected(), exists) {
+                (0, _) => report.skipped += 1,
+                (_, true) => report.overwritten += 1,
+                (_, false) => report.inserted += 1,
+            }
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn find_inconsistencies(&self) -> Result<Vec<Inconsistency>> {
+        let rows = sqlx::query(
+            "SELECT id, 'done_without_completed_at' AS kind FROM todos WHERE status = 'done' AND completed_at IS NULL
+             UNION ALL
+             SELECT id, 'completed_at_while_not_done' AS kind FROM todos WHERE status != 'done' AND completed_at IS NOT NULL
+             UNION ALL
+             SELECT id, 'due_before_created' AS kind FROM todos WHERE due_at < created_at
+             ORDER BY id",
+        )
+        .fetch_all(&*self.pool)
+        .await?;
+        Ok(rows.iter().map(|r| {
+            let kind = match r.get::<&str, _>("kind") {
+                "done_without_completed_at" => InconsistencyKind::DoneWithoutCompletedAt,
+                "due_before_created" => InconsistencyKind::DueBeforeCreated,
+                _ => InconsistencyKind::CompletedAtWhileNotDone,
+            };
+            Inconsistency { id: TodoId(r.get("id")), kind }
+        }).collect())
+    }
+
+    async fn fix_inconsistencies(&self) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        let done = sqlx::query("UPDATE todos SET completed_at = updated_at WHERE status = 'done' AND completed_at IS NULL")
+            .execute(&mut *tx).await?.rows_affected();
+        let not_done = sqlx::query("UPDATE todos SET completed_at = NULL WHERE status != 'done' AND completed_at IS NOT NULL")
+            .execute(&mut *tx).await?.rows_affected();
+        tx.commit().await?;
+        Ok(done + not_done)
+    }
+
+    async fn create(&self, input: CreateTodo) -> Result<Todo> {
+        let tx = tokio::sync::Mutex::new(self.pool.begin().await?);
+        let todo = insert_todo(&tx, input).await?;
+        tx.into_inner().commit().await?;
+        Ok(todo)
+    }
+
+    async fn create_many(&self, inputs: Vec<CreateTodo>) -> Result<Vec<Todo>> {
+        let tx = tokio::sync::Mutex::new(self.pool.begin().await?);
+        let mut todos = Vec::with_capacity(inputs.len());
+        for input in inputs { todos.push(insert_todo(&tx, input).await?); }
+        tx.into_inner().commit().await?;
+        Ok(todos)
+    }
+
+    async fn get(&self, id: TodoId, owner: Option<&str>) -> Result<Option<Todo>> {
+        let row = sqlx::query(&format!("SELECT {} FROM todos WHERE id = $1 AND deleted_at IS NULL AND ($2::TEXT IS NULL OR owner = $2)", *SELECT_COLUMNS))
+            .bind(id.0)
+            .bind(owner)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.map(row_to_todo))
+    }
+
+    async fn get_including_deleted(&self, id: TodoId, owner: Option<&str>) -> Result<Option<Todo>> {
+        let row = sqlx::query(&format!("SELECT {} FROM todos WHERE id = $1 AND ($2::TEXT IS NULL OR owner = $2)", *SELECT_COLUMNS))
+            .bind(id.0)
+            .bind(owner)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.map(row_to_todo))
+    }
+
+    async fn get_by_code(&self, code: &str, owner: Option<&str>) -> Result<Option<Todo>> {
+        let row = sqlx::query(&format!("SELECT {} FROM todos WHERE code = $1 AND deleted_at IS NULL AND ($2::TEXT IS NULL OR owner = $2)", *SELECT_COLUMNS))
+            .bind(code.to_ascii_uppercase())
+            .bind(owner)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.map(row_to_todo))
+    }
+
+    async fn get_many(&self, ids: Vec<TodoId>, owner: Option<&str>) -> Result<Vec<Todo>> {
+        // One array parameter, so no chunking is needed
+        let ids: Vec<Uuid> = ids.into_iter().map(|id| id.0).collect();
+        let rows = sqlx::query(&format!("SELECT {} FROM todos WHERE id = ANY($1) AND deleted_at IS NULL AND ($2::TEXT IS NULL OR owner = $2) ORDER BY created_at DESC", *SELECT_COLUMNS))
+            .bind(ids)
+            .bind(owner)
+            .fetch_all(&*self.pool)
+            .await?;
+        Ok(rows.into_iter().map(row_to_todo).collect())
+    }
+
+    async fn latest_updated(&self, owner: Option<&str>) -> Result<Option<Todo>> {
+        let row = sqlx::query(&format!("SELECT {} FROM todos WHERE deleted_at IS NULL AND ($1::TEXT IS NULL OR owner = $1) ORDER BY updated_at DESC LIMIT 1", *SELECT_COLUMNS))
+            .bind(owner)
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.map(row_to_todo))
+    }
+
+    async fn list(&self, filter: TodoFilter, options: ListOptions) -> Result<Vec<Todo>> {
+        let (clause, args) = filter_clause(&filter, 1);
+        let sql = format!("SELECT {} FROM todos {} {}", *SELECT_COLUMNS, clause, order_by(options.sort, options.order));
+        let mut query = sqlx::query(&sql);
+        for arg in &args { query = query.bind(arg); }
+        Ok(query.fetch_all(&*self.pool).await?.into_iter().map(row_to_todo).collect())
+    }
+
+    fn stream(&self, filter: TodoFilter) -> BoxStream<'_, Result<Todo>> {
+        let (clause, args) = export_clause(&TodoFilter { include_deleted: false, ..filter }, 1);
+        let mut query = sqlx::query(interned(format!("SELECT {} FROM todos {} ORDER BY created_at, id", *SELECT_COLUMNS, clause)));
+        for arg in args { query = query.bind(arg); }
+        query.fetch(&*self.pool).map(|row| Ok(row_to_todo(row?))).boxed()
+    }
+
+    async fn list_paged(&self, filter: TodoFilter, options: ListOptions, limit: u32, offset: u32) -> Result<Page<Todo>> {
+        let (clause, args) = filter_clause(&filter, 1);
+        let mut tx = self.pool.begin().await?;
+        let sql = format!("SELECT {} FROM todos {} {} LIMIT ${} OFFSET ${}", *SELECT_COLUMNS, clause, order_by(options.sort, options.order), args.len() + 1, args.len() + 2);
+        let mut query = sqlx::query(&sql);
+        for arg in &args { query = query.bind(arg); }
+        let rows = query.bind(i64::from(limit)).bind(i64::from(offset)).fetch_all(&mut *tx).await?;
+        let count_sql = format!("SELECT COUNT(*) AS total FROM todos {}", clause);
+        let mut count = sqlx::query(&count_sql);
+        for arg in &args { count = count.bind(arg); }
+        let total: i64 = count.fetch_one(&mut *tx).await?.get("total");
+        tx.commit().await?;
+        Ok(Page { items: rows.into_iter().map(row_to_todo).collect(), total: total as u64 })
+    }
+
+    async fn list_archived_paged(&self, sort: SortField, order: SortOrder, limit: u32, offset: u32, owner: Option<&str>) -> Result<Vec<Todo>> {
+        let sql = format!("SELECT {} FROM todos WHERE deleted_at IS NULL AND status = $1 AND ($4::TEXT IS NULL OR owner = $4) {} LIMIT $2 OFFSET $3", *SELECT_COLUMNS, order_by(sort, order));
+        let rows = sqlx::query(&sql)
+            .bind(status_str(&TodoStatus::Archived))
+            .bind(i64::from(limit))
+            .bind(i64::from(offset))
+            .bind(owner)
+            .fetch_all(&*self.pool)
+            .await?;
+        Ok(rows.into_iter().map(row_to_todo).collect())
+    }
+
+    async fn list_stale(&self, cutoff: DateTime<Utc>, status: Option<TodoStatus>, owner: Option<&str>) -> Result<Vec<Todo>> {
+        let (clause, args) = filter_clause(&TodoFilter { status, owner: owner.map(str::to_string), ..Default::default() }, 1);
+        let sql = format!("SELECT {} FROM todos {} AND updated_at < ${} {}", *SELECT_COLUMNS, clause, args.len() + 1, order_by(SortField::UpdatedAt, SortOrder::Asc));
+        let mut query = sqlx::query(&sql);
+        for arg in &args { query = query.bind(arg); }
+        Ok(query.bind(cutoff).fetch_all(&*self.pool).await?.into_iter().map(row_to_todo).collect())
+    }
+
+    async fn export_page(&self, filter: TodoFilter, after: Option<TodoId>, limit: u32) -> Result<Vec<Todo>> {
+        let (clause, args) = export_clause(&TodoFilter { include_deleted: false, ..filter }, 1);
+        let sql = format!("SELECT {} FROM todos {} AND (${}::UUID IS NULL OR id > ${}) ORDER BY id LIMIT ${}", *SELECT_COLUMNS, clause, args.len() + 1, args.len() + 1, args.len() + 2);
+        let mut query = sqlx::query(&sql);
+        for arg in &args { query = query.bind(arg); }
+        let rows = query.bind(after.map(|id| id.0)).bind(i64::from(limit)).fetch_all(&*self.pool).await?;
+        Ok(rows.into_iter().map(row_to_todo).collect())
+    }
+
+    async fn update(&self, id: TodoId, input: UpdateTodo) -> Result<Option<UpdatedTodo>> {
+        let mut tx = self.pool.begin().await?;
+        // Locks the row until commit, so nothing changes it between the read and the update
+        let row = sqlx::query(&format!("SELECT {} FROM todos WHERE id = $1 AND deleted_at IS NULL FOR UPDATE", *SELECT_COLUMNS))
+            .bind(id.0)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let Some(mut todo) = row.map(row_to_todo) else { return Ok(None) };
+        if !input.version_matches(todo.updated_at) { return Err(VersionMismatch.into()); }
+        let before = todo.clone();
+
+        if let Some(t) = input.title { todo.title = t; }
+        if let Some(d) = input.description { todo.description = d; }
+        if let Some(s) = input.status {
+            todo.completed_at = completed_at_after(&todo, &s);
+            todo.status = s;
+        }
+        if let Some(e) = input.estimate_minutes { todo.estimate_minutes = Some(e); }
+        if let Some(due) = input.due_at { todo.due_at = Some(due); }
+        if let Some(p) = input.priority { todo.priority = p; }
+        if let Some(tags) = input.tags { todo.tags = tags; }
+        if let Some(r) = input.recurrence { todo.recurrence = r; }
+        if let Some(w) = input.waiting_on { todo.waiting_on = w; }
+        todo.updated_at = Utc::now();
+
+        sqlx::query("UPDATE todos SET title = $2, description = $3, status = $4, estimate_minutes = $5, updated_at = $6, completed_at = $7, due_at = $8, priority = $9, recurrence = $10, waiting_on = $11 WHERE id = $1")
+            .bind(todo.id.0)
+            .bind(&todo.title)
+            .bind(&todo.description)
+            .bind(status_str(&todo.status))
+            .bind(todo.estimate_minutes)
+            .bind(todo.updated_at)
+            .bind(todo.completed_at)
+            .bind(todo.due_at)
+            .bind(todo.priority.as_str())
+            .bind(todo.recurrence.map(|r| r.as_str()))
+            .bind(&todo.waiting_on)
+            .execute(&mut *tx)
+            .await?;
+        replace_tags(&mut tx, &todo.id, &todo.tags).await?;
+        record_change(&mut tx, &before, &todo).await?;
+        let tx = tokio::sync::Mutex::new(tx);
+        let next = match todo.next_occurrence(todo.updated_at) {
+            Some(input) if before.status != TodoStatus::Done && todo.status == TodoStatus::Done => Some(insert_todo(&tx, input).await?),
+            _ => None,
+        };
+        tx.into_inner().commit().await?;
+
+        Ok(Some(UpdatedTodo { todo, next }))
+    }
+
+    async fn increment_defer_count(&self, id: TodoId) -> Result<Option<i64>> {
+        let row = sqlx::query("UPDATE todos SET defer_count = defer_count + 1, updated_at = $2 WHERE id = $1 AND deleted_at IS NULL RETURNING defer_count")
+            .bind(id.0)
+            .bind(Utc::now())
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.map(|r| r.get("defer_count")))
+    }
+
+    async fn list_changes(&self, id: TodoId) -> Result<Vec<TodoChange>> {
+        let rows = sqlx::query("SELECT changed_at, fields FROM todo_changes WHERE todo_id = $1 ORDER BY id")
+            .bind(id.0)
+            .fetch_all(&*self.pool)
+            .await?;
+        rows.iter().map(|row| {
+            let fields: String = row.get("fields");
+            Ok(TodoChange { changed_at: row.get("changed_at"), fields: serde_json::from_str(&fields)? })
+        }).collect()
+    }
+
+    async fn clear_due(&self, id: TodoId) -> Result<Option<Todo>> {
+        let mut tx = self.pool.begin().await?;
+        let row = sqlx::query(&format!("SELECT {} FROM todos WHERE id = $1 AND deleted_at IS NULL FOR UPDATE", *SELECT_COLUMNS))
+            .bind(id.0)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let Some(before) = row.map(row_to_todo) else { return Ok(None) };
+        let todo = Todo { due_at: None, updated_at: Utc::now(), ..before.clone() };
+        sqlx::query("UPDATE todos SET due_at = NULL, updated_at = $2 WHERE id = $1")
+            .bind(id.0)
+            .bind(todo.updated_at)
+            .execute(&mut *tx)
+            .await?;
+        record_change(&mut tx, &before, &todo).await?;
+        tx.commit().await?;
+        Ok(Some(todo))
+    }
+
+    async fn restore(&self, id: TodoId) -> Result<Option<Todo>> {
+        sqlx::query("UPDATE todos SET deleted_at = NULL, updated_at = $2 WHERE id = $1 AND deleted_at IS NOT NULL")
+            .bind(id.0)
+            .bind(Utc::now())
+            .execute(&*self.pool)
+            .await?;
+        self.get(id, None).await
+    }
+
+    async fn purge(&self, id: TodoId) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM todos WHERE id = $1").bind(id.0).execute(&*self.pool).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, id: TodoId) -> Result<bool> {
+        let result = sqlx::query("UPDATE todos SET deleted_at = $2 WHERE id = $1 AND deleted_at IS NULL")
+            .bind(id.0)
+            .bind(Utc::now())
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn workload(&self, status: Option<TodoStatus>, owner: Option<&str>) -> Result<Workload> {
+        // Unlike `list`, no status means every status, archived included
+        let row = sqlx::query("SELECT COALESCE(SUM(estimate_minutes), 0)::BIGINT AS total_minutes, COUNT(*) AS count FROM todos
+                               WHERE deleted_at IS NULL AND ($1::TEXT IS NULL OR status = $1) AND ($2::TEXT IS NULL OR owner = $2)")
+            .bind(status.as_ref().map(status_str))
+            .bind(owner)
+            .fetch_one(&*self.pool)
+            .await?;
+        Ok(Workload { total_minutes: row.get("total_minutes"), count: row.get("count") })
+    }
+
+    async fn count_by_status(&self, owner: Option<&str>) -> Result<StatusCounts> {
+        let rows = sqlx::query("SELECT status, COUNT(*) AS count FROM todos WHERE deleted_at IS NULL AND ($1::TEXT IS NULL OR owner = $1) GROUP BY status")
+            .bind(owner)
+            .fetch_all(&*self.pool)
+            .await?;
+        let mut counts = StatusCounts::default();
+        for row in rows {
+            let status: String = row.get("status");
+            counts.add(&TodoStatus::ALL.into_iter().find(|s| s.as_str() == status).unwrap_or(TodoStatus::Pending), row.get("count"));
+        }
+        Ok(counts)
+    }
+
+    async fn completions_by_week(&self, now: DateTime<Utc>, weeks: u32, owner: Option<&str>) -> Result<Vec<(u32, i64)>> {
+        let rows = sqlx::query(
+            "SELECT FLOOR(EXTRACT(EPOCH FROM ($1 - completed_at)) / 604800)::BIGINT AS weeks_ago, COUNT(*) AS completed
+             FROM todos
+             WHERE deleted_at IS NULL AND completed_at IS NOT NULL AND completed_at > $1 - make_interval(weeks => $2) AND completed_at <= $1
+               AND ($3::TEXT IS NULL OR owner = $3)
+             GROUP BY weeks_ago",
+        )
+        .bind(now)
+        .bind(weeks as i32)
+        .bind(owner)
+        .fetch_all(&*self.pool)
+        .await?;
+        Ok(rows.iter().map(|r| (r.get::<i64, _>("weeks_ago") as u32, r.get("completed"))).collect())
+    }
+}
+
+/// Inserts a new todo and its tags in `tx`. Any error aborts a Postgres transaction, so each
+/// code attempt runs in its own savepoint and a collision only rolls that attempt back.
+async fn insert_todo(tx: &tokio::sync::Mutex<sqlx::Transaction<'_, Postgres>>, input: CreateTodo) -> Result<Todo> {
+    let now = Utc::now();
+    let id = TodoId(Uuid::new_v4());
+    let status = TodoStatus::Pending;
+    let description = input.description.flatten();
+    let priority = input.priority.unwrap_or_default();
+    let code = with_unique_code(|code| {
+        let (id, title, tags, description, status, waiting_on, owner) = (&id, &input.title, &input.tags, &description, status_str(&status), &input.waiting_on, &input.owner);
+        async move {
+            let mut tx = tx.lock().await;
+            let mut savepoint = tx.begin().await?;
+            // One statement for the row and its tags
+            sqlx::query(
+                "WITH inserted AS (
+                     INSERT INTO todos (id, title, description, status, code, estimate_minutes, created_at, updated_at, due_at, priority, recurrence, waiting_on, owner)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) RETURNING id
+                 )
+                 INSERT INTO todo_tags (todo_id, tag) SELECT inserted.id, tag FROM inserted, unnest($14::TEXT[]) AS tag ON CONFLICT DO NOTHING",
+            )
+            .bind(id.0)
+            .bind(title)
+            .bind(description)
+            .bind(status)
+            .bind(code)
+            .bind(input.estimate_minutes)
+            .bind(now)
+            .bind(now)
+            .bind(input.due_at)
+            .bind(priority.as_str())
+            .bind(input.recurrence.map(|r| r.as_str()))
+            .bind(waiting_on)
+            .bind(owner)
+            .bind(tags)
+            .execute(&mut *savepoint)
+            .await?;
+            savepoint.commit().await
+        }
+    })
+    .await?;
+    Ok(Todo { id, title: input.title, description, status, code, estimate_minutes: input.estimate_minutes, created_at: now, updated_at: now, completed_at: None, deleted_at: None, due_at: input.due_at, priority, defer_count: 0, tags: input.tags, recurrence: input.recurrence, waiting_on: input.waiting_on, owner: input.owner })
+}
+
+/// Appends what changed between `before` and `after` to the changelog; a write that changed
+/// nothing leaves no entry.
+async fn record_change(conn: &mut PgConnection, before: &Todo, after: &Todo) -> Result<()> {
+    let Some(change) = TodoChange::between(before, after) else { return Ok(()) };
+    sqlx::query("INSERT INTO todo_changes (todo_id, changed_at, fields) VALUES ($1, $2, $3)")
+        .bind(after.id.0)
+        .bind(change.changed_at)
+        .bind(serde_json::Value::Object(change.fields).to_string())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Makes `tags` the todo's complete set of tags.
+async fn replace_tags(conn: &mut PgConnection, id: &TodoId, tags: &[String]) -> Result<()> {
+    sqlx::query("DELETE FROM todo_tags WHERE todo_id = $1").bind(id.0).execute(&mut *conn).await?;
+    sqlx::query("INSERT INTO todo_tags (todo_id, tag) SELECT $1, tag FROM unnest($2::TEXT[]) AS tag ON CONFLICT DO NOTHING")
+        .bind(id.0)
+        .bind(tags)
+        .execute(&mut *conn)
+        .await?;
+    Ok(())
+}
+
+/// Column types are enforced by Postgres, so unlike SQLite rows there is nothing to parse.
+fn row_to_todo(row: PgRow) -> Todo {
+    let status_str: String = row.get("status");
+    let priority_str: String = row.get("priority");
+    let recurrence_str: Option<String> = row.get("recurrence");
+    Todo {
+        id: TodoId(row.get("id")),
+        title: row.get("title"),
+        description: row.get("description"),
+        status: TodoStatus::ALL.into_iter().find(|s| s.as_str() == status_str).unwrap_or(TodoStatus::Pending),
+        code: row.get::<Option<String>, _>("code").unwrap_or_default(),
+        estimate_minutes: row.get("estimate_minutes"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        completed_at: row.get("completed_at"),
+        deleted_at: row.get("deleted_at"),
+        due_at: row.get("due_at"),
+        // Unknown values (e.g. written by a newer version) read as the default
+        priority: Priority::ALL.into_iter().find(|p| p.as_str() == priority_str).unwrap_or_default(),
+        defer_count: row.get("defer_count"),
+        tags: row.get("tags"),
+        recurrence: recurrence_str.and_then(|s| Recurrence::ALL.into_iter().find(|r| r.as_str() == s)),
+        waiting_on: row.get("waiting_on"),
+        owner: row.get("owner"),
+    }
+}
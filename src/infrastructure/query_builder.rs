@@ -0,0 +1,122 @@
+//! Parameterized `WHERE` clauses for queries over `todos`. Values only ever reach SQLite
+//! as bound arguments, never as SQL text.
+
+use std::{collections::HashSet, sync::{LazyLock, Mutex}};
+
+use sqlx::{query::Query, sqlite::SqliteArguments, Sqlite};
+
+use crate::domain::todo::{Priority, SortField, SortOrder, TodoFilter, TodoStatus};
+
+use super::sqlite_repo::status_str;
+
+/// Predicates joined with `AND`; starts out matching every todo that isn't deleted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WhereClause {
+    predicates: Vec<String>,
+    args: Vec<String>,
+}
+
+impl Default for WhereClause {
+    fn default() -> Self { Self { predicates: vec!["deleted_at IS NULL".into()], args: Vec::new() } }
+}
+
+impl WhereClause {
+    /// Matches every todo, deleted ones included.
+    pub fn including_deleted() -> Self { Self { predicates: Vec::new(), args: Vec::new() } }
+
+    /// What `list` matches for `filter`: archived todos only when asked for by status,
+    /// deleted ones only with `include_deleted`.
+    pub fn from_filter(filter: &TodoFilter) -> Self { Self::matching(filter, false) }
+
+    /// What the exports match for `filter`: like [`from_filter`](Self::from_filter), except
+    /// that without a status every status matches, archived included.
+    pub fn for_export(filter: &TodoFilter) -> Self { Self::matching(filter, true) }
+
+    fn matching(filter: &TodoFilter, every_status: bool) -> Self {
+        let base = if filter.include_deleted { Self::including_deleted() } else { Self::default() };
+        let clause = match &filter.status {
+            Some(status) => base.status(status),
+            None if every_status => base,
+            None => base.and("status != ?", status_str(&TodoStatus::Archived)),
+        };
+        let clause = match filter.completed_after {
+            Some(after) => clause.and("julianday(completed_at) >= julianday(?)", after.to_rfc3339()),
+            None => clause,
+        };
+        let clause = match &filter.tag {
+            // `todo_tags.tag` is COLLATE NOCASE, so this ignores case
+            Some(tag) => clause.and("id IN (SELECT todo_id FROM todo_tags WHERE tag = ?)", tag.trim()),
+            None => clause,
+        };
+        clause.owner(filter.owner.as_deref())
+    }
+
+    /// Adds `predicate`, which must contain exactly one `?` for `arg`.
+    pub fn and(mut self, predicate: &str, arg: impl Into<String>) -> Self {
+        debug_assert_eq!(predicate.matches('?').count(), 1, "{}", predicate);
+        self.predicates.push(predicate.into());
+        self.args.push(arg.into());
+        self
+    }
+
+    pub fn status(self, status: &TodoStatus) -> Self { self.and("status = ?", status_str(status)) }
+
+    /// Only todos created by `owner`; `None` adds nothing.
+    pub fn owner(self, owner: Option<&str>) -> Self {
+        match owner { Some(owner) => self.and("owner = ?", owner), None => self }
+    }
+
+    /// The clause including the `WHERE` keyword, with one anonymous `?` per argument;
+    /// empty when there are no predicates.
+    pub fn sql(&self) -> String {
+        if self.predicates.is_empty() { return String::new(); }
+        format!("WHERE {}", self.predicates.join(" AND "))
+    }
+
+    pub fn args(&self) -> &[String] { &self.args }
+
+    /// Binds the arguments in order; bind anything placed after the clause afterwards. The
+    /// query owns copies, so it can outlive the clause.
+    pub fn bind<'q>(&self, mut query: Query<'q, Sqlite, SqliteArguments<'q>>) -> Query<'q, Sqlite, SqliteArguments<'q>> {
+        for arg in &self.args { query = query.bind(arg.clone()); }
+        query
+    }
+}
+
+/// `sql` with a `'static` lifetime, for queries that outlive the call building them (such as a
+/// repository's `stream`). Each distinct text is leaked once; only pass SQL built from a fixed
+/// set of shapes, e.g. by [`WhereClause`], never text containing values.
+pub fn interned(sql: String) -> &'static str {
+    static SEEN: LazyLock<Mutex<HashSet<&'static str>>> = LazyLock::new(Default::default);
+    let mut seen = SEEN.lock().unwrap();
+    if let Some(&known) = seen.get(sql.as_str()) { return known; }
+    let leaked: &'static str = Box::leak(sql.into_boxed_str());
+    seen.insert(leaked);
+    leaked
+}
+
+/// `ORDER BY` for a listing. Columns come from a fixed mapping of [`SortField`], and the status and
+/// priorities from [`status_str`] and [`Priority::as_str`], never from request text; `id` breaks
+/// ties so pages don't overlap when sort values repeat.
+pub fn order_by(sort: SortField, order: SortOrder) -> String {
+    let direction = match order { SortOrder::Asc => "ASC", SortOrder::Desc => "DESC" };
+    let column = match sort {
+        SortField::UpdatedAt => "updated_at",
+        SortField::CreatedAt => "created_at",
+        SortField::Title => "title",
+        SortField::Staleness => {
+            // More stale means an older `updated_at`, so the direction flips; pending always leads
+            let direction = match order { SortOrder::Asc => "DESC", SortOrder::Desc => "ASC" };
+            return format!("ORDER BY (status = '{}') DESC, updated_at {}, id {}", status_str(&TodoStatus::Pending), direction, direction);
+        }
+        SortField::Priority => {
+            // Sooner due dates lead within a priority, so they run against the direction
+            let reverse = match order { SortOrder::Asc => "DESC", SortOrder::Desc => "ASC" };
+            return format!(
+                "ORDER BY CASE priority WHEN '{}' THEN 2 WHEN '{}' THEN 1 ELSE 0 END {}, (due_at IS NULL) {}, due_at {}, id {}",
+                Priority::High.as_str(), Priority::Medium.as_str(), direction, reverse, reverse, direction,
+            );
+        }
+    };
+    format!("ORDER BY {} {}, id {}", column, direction, direction)
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+mod tests {
+    use super::super::query_builder::{order_by, WhereClause};
+    use crate::domain::todo::{ListOptions, SortField, SortOrder, TodoFilter, TodoStatus};
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn unit_default_filter_hides_deleted_and_archived() {
+        let clause = WhereClause::from_filter(&TodoFilter::default());
+        assert_eq!(clause.sql(), "WHERE deleted_at IS NULL AND status != ?");
+        assert_eq!(clause.args(), ["archived"]);
+    }
+
+    #[test]
+    fn unit_status_and_completed_after_bind_in_order() {
+        let after = Utc.with_ymd_and_hms(2024, 5, 6, 0, 0, 0).unwrap();
+        let clause = WhereClause::from_filter(&TodoFilter { status: Some(TodoStatus::Done), completed_after: Some(after), ..Default::default() });
+        assert_eq!(clause.sql(), "WHERE deleted_at IS NULL AND status = ? AND julianday(completed_at) >= julianday(?)");
+        assert_eq!(clause.args(), ["done", "2024-05-06T00:00:00+00:00"]);
+    }
+
+    #[test]
+    fn unit_include_deleted_drops_the_tombstone_predicate() {
+        let clause = WhereClause::from_filter(&TodoFilter { include_deleted: true, ..Default::default() });
+        assert_eq!(clause.sql(), "WHERE status != ?");
+        assert_eq!(WhereClause::including_deleted().sql(), "");
+    }
+
+    #[test]
+    fn unit_tag_filter_is_a_subquery_on_todo_tags() {
+        let clause = WhereClause::from_filter(&TodoFilter { tag: Some(" work ".into()), ..Default::default() });
+        assert_eq!(clause.sql(), "WHERE deleted_at IS NULL AND status != ? AND id IN (SELECT todo_id FROM todo_tags WHERE tag = ?)");
+        assert_eq!(clause.args(), ["archived", "work"]);
+    }
+
+    #[test]
+    fn unit_export_filter_matches_every_status_unless_one_is_given() {
+        let after = Utc.with_ymd_and_hms(2024, 5, 6, 0, 0, 0).unwrap();
+        let clause = WhereClause::for_export(&TodoFilter { tag: Some("work".into()), owner: Some("me@example.com".into()), ..Default::default() });
+        assert_eq!(clause.sql(), "WHERE deleted_at IS NULL AND id IN (SELECT todo_id FROM todo_tags WHERE tag = ?) AND owner = ?");
+        assert_eq!(clause.args(), ["work", "me@example.com"]);
+
+        let clause = WhereClause::for_export(&TodoFilter { status: Some(TodoStatus::Archived), completed_after: Some(after), ..Default::default() });
+        assert_eq!(clause.sql(), "WHERE deleted_at IS NULL AND status = ? AND julianday(completed_at) >= julianday(?)");
+        assert_eq!(clause.args(), ["archived", "2024-05-06T00:00:00+00:00"]);
+        assert_eq!(WhereClause::for_export(&TodoFilter::default()).sql(), "WHERE deleted_at IS NULL");
+    }
+
+    #[test]
+    fn unit_order_by_defaults_to_newest_first_with_id_tiebreak() {
+        let ListOptions { sort, order } = ListOptions::default();
+        assert_eq!((sort, order), (SortField::default(), SortOrder::default()));
+        assert_eq!(order_by(sort, order), "ORDER BY created_at DESC, id DESC");
+        assert_eq!(order_by(SortField::Title, SortOrder::Asc), "ORDER BY title ASC, id ASC");
+        assert_eq!(order_by(SortField::Staleness, SortOrder::Desc), "ORDER BY (status = 'pending') DESC, updated_at ASC, id ASC");
+        assert_eq!(
+            order_by(SortField::Priority, SortOrder::Desc),
+            "ORDER BY CASE priority WHEN 'high' THEN 2 WHEN 'medium' THEN 1 ELSE 0 END DESC, (due_at IS NULL) ASC, due_at ASC, id DESC",
+        );
+    }
+
+    #[test]
+    fn unit_values_never_end_up_in_the_sql() {
+        let clause = WhereClause::default().and("title = ?", "x' OR 1=1 --");
+        assert_eq!(clause.sql(), "WHERE deleted_at IS NULL AND title = ?");
+        assert_eq!(clause.args(), ["x' OR 1=1 --"]);
+    }
+}
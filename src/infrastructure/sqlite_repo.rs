@@ -1,16 +1,28 @@
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use chrono::{DateTime, Utc};
-use sqlx::{sqlite::{SqlitePoolOptions, SqliteRow}, Pool, Row, Sqlite};
+use futures_util::{stream::BoxStream, StreamExt};
+use sqlx::{sqlite::{SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePoolOptions, SqliteRow, SqliteSynchronous}, Pool, Row, Sqlite};
 use uuid::Uuid;
 
+use super::query_builder::{interned, order_by, WhereClause};
 use crate::domain::{
     repository::TodoRepository,
-    todo::{CreateTodo, Todo, TodoId, TodoStatus, UpdateTodo},
+    todo::{new_code, ConflictPolicy, ListOptions, Page, Priority, CreateTodo, Recurrence, ImportReport, Inconsistency, InconsistencyKind, SortField, SortOrder, StatusCounts, Todo, TodoChange, TodoFilter, TodoId, TodoStatus, UniqueViolation, UpdateTodo, UpdatedTodo, VersionMismatch, Workload},
 };
 
+const GET_MANY_CHUNK: usize = 500;
+const CODE_ATTEMPTS: usize = 8;
+/// How long a statement waits for another connection's (or process's) lock before failing.
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+const TODO_COLUMNS: &str = "id, title, description, status, code, estimate_minutes, created_at, updated_at, completed_at, deleted_at, due_at, priority, defer_count, recurrence, waiting_on, owner";
+/// `TODO_COLUMNS` plus the todo's tags aggregated into a JSON array, for reads.
+static SELECT_COLUMNS: LazyLock<String> = LazyLock::new(|| {
+    format!("{}, (SELECT json_group_array(tag) FROM (SELECT tag FROM todo_tags WHERE todo_id = todos.id ORDER BY rowid)) AS tags", TODO_COLUMNS)
+});
+
 #[derive(Clone)]
 pub struct SqliteTodoRepository {
     pool: Arc<Pool<Sqlite>>,
@@ -20,10 +32,95 @@ impl SqliteTodoRepository {
     pub async fn connect(database_url: &str) -> Result<Self> {
         let pool = SqlitePoolOptions::new()
             .max_connections(5)
-            .connect(database_url)
+            .connect_with(connect_options(database_url)?)
             .await?;
         Ok(Self { pool: Arc::new(pool) })
     }
+
+    /// Writes a consistent copy of the whole database to `path`, which must not exist yet.
+    /// Safe while the database is in use.
+    pub async fn backup_to(&self, path: &std::path::Path) -> Result<()> {
+        sqlx::query("VACUUM INTO ?1").bind(path.to_string_lossy().into_owned()).execute(&*self.pool).await?;
+        Ok(())
+    }
+
+    /// Adds a column to `todos` for databases created before it existed.
+    async fn add_column_if_missing(&self, column_def: &str) -> Result<()> {
+        match sqlx::query(&format!("ALTER TABLE todos ADD COLUMN {}", column_def)).execute(&*self.pool).await {
+            Ok(_) => Ok(()),
+            Err(sqlx::Error::Database(e)) if e.message().contains("duplicate column name") => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Gives rows created before codes existed a unique code.
+    async fn backfill_codes(&self) -> Result<()> {
+        let rows = sqlx::query("SELECT id FROM todos WHERE code IS NULL").fetch_all(&*self.pool).await?;
+        for row in rows {
+            let id: String = row.get("id");
+            with_unique_code(|code| {
+                let id = id.clone();
+                async move { sqlx::query("UPDATE todos SET code = ?2 WHERE id = ?1").bind(id).bind(code).execute(&*self.pool).await }
+            }).await?;
+        }
+        Ok(())
+    }
+
+    /// The one todo `clause` matches, if any.
+    async fn get_where(&self, clause: WhereClause) -> Result<Option<Todo>> {
+        let sql = format!("SELECT {} FROM todos {}", *SELECT_COLUMNS, clause.sql());
+        let row = clause.bind(sqlx::query(&sql)).fetch_optional(&*self.pool).await?;
+        row.map(row_to_todo).transpose()
+    }
+}
+
+/// Options every process opening the database uses, so the server and the TUI can share a
+/// file: WAL lets readers and a writer work at the same time, and the busy timeout makes a
+/// second writer wait for the lock instead of failing straight away.
+pub fn connect_options(database_url: &str) -> Result<SqliteConnectOptions> {
+    let options = database_url.parse::<SqliteConnectOptions>()?.busy_timeout(BUSY_TIMEOUT);
+    // In-memory databases have no journal file to switch
+    if database_url.contains(":memory:") || database_url.contains("mode=memory") { return Ok(options); }
+    Ok(options.journal_mode(SqliteJournalMode::Wal).synchronous(SqliteSynchronous::Normal))
+}
+
+/// Whether `err` is SQLite reporting a lock held by someone else, which is worth retrying.
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<sqlx::Error>() {
+        // SQLITE_BUSY (5) and SQLITE_LOCKED (6), including their extended codes
+        Some(sqlx::Error::Database(e)) => e.code().and_then(|c| c.parse::<i32>().ok()).is_some_and(|c| matches!(c & 0xff, 5 | 6)),
+        Some(sqlx::Error::PoolTimedOut) => true,
+        _ => false,
+    }
+}
+
+/// Turns a unique index failure into [`UniqueViolation`] naming the column; other errors pass
+/// through. SQLite names the column in the message and Postgres the index in the constraint.
+pub(super) fn unique_violation(e: sqlx::Error) -> anyhow::Error {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            let field = if db.constraint().unwrap_or(db.message()).contains("code") { "code" } else { "id" };
+            UniqueViolation { field }.into()
+        }
+        _ => e.into(),
+    }
+}
+
+/// Runs `write` with fresh codes until it doesn't hit the unique index on `code`.
+pub(super) async fn with_unique_code<F, Fut, T>(mut write: F) -> Result<String>
+where
+    F: FnMut(String) -> Fut,
+    Fut: std::future::Future<Output = std::result::Result<T, sqlx::Error>>,
+{
+    for _ in 0..CODE_ATTEMPTS {
+        let code = new_code();
+        match write(code.clone()).await {
+            Ok(_) => return Ok(code),
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    anyhow::bail!("could not allocate a unique todo code after {} attempts", CODE_ATTEMPTS)
 }
 
 #[async_trait]
@@ -35,98 +132,562 @@ impl TodoRepository for SqliteTodoRepository {
                 title TEXT NOT NULL,
                 description TEXT,
                 status TEXT NOT NULL,
+                code TEXT,
+                estimate_minutes INTEGER,
                 created_at TEXT NOT NULL,
-                updated_at TEXT NOT NULL
+                updated_at TEXT NOT NULL,
+                completed_at TEXT,
+                deleted_at TEXT,
+                due_at TEXT,
+                priority TEXT NOT NULL DEFAULT 'medium',
+                defer_count INTEGER NOT NULL DEFAULT 0
+            )",
+        )
+        .execute(&*self.pool)
+        .await?;
+        self.add_column_if_missing("estimate_minutes INTEGER").await?;
+        self.add_column_if_missing("completed_at TEXT").await?;
+        self.add_column_if_missing("code TEXT").await?;
+        self.add_column_if_missing("deleted_at TEXT").await?;
+        self.add_column_if_missing("due_at TEXT").await?;
+        self.add_column_if_missing("priority TEXT NOT NULL DEFAULT 'medium'").await?;
+        self.add_column_if_missing("defer_count INTEGER NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("recurrence TEXT").await?;
+        self.add_column_if_missing("waiting_on TEXT").await?;
+        self.add_column_if_missing("owner TEXT").await?;
+        // Foreign keys are on for every sqlx connection, so tags go when their todo is purged
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS todo_tags (
+                todo_id TEXT NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+                tag TEXT NOT NULL COLLATE NOCASE,
+                PRIMARY KEY (todo_id, tag)
             )",
         )
         .execute(&*self.pool)
         .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_todo_tags_tag ON todo_tags(tag)").execute(&*self.pool).await?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS todo_changes (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                todo_id TEXT NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+                changed_at TEXT NOT NULL,
+                fields TEXT NOT NULL
+            )",
+        )
+        .execute(&*self.pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_todo_changes_todo_id ON todo_changes(todo_id)").execute(&*self.pool).await?;
+        sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_todos_code ON todos(code)").execute(&*self.pool).await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_todos_updated_at ON todos(updated_at)").execute(&*self.pool).await?;
+        self.backfill_codes().await?;
         Ok(())
     }
 
-    async fn create(&self, input: CreateTodo) -> Result<Todo> {
-        let now = Utc::now();
-        let id = TodoId(Uuid::new_v4());
-        let status = TodoStatus::Pending;
-        sqlx::query(
-            "INSERT INTO todos (id, title, description, status, created_at, updated_at)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&*self.pool).await?;
+        Ok(())
+    }
+
+    async fn is_schema_current(&self) -> Result<bool> {
+        let rows = sqlx::query("SELECT name FROM pragma_table_info('todos')")
+            .fetch_all(&*self.pool)
+            .await?;
+        let present: Vec<String> = rows.iter().map(|r| r.get("name")).collect();
+        Ok(TODO_COLUMNS.split(", ").all(|c| present.iter().any(|p| p == c)))
+    }
+
+    async fn reindex(&self) -> Result<()> {
+        // Nothing is cached outside SQLite's own indexes, so these two cover every derived structure
+        sqlx::query("REINDEX todos").execute(&*self.pool).await?;
+        sqlx::query("ANALYZE").execute(&*self.pool).await?;
+        Ok(())
+    }
+
+    async fn vacuum(&self) -> Result<()> {
+        sqlx::query("VACUUM").execute(&*self.pool).await?;
+        Ok(())
+    }
+
+    async fn purge_deleted(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM todos WHERE deleted_at IS NOT NULL").execute(&*self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn purge_deleted_before(&self, cutoff: DateTime<Utc>) -> Result<u64> {
+        // julianday() rather than comparing text, since RFC 3339 strings vary in fractional digits
+        let result = sqlx::query("DELETE FROM todos WHERE deleted_at IS NOT NULL AND julianday(deleted_at) < julianday(?1)")
+            .bind(cutoff.to_rfc3339())
+            .execute(&*self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn import_todos(&self, todos: Vec<Todo>, policy: ConflictPolicy) -> Result<ImportReport> {
+        let on_conflict = match policy {
+            // No target, so a clashing code is skipped as well
+            ConflictPolicy::Skip => "ON CONFLICT DO NOTHING",
+            ConflictPolicy::Overwrite => "ON CONFLICT(id) DO UPDATE SET title = excluded.title, description = excluded.description, status = excluded.status,
+                code = excluded.code, estimate_minutes = excluded.estimate_minutes, created_at = excluded.created_at, updated_at = excluded.updated_at,
+                completed_at = excluded.completed_at, deleted_at = excluded.deleted_at, due_at = excluded.due_at, priority = excluded.priority,
+                defer_count = excluded.defer_count, recurrence = excluded.recurrence, waiting_on = excluded.waiting_on,
+                owner = excluded.owner",
+            ConflictPolicy::Fail => "",
+        };
+        let sql = format!("INSERT INTO todos ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16) {}", TODO_COLUMNS, on_conflict);
+        let mut report = ImportReport::default();
+        let mut tx = self.pool.begin().await?;
+        for todo in &todos {
+            let exists = sqlx::query("SELECT 1 FROM todos WHERE id = ?1").bind(todo.id.0.to_string()).fetch_optional(&mut *tx).await?.is_some();
+            let result = sqlx::query(&sql)
+                .bind(todo.id.0.to_string())
+                .bind(&todo.title)
+                .bind(&todo.description)
+                .bind(status_str(&todo.status))
+                .bind(&todo.code)
+                .bind(todo.estimate_minutes)
+                .bind(todo.created_at.to_rfc3339())
+                .bind(todo.updated_at.to_rfc3339())
+                .bind(todo.completed_at.map(|d| d.to_rfc3339()))
+                .bind(todo.deleted_at.map(|d| d.to_rfc3339()))
+                .bind(todo.due_at.map(|d| d.to_rfc3339()))
+                .bind(todo.priority.as_str())
+                .bind(todo.defer_count)
+                .bind(todo.recurrence.map(|r| r.as_str()))
+                .bind(&todo.waiting_on)
+                .bind(&todo.owner)
+                .execute(&mut *tx)
+                .await
+                .map_err(unique_violation)?;
+            if result.rows_affected() > 0 { replace_tags(&mut tx, &todo.id, &todo.tags).await?; }
+            match (result.rows_affected(), exists) {
+                (0, _) => report.skipped += 1,
+                (_, true) => report.overwritten += 1,
+                (_, false) => report.inserted += 1,
+            }
+        }
+        tx.commit().await?;
+        Ok(report)
+    }
+
+    async fn find_inconsistencies(&self) -> Result<Vec<Inconsistency>> {
+        let rows = sqlx::query(
+            "SELECT id, 'done_without_completed_at' AS kind FROM todos WHERE status = 'done' AND completed_at IS NULL
+             UNION ALL
+             SELECT id, 'completed_at_while_not_done' AS kind FROM todos WHERE status != 'done' AND completed_at IS NOT NULL
+             UNION ALL
+             SELECT id, 'due_before_created' AS kind FROM todos WHERE due_at IS NOT NULL AND julianday(due_at) < julianday(created_at)
+             ORDER BY id",
         )
-        .bind(id.0.to_string())
-        .bind(&input.title)
-        .bind(&input.description)
-        .bind(match status { TodoStatus::Pending => "pending", TodoStatus::Done => "done" })
-        .bind(now.to_rfc3339())
-        .bind(now.to_rfc3339())
-        .execute(&*self.pool)
+        .fetch_all(&*self.pool)
         .await?;
-        Ok(Todo { id, title: input.title, description: input.description, status, created_at: now, updated_at: now })
+        rows.iter().map(|r| {
+            let id: String = r.get("id");
+            let kind = match r.get::<&str, _>("kind") {
+                "done_without_completed_at" => InconsistencyKind::DoneWithoutCompletedAt,
+                "due_before_created" => InconsistencyKind::DueBeforeCreated,
+                _ => InconsistencyKind::CompletedAtWhileNotDone,
+            };
+            Ok(Inconsistency { id: TodoId(Uuid::parse_str(&id)?), kind })
+        }).collect()
     }
 
-    async fn get(&self, id: TodoId) -> Result<Option<Todo>> {
-        let row = sqlx::query("SELECT id, title, description, status, created_at, updated_at FROM todos WHERE id = ?1")
-            .bind(id.0.to_string())
+    async fn fix_inconsistencies(&self) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+        // The last update is the best available guess for when it was completed
+        let done = sqlx::query("UPDATE todos SET completed_at = updated_at WHERE status = 'done' AND completed_at IS NULL")
+            .execute(&mut *tx).await?.rows_affected();
+        let not_done = sqlx::query("UPDATE todos SET completed_at = NULL WHERE status != 'done' AND completed_at IS NOT NULL")
+            .execute(&mut *tx).await?.rows_affected();
+        tx.commit().await?;
+        Ok(done + not_done)
+    }
+
+    async fn create(&self, input: CreateTodo) -> Result<Todo> {
+        let tx = tokio::sync::Mutex::new(self.pool.begin().await?);
+        let todo = insert_todo(&tx, input).await?;
+        tx.into_inner().commit().await?;
+        Ok(todo)
+    }
+
+    async fn create_many(&self, inputs: Vec<CreateTodo>) -> Result<Vec<Todo>> {
+        let tx = tokio::sync::Mutex::new(self.pool.begin().await?);
+        let mut todos = Vec::with_capacity(inputs.len());
+        // An error drops the transaction, rolling back everything inserted so far
+        for input in inputs { todos.push(insert_todo(&tx, input).await?); }
+        tx.into_inner().commit().await?;
+        Ok(todos)
+    }
+
+    async fn get(&self, id: TodoId, owner: Option<&str>) -> Result<Option<Todo>> {
+        self.get_where(WhereClause::default().and("id = ?", id.0.to_string()).owner(owner)).await
+    }
+
+    async fn get_including_deleted(&self, id: TodoId, owner: Option<&str>) -> Result<Option<Todo>> {
+        self.get_where(WhereClause::including_deleted().and("id = ?", id.0.to_string()).owner(owner)).await
+    }
+
+    async fn get_by_code(&self, code: &str, owner: Option<&str>) -> Result<Option<Todo>> {
+        self.get_where(WhereClause::default().and("code = ?", code.to_ascii_uppercase()).owner(owner)).await
+    }
+
+    async fn latest_updated(&self, owner: Option<&str>) -> Result<Option<Todo>> {
+        let clause = WhereClause::default().owner(owner);
+        let sql = format!("SELECT {} FROM todos {} ORDER BY updated_at DESC LIMIT 1", *SELECT_COLUMNS, clause.sql());
+        let row = clause.bind(sqlx::query(&sql))
             .fetch_optional(&*self.pool)
             .await?;
-        Ok(row.map(row_to_todo))
+        row.map(row_to_todo).transpose()
     }
 
-    async fn list(&self) -> Result<Vec<Todo>> {
-        let rows = sqlx::query("SELECT id, title, description, status, created_at, updated_at FROM todos ORDER BY created_at DESC")
-            .fetch_all(&*self.pool)
-            .await?;
-        Ok(rows.into_iter().map(row_to_todo).collect())
+    async fn get_many(&self, ids: Vec<TodoId>, owner: Option<&str>) -> Result<Vec<Todo>> {
+        let clause = WhereClause::default().owner(owner);
+        let mut todos = Vec::with_capacity(ids.len());
+        // Chunk to stay well below SQLite's bound-parameter limit
+        for chunk in ids.chunks(GET_MANY_CHUNK) {
+            let placeholders = vec!["?"; chunk.len()].join(", ");
+            let sql = format!("SELECT {} FROM todos {} AND id IN ({})", *SELECT_COLUMNS, clause.sql(), placeholders);
+            let mut query = clause.bind(sqlx::query(&sql));
+            for id in chunk { query = query.bind(id.0.to_string()); }
+            let rows = query.fetch_all(&*self.pool).await?;
+            for row in rows { todos.push(row_to_todo(row)?); }
+        }
+        todos.sort_by_key(|t| std::cmp::Reverse(t.created_at));
+        Ok(todos)
+    }
+
+    async fn list(&self, filter: TodoFilter, options: ListOptions) -> Result<Vec<Todo>> {
+        let clause = WhereClause::from_filter(&filter);
+        let sql = format!("SELECT {} FROM todos {} {}", *SELECT_COLUMNS, clause.sql(), order_by(options.sort, options.order));
+        let rows = clause.bind(sqlx::query(&sql)).fetch_all(&*self.pool).await?;
+        rows.into_iter().map(row_to_todo).collect()
+    }
+
+    fn stream(&self, filter: TodoFilter) -> BoxStream<'_, Result<Todo>> {
+        let clause = WhereClause::for_export(&TodoFilter { include_deleted: false, ..filter });
+        let sql = interned(format!("SELECT {} FROM todos {} ORDER BY created_at, id", *SELECT_COLUMNS, clause.sql()));
+        clause.bind(sqlx::query(sql)).fetch(&*self.pool).map(|row| row_to_todo(row?)).boxed()
     }
 
-    async fn update(&self, id: TodoId, input: UpdateTodo) -> Result<Option<Todo>> {
-        // Fetch existing
-        let existing = self.get(id.clone()).await?;
-        let Some(mut todo) = existing else { return Ok(None) };
+    async fn list_paged(&self, filter: TodoFilter, options: ListOptions, limit: u32, offset: u32) -> Result<Page<Todo>> {
+        let clause = WhereClause::from_filter(&filter);
+        // One read transaction so the total matches the page
+        let mut tx = self.pool.begin().await?;
+        let sql = format!("SELECT {} FROM todos {} {} LIMIT ? OFFSET ?", *SELECT_COLUMNS, clause.sql(), order_by(options.sort, options.order));
+        let rows = clause.bind(sqlx::query(&sql)).bind(limit).bind(offset).fetch_all(&mut *tx).await?;
+        let count_sql = format!("SELECT COUNT(*) AS total FROM todos {}", clause.sql());
+        let total: i64 = clause.bind(sqlx::query(&count_sql)).fetch_one(&mut *tx).await?.get("total");
+        tx.commit().await?;
+        Ok(Page { items: rows.into_iter().map(row_to_todo).collect::<Result<_>>()?, total: total as u64 })
+    }
+
+    async fn list_archived_paged(&self, sort: SortField, order: SortOrder, limit: u32, offset: u32, owner: Option<&str>) -> Result<Vec<Todo>> {
+        let clause = WhereClause::default().status(&TodoStatus::Archived).owner(owner);
+        let sql = format!("SELECT {} FROM todos {} {} LIMIT ? OFFSET ?", *SELECT_COLUMNS, clause.sql(), order_by(sort, order));
+        let rows = clause.bind(sqlx::query(&sql)).bind(limit).bind(offset).fetch_all(&*self.pool).await?;
+        rows.into_iter().map(row_to_todo).collect()
+    }
+
+    async fn list_stale(&self, cutoff: DateTime<Utc>, status: Option<TodoStatus>, owner: Option<&str>) -> Result<Vec<Todo>> {
+        let clause = WhereClause::from_filter(&TodoFilter { status, owner: owner.map(str::to_string), ..Default::default() })
+            .and("julianday(updated_at) < julianday(?)", cutoff.to_rfc3339());
+        let sql = format!("SELECT {} FROM todos {} {}", *SELECT_COLUMNS, clause.sql(), order_by(SortField::UpdatedAt, SortOrder::Asc));
+        let rows = clause.bind(sqlx::query(&sql)).fetch_all(&*self.pool).await?;
+        rows.into_iter().map(row_to_todo).collect()
+    }
+
+    async fn export_page(&self, filter: TodoFilter, after: Option<TodoId>, limit: u32) -> Result<Vec<Todo>> {
+        let clause = WhereClause::for_export(&TodoFilter { include_deleted: false, ..filter });
+        // Ids are stored as lowercase hyphenated text, which sorts like the UUIDs themselves
+        let clause = match after { Some(after) => clause.and("id > ?", after.0.to_string()), None => clause };
+        let sql = format!("SELECT {} FROM todos {} ORDER BY id LIMIT ?", *SELECT_COLUMNS, clause.sql());
+        let rows = clause.bind(sqlx::query(&sql)).bind(limit).fetch_all(&*self.pool).await?;
+        rows.into_iter().map(row_to_todo).collect()
+    }
+
+    async fn update(&self, id: TodoId, input: UpdateTodo) -> Result<Option<UpdatedTodo>> {
+        let mut tx = self.pool.begin().await?;
+        // A no-op write first takes the write lock (waiting out the busy timeout), so nothing
+        // can change the row between the read below and the update
+        sqlx::query("UPDATE todos SET id = id WHERE id = ?1").bind(id.0.to_string()).execute(&mut *tx).await?;
+        let row = sqlx::query(&format!("SELECT {} FROM todos WHERE id = ?1 AND deleted_at IS NULL", *SELECT_COLUMNS))
+            .bind(id.0.to_string())
+            .fetch_optional(&mut *tx)
+            .await?;
+        let Some(mut todo) = row.map(row_to_todo).transpose()? else { return Ok(None) };
+        if !input.version_matches(todo.updated_at) { return Err(VersionMismatch.into()); }
+        let before = todo.clone();
 
         if let Some(t) = input.title { todo.title = t; }
-        if let Some(d) = input.description { todo.description = Some(d); }
-        if let Some(s) = input.status { todo.status = s; }
+        if let Some(d) = input.description { todo.description = d; }
+        if let Some(s) = input.status {
+            todo.completed_at = completed_at_after(&todo, &s);
+            todo.status = s;
+        }
+        if let Some(e) = input.estimate_minutes { todo.estimate_minutes = Some(e); }
+        if let Some(due) = input.due_at { todo.due_at = Some(due); }
+        if let Some(p) = input.priority { todo.priority = p; }
+        if let Some(tags) = input.tags { todo.tags = tags; }
+        if let Some(r) = input.recurrence { todo.recurrence = r; }
+        if let Some(w) = input.waiting_on { todo.waiting_on = w; }
         todo.updated_at = Utc::now();
 
-        sqlx::query("UPDATE todos SET title = ?2, description = ?3, status = ?4, updated_at = ?5 WHERE id = ?1")
+        sqlx::query("UPDATE todos SET title = ?2, description = ?3, status = ?4, estimate_minutes = ?5, updated_at = ?6, completed_at = ?7, due_at = ?8, priority = ?9, recurrence = ?10, waiting_on = ?11 WHERE id = ?1")
             .bind(todo.id.0.to_string())
             .bind(&todo.title)
             .bind(&todo.description)
-            .bind(match todo.status { TodoStatus::Pending => "pending", TodoStatus::Done => "done" })
+            .bind(status_str(&todo.status))
+            .bind(todo.estimate_minutes)
             .bind(todo.updated_at.to_rfc3339())
-            .execute(&*self.pool)
+            .bind(todo.completed_at.map(|d| d.to_rfc3339()))
+            .bind(todo.due_at.map(|d| d.to_rfc3339()))
+            .bind(todo.priority.as_str())
+            .bind(todo.recurrence.map(|r| r.as_str()))
+            .bind(&todo.waiting_on)
+            .execute(&mut *tx)
             .await?;
+        replace_tags(&mut tx, &todo.id, &todo.tags).await?;
+        record_change(&mut tx, &before, &todo).await?;
+        let tx = tokio::sync::Mutex::new(tx);
+        let next = match todo.next_occurrence(todo.updated_at) {
+            Some(input) if before.status != TodoStatus::Done && todo.status == TodoStatus::Done => Some(insert_todo(&tx, input).await?),
+            _ => None,
+        };
+        tx.into_inner().commit().await?;
+
+        Ok(Some(UpdatedTodo { todo, next }))
+    }
+
+    async fn increment_defer_count(&self, id: TodoId) -> Result<Option<i64>> {
+        // Incrementing in SQL rather than read-modify-write, so concurrent defers all count
+        let row = sqlx::query("UPDATE todos SET defer_count = defer_count + 1, updated_at = ?2 WHERE id = ?1 AND deleted_at IS NULL RETURNING defer_count")
+            .bind(id.0.to_string())
+            .bind(Utc::now().to_rfc3339())
+            .fetch_optional(&*self.pool)
+            .await?;
+        Ok(row.map(|r| r.get("defer_count")))
+    }
 
+    async fn list_changes(&self, id: TodoId) -> Result<Vec<TodoChange>> {
+        let rows = sqlx::query("SELECT changed_at, fields FROM todo_changes WHERE todo_id = ?1 ORDER BY id")
+            .bind(id.0.to_string())
+            .fetch_all(&*self.pool)
+            .await?;
+        rows.iter().map(|row| {
+            let changed_at: String = row.get("changed_at");
+            let fields: String = row.get("fields");
+            Ok(TodoChange {
+                changed_at: DateTime::parse_from_rfc3339(&changed_at).map_err(|e| anyhow!("todo {} has a change with an invalid changed_at {:?}: {}", id.0, changed_at, e))?.with_timezone(&Utc),
+                fields: serde_json::from_str(&fields).map_err(|e| anyhow!("todo {} has a change with invalid fields {:?}: {}", id.0, fields, e))?,
+            })
+        }).collect()
+    }
+
+    async fn clear_due(&self, id: TodoId) -> Result<Option<Todo>> {
+        let mut tx = self.pool.begin().await?;
+        // Takes the write lock before the read, as in `update`
+        sqlx::query("UPDATE todos SET id = id WHERE id = ?1").bind(id.0.to_string()).execute(&mut *tx).await?;
+        let row = sqlx::query(&format!("SELECT {} FROM todos WHERE id = ?1 AND deleted_at IS NULL", *SELECT_COLUMNS))
+            .bind(id.0.to_string())
+            .fetch_optional(&mut *tx)
+            .await?;
+        let Some(before) = row.map(row_to_todo).transpose()? else { return Ok(None) };
+        let todo = Todo { due_at: None, updated_at: Utc::now(), ..before.clone() };
+        sqlx::query("UPDATE todos SET due_at = NULL, updated_at = ?2 WHERE id = ?1")
+            .bind(id.0.to_string())
+            .bind(todo.updated_at.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        record_change(&mut tx, &before, &todo).await?;
+        tx.commit().await?;
         Ok(Some(todo))
     }
 
+    async fn restore(&self, id: TodoId) -> Result<Option<Todo>> {
+        sqlx::query("UPDATE todos SET deleted_at = NULL, updated_at = ?2 WHERE id = ?1 AND deleted_at IS NOT NULL")
+            .bind(id.0.to_string())
+            .bind(Utc::now().to_rfc3339())
+            .execute(&*self.pool)
+            .await?;
+        self.get(id, None).await
+    }
+
+    async fn purge(&self, id: TodoId) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM todos WHERE id = ?1").bind(id.0.to_string()).execute(&*self.pool).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
     async fn delete(&self, id: TodoId) -> Result<bool> {
-        let result = sqlx::query("DELETE FROM todos WHERE id = ?1")
+        let result = sqlx::query("UPDATE todos SET deleted_at = ?2 WHERE id = ?1 AND deleted_at IS NULL")
             .bind(id.0.to_string())
+            .bind(Utc::now().to_rfc3339())
             .execute(&*self.pool)
             .await?;
         Ok(result.rows_affected() > 0)
     }
+
+    async fn workload(&self, status: Option<TodoStatus>, owner: Option<&str>) -> Result<Workload> {
+        // Unlike `list`, no status means every status, archived included
+        let clause = match &status { Some(s) => WhereClause::default().status(s), None => WhereClause::default() }.owner(owner);
+        let sql = format!("SELECT COALESCE(SUM(estimate_minutes), 0) AS total_minutes, COUNT(*) AS count FROM todos {}", clause.sql());
+        let row = clause.bind(sqlx::query(&sql)).fetch_one(&*self.pool).await?;
+        Ok(Workload { total_minutes: row.get("total_minutes"), count: row.get("count") })
+    }
+
+    async fn count_by_status(&self, owner: Option<&str>) -> Result<StatusCounts> {
+        let clause = WhereClause::default().owner(owner);
+        let sql = format!("SELECT status, COUNT(*) AS count FROM todos {} GROUP BY status", clause.sql());
+        let rows = clause.bind(sqlx::query(&sql)).fetch_all(&*self.pool).await?;
+        let mut counts = StatusCounts::default();
+        for row in rows {
+            let status: String = row.get("status");
+            // Unknown values count as pending, the way `row_to_todo` reads them
+            counts.add(&TodoStatus::ALL.into_iter().find(|s| s.as_str() == status).unwrap_or(TodoStatus::Pending), row.get("count"));
+        }
+        Ok(counts)
+    }
+
+    async fn completions_by_week(&self, now: DateTime<Utc>, weeks: u32, owner: Option<&str>) -> Result<Vec<(u32, i64)>> {
+        let rows = sqlx::query(
+            "SELECT CAST((julianday(?1) - julianday(completed_at)) / 7 AS INTEGER) AS weeks_ago, COUNT(*) AS completed
+             FROM todos
+             WHERE deleted_at IS NULL AND completed_at IS NOT NULL AND julianday(completed_at) > julianday(?1) - 7 * ?2 AND julianday(completed_at) <= julianday(?1)
+               AND (?3 IS NULL OR owner = ?3)
+             GROUP BY weeks_ago",
+        )
+        .bind(now.to_rfc3339())
+        .bind(weeks)
+        .bind(owner)
+        .fetch_all(&*self.pool)
+        .await?;
+        Ok(rows.iter().map(|r| (r.get::<i64, _>("weeks_ago") as u32, r.get("completed"))).collect())
+    }
+}
+
+/// Inserts a new todo and its tags in `tx`. The transaction sits behind a mutex so each code
+/// attempt can borrow it; a code collision only fails its own statement.
+async fn insert_todo(tx: &tokio::sync::Mutex<sqlx::Transaction<'_, Sqlite>>, input: CreateTodo) -> Result<Todo> {
+    let now = Utc::now();
+    let id = TodoId(Uuid::new_v4());
+    let status = TodoStatus::Pending;
+    let description = input.description.flatten();
+    let priority = input.priority.unwrap_or_default();
+    let code = with_unique_code(|code| {
+        let (id, title, description, status, waiting_on, owner) = (&id, &input.title, &description, status_str(&status), &input.waiting_on, &input.owner);
+        async move {
+            let mut tx = tx.lock().await;
+            sqlx::query(
+                "INSERT INTO todos (id, title, description, status, code, estimate_minutes, created_at, updated_at, due_at, priority, recurrence, waiting_on, owner)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+            )
+            .bind(id.0.to_string())
+            .bind(title)
+            .bind(description)
+            .bind(status)
+            .bind(code)
+            .bind(input.estimate_minutes)
+            .bind(now.to_rfc3339())
+            .bind(now.to_rfc3339())
+            .bind(input.due_at.map(|d| d.to_rfc3339()))
+            .bind(priority.as_str())
+            .bind(input.recurrence.map(|r| r.as_str()))
+            .bind(waiting_on)
+            .bind(owner)
+            .execute(&mut **tx)
+            .await
+        }
+    })
+    .await?;
+    replace_tags(&mut *tx.lock().await, &id, &input.tags).await?;
+    Ok(Todo { id, title: input.title, description, status, code, estimate_minutes: input.estimate_minutes, created_at: now, updated_at: now, completed_at: None, deleted_at: None, due_at: input.due_at, priority, defer_count: 0, tags: input.tags, recurrence: input.recurrence, waiting_on: input.waiting_on, owner: input.owner })
 }
 
-fn row_to_todo(row: SqliteRow) -> Todo {
+/// Appends what changed between `before` and `after` to the changelog; a write that changed
+/// nothing leaves no entry.
+async fn record_change(conn: &mut SqliteConnection, before: &Todo, after: &Todo) -> Result<()> {
+    let Some(change) = TodoChange::between(before, after) else { return Ok(()) };
+    sqlx::query("INSERT INTO todo_changes (todo_id, changed_at, fields) VALUES (?1, ?2, ?3)")
+        .bind(after.id.0.to_string())
+        .bind(change.changed_at.to_rfc3339())
+        .bind(serde_json::Value::Object(change.fields).to_string())
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Makes `tags` the todo's complete set of tags.
+async fn replace_tags(conn: &mut SqliteConnection, id: &TodoId, tags: &[String]) -> Result<()> {
+    sqlx::query("DELETE FROM todo_tags WHERE todo_id = ?1").bind(id.0.to_string()).execute(&mut *conn).await?;
+    for tag in tags {
+        // Duplicates that differ in case collapse under NOCASE instead of failing the write
+        sqlx::query("INSERT OR IGNORE INTO todo_tags (todo_id, tag) VALUES (?1, ?2)").bind(id.0.to_string()).bind(tag).execute(&mut *conn).await?;
+    }
+    Ok(())
+}
+
+/// Keeps the original completion time while a todo stays done.
+pub(super) fn completed_at_after(todo: &Todo, next: &TodoStatus) -> Option<DateTime<Utc>> {
+    match (&todo.status, next) {
+        (TodoStatus::Done, TodoStatus::Done) => todo.completed_at,
+        (_, TodoStatus::Done) => Some(Utc::now()),
+        _ => None,
+    }
+}
+
+pub(super) fn status_str(status: &TodoStatus) -> &'static str {
+    status.as_str()
+}
+
+/// Fails with a message naming the todo and column when a stored value doesn't parse (e.g.
+/// rows imported by other tools), instead of bringing the process down.
+fn row_to_todo(row: SqliteRow) -> Result<Todo> {
     let id_str: String = row.get("id");
     let title: String = row.get("title");
     let description: Option<String> = row.get("description");
     let status_str: String = row.get("status");
+    let code: Option<String> = row.get("code");
+    let estimate_minutes: Option<i64> = row.get("estimate_minutes");
     let created_at_str: String = row.get("created_at");
     let updated_at_str: String = row.get("updated_at");
+    let completed_at_str: Option<String> = row.get("completed_at");
+    let deleted_at_str: Option<String> = row.get("deleted_at");
+    let due_at_str: Option<String> = row.get("due_at");
+    let priority_str: String = row.get("priority");
+    let defer_count: i64 = row.get("defer_count");
+    let tags_json: String = row.get("tags");
+    let recurrence_str: Option<String> = row.get("recurrence");
 
-    let status = match status_str.as_str() { "pending" => TodoStatus::Pending, "done" => TodoStatus::Done, _ => TodoStatus::Pending };
-    let created_at = DateTime::parse_from_rfc3339(&created_at_str).unwrap().with_timezone(&Utc);
-    let updated_at = DateTime::parse_from_rfc3339(&updated_at_str).unwrap().with_timezone(&Utc);
+    let status = TodoStatus::ALL.into_iter().find(|s| s.as_str() == status_str).unwrap_or(TodoStatus::Pending);
+    let id = Uuid::parse_str(&id_str).map_err(|e| anyhow!("todo has an invalid id {:?}: {}", id_str, e))?;
+    let timestamp = |column: &str, value: &str| {
+        DateTime::parse_from_rfc3339(value).map(|d| d.with_timezone(&Utc)).map_err(|e| anyhow!("todo {} has an invalid {} {:?}: {}", id_str, column, value, e))
+    };
+    let created_at = timestamp("created_at", &created_at_str)?;
+    let updated_at = timestamp("updated_at", &updated_at_str)?;
+    let completed_at = completed_at_str.map(|s| timestamp("completed_at", &s)).transpose()?;
+    let deleted_at = deleted_at_str.map(|s| timestamp("deleted_at", &s)).transpose()?;
+    // Unknown values (e.g. written by a newer version) read as the default
+    let priority = Priority::ALL.into_iter().find(|p| p.as_str() == priority_str).unwrap_or_default();
+    let due_at = due_at_str.map(|s| timestamp("due_at", &s)).transpose()?;
+    let recurrence = recurrence_str.and_then(|s| Recurrence::ALL.into_iter().find(|r| r.as_str() == s));
+    let tags = serde_json::from_str(&tags_json).map_err(|e| anyhow!("todo {} has invalid tags {:?}: {}", id_str, tags_json, e))?;
 
-    Todo {
-        id: TodoId(Uuid::parse_str(&id_str).unwrap()),
+    Ok(Todo {
+        id: TodoId(id),
         title,
         description,
         status,
+        code: code.unwrap_or_default(),
+        estimate_minutes,
         created_at,
         updated_at,
-    }
+        completed_at,
+        deleted_at,
+        due_at,
+        priority,
+        defer_count,
+        tags,
+        recurrence,
+        waiting_on: row.get("waiting_on"),
+        owner: row.get("owner"),
+    })
 }
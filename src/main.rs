@@ -1,44 +1,115 @@
-mod domain;
-mod application;
-mod infrastructure;
-mod http;
+use std::{net::SocketAddr, time::Duration};
 
-use std::net::SocketAddr;
-
-use application::todo_service::TodoServiceImpl;
-use domain::repository::TodoRepository;
-use http::routing::{self, todos};
-use infrastructure::sqlite_repo::SqliteTodoRepository;
+use api::application::todo_service::{TodoService, TodoServiceConfig, TodoServiceImpl};
+use api::domain::repository::TodoRepository;
+use api::http::routing;
+use api::infrastructure::{backup::{self, BackupConfig}, sqlite_repo::SqliteTodoRepository};
 use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let _ = dotenvy::dotenv();
+    // The default format prefixes lines with their spans, so request logs read `request{id=… method=… path=…}: …`
     tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into()))
         .init();
 
     let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite://todos.db".to_string());
+    if database_url.starts_with("postgres://") || database_url.starts_with("postgresql://") {
+        return serve_postgres(&database_url).await;
+    }
     // Ensure SQLite file can be created/opened when using a file-backed URL
     prepare_sqlite_file(&database_url)?;
     let repo = SqliteTodoRepository::connect(&database_url).await?;
     repo.init().await?;
-    let service = TodoServiceImpl::new(repo);
-    let todos_router = todos::router(todos::AppState { service });
-    let router = routing::app(todos_router);
+    if let Some(config) = BackupConfig::from_env()? {
+        backup::spawn(repo.clone(), &database_url, config);
+    }
+    serve(repo).await
+}
+
+#[cfg(feature = "postgres")]
+async fn serve_postgres(database_url: &str) -> anyhow::Result<()> {
+    let repo = api::infrastructure::postgres_repo::PostgresTodoRepository::connect(database_url).await?;
+    repo.init().await?;
+    serve(repo).await
+}
+
+#[cfg(not(feature = "postgres"))]
+async fn serve_postgres(_database_url: &str) -> anyhow::Result<()> {
+    anyhow::bail!("DATABASE_URL is a PostgreSQL URL but this build lacks the `postgres` feature (cargo run --features postgres)")
+}
+
+async fn serve<R: TodoRepository + Clone>(repo: R) -> anyhow::Result<()> {
+    let metrics = routing::install_metrics()?;
+    let config = TodoServiceConfig::from_env()?;
+    let undo_delete_window = config.undo_delete_window;
+    let service = TodoServiceImpl::with_config(repo.clone(), config);
+    if let Some(window) = undo_delete_window { spawn_expired_delete_purge(service.clone(), window); }
+    let events = service.events().clone();
+    let router = routing::api(repo, service, Some(metrics))?;
 
     let addr: SocketAddr = "127.0.0.1:3000".parse().unwrap();
     tracing::info!(%addr, "listening");
     axum::serve(tokio::net::TcpListener::bind(addr).await?, router)
         .with_graceful_shutdown(shutdown_signal())
         .await?;
+
+    // Let event subscribers finish deliveries queued before the server stopped
+    let report = events.shutdown(shutdown_timeout()?).await;
+    if report.dropped > 0 {
+        tracing::warn!(flushed = report.flushed, dropped = report.dropped, "event queue not fully drained");
+    } else {
+        tracing::info!(flushed = report.flushed, "event queue drained");
+    }
     Ok(())
 }
 
+/// Purges deletes that can no longer be undone, checking at least once a minute so nothing
+/// outlives its window by much.
+fn spawn_expired_delete_purge<S: TodoService + Clone>(service: S, window: Duration) {
+    let every = window.clamp(Duration::from_secs(1), Duration::from_secs(60));
+    tokio::spawn(async move {
+        let mut ticks = tokio::time::interval(every);
+        loop {
+            ticks.tick().await;
+            match service.purge_expired_deletes().await {
+                Ok(0) => {}
+                Ok(purged) => tracing::info!(purged, "purged deletes past the undo window"),
+                Err(e) => tracing::warn!(error = %e, "purging expired deletes failed"),
+            }
+        }
+    });
+}
+
+/// `SHUTDOWN_TIMEOUT_SECS`, default 10: how long shutdown waits for queued events.
+fn shutdown_timeout() -> anyhow::Result<Duration> {
+    match std::env::var("SHUTDOWN_TIMEOUT_SECS") {
+        Ok(v) => Ok(Duration::from_secs(v.trim().parse().map_err(|_| anyhow::anyhow!("invalid SHUTDOWN_TIMEOUT_SECS {:?}", v))?)),
+        Err(_) => Ok(Duration::from_secs(10)),
+    }
+}
+
+/// Resolves on Ctrl-C or, on Unix, SIGTERM (what systemd and Docker send on stop).
 async fn shutdown_signal() {
-    use tokio::signal::ctrl_c;
-    let _ = ctrl_c().await;
-    tracing::info!("shutdown");
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await { tracing::warn!(error = %e, "cannot listen for Ctrl-C"); std::future::pending::<()>().await; }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut stream) => { stream.recv().await; }
+            Err(e) => { tracing::warn!(error = %e, "cannot listen for SIGTERM"); std::future::pending::<()>().await; }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    let signal = tokio::select! {
+        _ = ctrl_c => "ctrl_c",
+        _ = terminate => "sigterm",
+    };
+    tracing::info!(signal, "shutdown");
 }
 
 fn prepare_sqlite_file(database_url: &str) -> anyhow::Result<()> {
@@ -53,7 +124,7 @@ fn prepare_sqlite_file(database_url: &str) -> anyhow::Result<()> {
         };
         use std::{fs, path::Path, fs::OpenOptions};
         let p = Path::new(path);
-        if let Some(parent) = p.parent() { if !parent.as_os_str().is_empty() { fs::create_dir_all(parent)?; } }
+        if let Some(parent) = p.parent() && !parent.as_os_str().is_empty() { fs::create_dir_all(parent)?; }
         if !p.exists() {
             let _ = OpenOptions::new().create(true).append(true).open(p)?;
         }
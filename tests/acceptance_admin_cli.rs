@@ -0,0 +1,147 @@
+mod common;
+
+use api::application::maintenance::{self, AdminCommand};
+use api::infrastructure::sqlite_repo::SqliteTodoRepository;
+use api::domain::{repository::TodoRepository, todo::{ConflictPolicy, CreateTodo, ImportReport, Todo, TodoFilter}};
+
+use common::{isolated_db_url, spawn_test_repo};
+
+#[tokio::test]
+async fn acceptance_admin_stats_and_purge_trash() {
+    let repo = spawn_test_repo(&isolated_db_url()).await;
+    let kept = repo.create(CreateTodo { title: "keep".into(), estimate_minutes: Some(30), ..Default::default() }).await.unwrap();
+    let trashed = repo.create(CreateTodo { title: "trash".into(), ..Default::default() }).await.unwrap();
+    repo.delete(trashed.id.clone()).await.unwrap();
+
+    let stats = maintenance::run(&repo, AdminCommand::parse("stats").unwrap()).await.unwrap();
+    assert_eq!(stats["total"], 1);
+    assert_eq!(stats["by_status"]["pending"]["estimate_minutes"], 30);
+
+    let purged = maintenance::run(&repo, AdminCommand::parse("purge-trash").unwrap()).await.unwrap();
+    assert_eq!(purged["purged"], 1);
+    assert!(repo.get_including_deleted(trashed.id, None).await.unwrap().is_none());
+    assert!(repo.get(kept.id, None).await.unwrap().is_some());
+
+    for command in ["vacuum", "reindex"] {
+        let result = maintenance::run(&repo, AdminCommand::parse(command).unwrap()).await.unwrap();
+        assert_eq!(result["status"], "ok");
+    }
+    assert!(AdminCommand::parse("drop-everything").is_err());
+}
+
+#[tokio::test]
+async fn acceptance_admin_export_then_import_into_another_db() {
+    let source = spawn_test_repo(&isolated_db_url()).await;
+    for title in ["a", "b"] {
+        source.create(CreateTodo { title: title.into(), ..Default::default() }).await.unwrap();
+    }
+    let exported = maintenance::run(&source, AdminCommand::parse("export").unwrap()).await.unwrap();
+    assert_eq!(exported["count"], 2);
+
+    let target = spawn_test_repo(&isolated_db_url()).await;
+    let todos: Vec<Todo> = serde_json::from_value(exported["todos"].clone()).unwrap();
+    let imported = maintenance::run(&target, AdminCommand::Import(todos.clone(), ConflictPolicy::Fail)).await.unwrap();
+    assert_eq!(imported["result"]["inserted"], 2);
+    for todo in &todos {
+        assert_eq!(target.get(todo.id.clone(), None).await.unwrap().as_ref(), Some(todo));
+    }
+
+    // a second import collides on every id and changes nothing
+    assert!(maintenance::run(&target, AdminCommand::Import(todos, ConflictPolicy::Fail)).await.is_err());
+}
+
+#[tokio::test]
+async fn acceptance_admin_export_only_done_todos() {
+    use api::domain::todo::{TodoStatus, UpdateTodo};
+
+    let repo = spawn_test_repo(&isolated_db_url()).await;
+    for (title, status) in [("done", Some(TodoStatus::Done)), ("pending", None), ("archived", Some(TodoStatus::Archived))] {
+        let todo = repo.create(CreateTodo { title: title.into(), ..Default::default() }).await.unwrap();
+        if let Some(status) = status {
+            repo.update(todo.id, UpdateTodo { status: Some(status), ..Default::default() }).await.unwrap();
+        }
+    }
+
+    let exported = maintenance::run(&repo, AdminCommand::parse_export(&["--status", "done"]).unwrap()).await.unwrap();
+    assert_eq!(exported["count"], 1);
+    assert_eq!(exported["todos"][0]["title"], "done");
+
+    // completed today, so a later cut-off matches nothing
+    let exported = maintenance::run(&repo, AdminCommand::parse_export(&["--completed-after", "2999-01-01"]).unwrap()).await.unwrap();
+    assert_eq!(exported["count"], 0);
+    // without a status, everything is exported as before
+    let exported = maintenance::run(&repo, AdminCommand::parse_export(&[]).unwrap()).await.unwrap();
+    assert_eq!(exported["count"], 3);
+
+    assert!(AdminCommand::parse_export(&["--status", "someday"]).is_err());
+    assert!(AdminCommand::parse_export(&["--tag"]).is_err());
+}
+
+/// Two todos already in the target, one of them edited since the export, plus one new todo.
+async fn import_with(policy: ConflictPolicy) -> (SqliteTodoRepository, Vec<Todo>, anyhow::Result<ImportReport>) {
+    let target = spawn_test_repo(&isolated_db_url()).await;
+    let mut existing = Vec::new();
+    for title in ["a", "b"] {
+        existing.push(target.create(CreateTodo { title: title.into(), ..Default::default() }).await.unwrap());
+    }
+    let fresh = spawn_test_repo(&isolated_db_url()).await.create(CreateTodo { title: "c".into(), ..Default::default() }).await.unwrap();
+    let mut incoming = existing.clone();
+    incoming[0].title = "a (edited)".into();
+    incoming.push(fresh);
+    let report = target.import_todos(incoming.clone(), policy).await;
+    (target, incoming, report)
+}
+
+#[tokio::test]
+async fn acceptance_import_conflict_skip_keeps_existing() {
+    let (target, incoming, report) = import_with(ConflictPolicy::Skip).await;
+    assert_eq!(report.unwrap(), ImportReport { inserted: 1, skipped: 2, overwritten: 0 });
+    assert_eq!(target.get(incoming[0].id.clone(), None).await.unwrap().unwrap().title, "a");
+    assert!(target.get(incoming[2].id.clone(), None).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn acceptance_import_conflict_overwrite_replaces_existing() {
+    let (target, incoming, report) = import_with(ConflictPolicy::Overwrite).await;
+    assert_eq!(report.unwrap(), ImportReport { inserted: 1, skipped: 0, overwritten: 2 });
+    assert_eq!(target.get(incoming[0].id.clone(), None).await.unwrap().unwrap().title, "a (edited)");
+    assert!(target.get(incoming[2].id.clone(), None).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn acceptance_import_conflict_fail_rolls_back() {
+    let (target, incoming, report) = import_with(ConflictPolicy::Fail).await;
+    assert!(report.is_err());
+    assert_eq!(target.get(incoming[0].id.clone(), None).await.unwrap().unwrap().title, "a");
+    assert!(target.get(incoming[2].id.clone(), None).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn acceptance_stream_yields_every_row_one_at_a_time() {
+    use futures_util::StreamExt;
+
+    let repo = spawn_test_repo(&isolated_db_url()).await;
+    for i in 0..300 {
+        repo.create(CreateTodo { title: format!("todo {}", i), ..Default::default() }).await.unwrap();
+    }
+    let gone = repo.create(CreateTodo { title: "deleted".into(), ..Default::default() }).await.unwrap();
+    repo.delete(gone.id).await.unwrap();
+
+    // consume row by row, keeping only a counter and the previous timestamp
+    let mut stream = repo.stream(TodoFilter::default());
+    let (mut count, mut previous) = (0, None);
+    while let Some(todo) = stream.next().await {
+        let todo = todo.unwrap();
+        assert!(previous <= Some(todo.created_at), "oldest first");
+        previous = Some(todo.created_at);
+        count += 1;
+    }
+    assert_eq!(count, 300);
+    drop(stream);
+
+    let mut out = Vec::new();
+    assert_eq!(maintenance::export_ndjson(&repo, TodoFilter::default(), &mut out).await.unwrap(), 300);
+    let lines: Vec<Todo> = String::from_utf8(out).unwrap().lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lines.len(), 300);
+    assert_eq!(lines[0].title, "todo 0");
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use api::http::{auth::{self, AuthConfig, MagicLinkSender}, rate_limit::{self, RateLimitConfig}};
+use axum::{body::{to_bytes, Body}, http::Request, Router};
+use serde_json::json;
+use tower::ServiceExt;
+
+use common::{app_for, isolated_db_url, request, spawn_test_repo};
+
+/// Keeps sent links so the test can follow them.
+#[derive(Default)]
+struct Outbox(Mutex<Vec<(String, String)>>);
+
+#[async_trait::async_trait]
+impl MagicLinkSender for Outbox {
+    async fn send(&self, email: &str, link: &str) -> anyhow::Result<()> {
+        self.0.lock().unwrap().push((email.to_string(), link.to_string()));
+        Ok(())
+    }
+}
+
+async fn protected_app(config: AuthConfig, outbox: Arc<Outbox>) -> Router {
+    let todos = app_for(spawn_test_repo(&isolated_db_url()).await);
+    auth::protect(todos, config.clone()).merge(auth::router(config, outbox))
+}
+
+async fn get_with_token(app: &Router, path: &str, token: &str) -> hyper::Response<Body> {
+    let req = Request::get(path).header("authorization", format!("Bearer {}", token)).body(Body::empty()).unwrap();
+    app.clone().oneshot(req).await.unwrap()
+}
+
+async fn sign_in(app: &Router, outbox: &Outbox, email: &str) -> hyper::Response<Body> {
+    let res = request(app, "POST", "/auth/magic-link", Some(json!({ "email": email }))).await;
+    assert_eq!(res.status(), 202);
+    let (_, link) = outbox.0.lock().unwrap().pop().unwrap();
+    let path = link.strip_prefix("http://127.0.0.1:3000").unwrap().to_string();
+    request(app, "GET", &path, None).await
+}
+
+#[tokio::test]
+async fn acceptance_magic_link_issues_bearer_token() {
+    let outbox = Arc::new(Outbox::default());
+    let app = protected_app(AuthConfig::new("test-secret"), outbox.clone()).await;
+
+    let res = request(&app, "GET", "/todos", None).await;
+    assert_eq!(res.status(), 401);
+
+    let res = sign_in(&app, &outbox, "Me@Example.com").await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let token = body["access_token"].as_str().unwrap();
+    assert_eq!(AuthConfig::new("test-secret").verify(token, auth::Purpose::Access).unwrap().sub, "me@example.com");
+
+    let res = get_with_token(&app, "/todos", token).await;
+    assert_eq!(res.status(), 200);
+
+    // a token signed with another secret, or a link token, is not a bearer token
+    let forged = AuthConfig::new("other-secret").issue("me@example.com", auth::Purpose::Access).unwrap();
+    assert_eq!(get_with_token(&app, "/todos", &forged).await.status(), 401);
+    let link_token = AuthConfig::new("test-secret").issue("me@example.com", auth::Purpose::MagicLink).unwrap();
+    assert_eq!(get_with_token(&app, "/todos", &link_token).await.status(), 401);
+}
+
+#[tokio::test]
+async fn acceptance_expired_tokens_are_rejected() {
+    let outbox = Arc::new(Outbox::default());
+    let mut config = AuthConfig::new("test-secret");
+    config.access_ttl = chrono::Duration::seconds(-1);
+    let app = protected_app(config.clone(), outbox.clone()).await;
+
+    let res = sign_in(&app, &outbox, "me@example.com").await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let res = get_with_token(&app, "/todos", body["access_token"].as_str().unwrap()).await;
+    assert_eq!(res.status(), 401);
+
+    config.link_ttl = chrono::Duration::seconds(-1);
+    let app = protected_app(config, outbox.clone()).await;
+    let res = sign_in(&app, &outbox, "me@example.com").await;
+    assert_eq!(res.status(), 401);
+}
+
+#[tokio::test]
+async fn acceptance_rate_limit_throttles_a_burst_per_caller() {
+    let outbox = Arc::new(Outbox::default());
+    let config = AuthConfig::new("test-secret");
+    // Slow enough that nothing drains while the test runs
+    let todos = rate_limit::apply(app_for(spawn_test_repo(&isolated_db_url()).await), RateLimitConfig { rate_per_sec: 0.01, burst: 3 });
+    let app = auth::protect(todos, config.clone()).merge(auth::router(config, outbox.clone()));
+    let mut tokens = Vec::new();
+    for email in ["a@example.com", "b@example.com"] {
+        let res = sign_in(&app, &outbox, email).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        tokens.push(body["access_token"].as_str().unwrap().to_string());
+    }
+
+    for _ in 0..3 { assert_eq!(get_with_token(&app, "/todos", &tokens[0]).await.status(), 200); }
+    let res = get_with_token(&app, "/todos", &tokens[0]).await;
+    assert_eq!(res.status(), 429);
+    let retry_after: u64 = res.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+    assert!((1..=100).contains(&retry_after), "{}", retry_after);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["code"], "too_many_requests");
+
+    // another caller has a bucket of their own
+    assert_eq!(get_with_token(&app, "/todos", &tokens[1]).await.status(), 200);
+}
+
+async fn send_with_token(app: &Router, method: &str, path: &str, token: &str, body: Option<serde_json::Value>) -> hyper::Response<Body> {
+    let req = Request::builder().method(method).uri(path).header("authorization", format!("Bearer {}", token));
+    let req = match body {
+        Some(json) => req.header("content-type", "application/json").body(Body::from(json.to_string())).unwrap(),
+        None => req.body(Body::empty()).unwrap(),
+    };
+    app.clone().oneshot(req).await.unwrap()
+}
+
+async fn json_body(res: hyper::Response<Body>) -> serde_json::Value {
+    serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn acceptance_todos_are_scoped_to_the_signed_in_subject() {
+    let outbox = Arc::new(Outbox::default());
+    let app = protected_app(AuthConfig::new("test-secret"), outbox.clone()).await;
+    let mut tokens = Vec::new();
+    for email in ["a@example.com", "b@example.com"] {
+        let body = json_body(sign_in(&app, &outbox, email).await).await;
+        tokens.push(body["access_token"].as_str().unwrap().to_string());
+    }
+    let (alice, bob) = (&tokens[0], &tokens[1]);
+
+    let created = json_body(send_with_token(&app, "POST", "/todos", alice, Some(json!({ "title": "Alice's" }))).await).await;
+    let path = format!("/todos/{}", created["id"].as_str().unwrap());
+    assert_eq!(json_body(get_with_token(&app, "/todos", alice).await).await["total"], 1);
+    assert_eq!(get_with_token(&app, &path, alice).await.status(), 200);
+
+    // to the other subject the todo doesn't exist
+    assert_eq!(json_body(get_with_token(&app, "/todos", bob).await).await["total"], 0);
+    assert_eq!(json_body(get_with_token(&app, "/todos/stats", bob).await).await["total"], 0);
+    assert_eq!(get_with_token(&app, &path, bob).await.status(), 404);
+    assert_eq!(get_with_token(&app, &format!("/todos/code/{}", created["code"].as_str().unwrap()), bob).await.status(), 404);
+    assert_eq!(send_with_token(&app, "PUT", &path, bob, Some(json!({ "title": "Bob's now" }))).await.status(), 404);
+    assert_eq!(send_with_token(&app, "DELETE", &path, bob, None).await.status(), 404);
+    assert_eq!(json_body(get_with_token(&app, "/todos/export", bob).await).await, json!([]));
+    let import = json!([{ "id": created["id"], "title": "Taken", "description": null, "status": "pending", "code": "TODO-ZZZZ", "estimate_minutes": null,
+        "created_at": created["created_at"], "updated_at": created["updated_at"], "completed_at": null, "deleted_at": null, "due_at": null }]);
+    assert_eq!(send_with_token(&app, "POST", "/todos/import", bob, Some(import)).await.status(), 409);
+
+    let unchanged = json_body(get_with_token(&app, &path, alice).await).await;
+    assert_eq!(unchanged["title"], "Alice's");
+}
@@ -0,0 +1,43 @@
+mod common;
+
+use std::time::Duration;
+
+use api::domain::{repository::TodoRepository, todo::CreateTodo};
+use api::infrastructure::{backup::{self, BackupConfig}, sqlite_repo::SqliteTodoRepository};
+
+use common::{isolated_db_url, spawn_test_repo};
+
+#[tokio::test]
+async fn acceptance_backup_writes_snapshots_and_keeps_the_newest() {
+    let root = std::env::temp_dir().join(format!("backup-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&root).unwrap();
+    let repo = spawn_test_repo(&format!("sqlite://{}?mode=rwc", root.join("live.db").display())).await;
+    repo.create(CreateTodo { title: "precious".into(), ..Default::default() }).await.unwrap();
+    let dir = root.join("backups");
+    let config = BackupConfig { dir: dir.clone(), interval: Duration::from_secs(3600), keep: 2 };
+
+    let mut written = Vec::new();
+    for _ in 0..3 {
+        written.push(backup::backup_once(&repo, &config).await.unwrap());
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    let mut left: Vec<_> = std::fs::read_dir(&dir).unwrap().map(|e| e.unwrap().path()).collect();
+    left.sort();
+    assert_eq!(left, written[1..]);
+
+    // a backup is a complete database in its own right
+    let copy = SqliteTodoRepository::connect(&format!("sqlite://{}", written[2].display())).await.unwrap();
+    let todos = copy.list(Default::default(), Default::default()).await.unwrap();
+    assert_eq!(todos.len(), 1);
+    assert_eq!(todos[0].title, "precious");
+
+    let _ = std::fs::remove_dir_all(root);
+}
+
+#[tokio::test]
+async fn acceptance_backup_is_off_for_in_memory_databases() {
+    let url = isolated_db_url();
+    let repo = spawn_test_repo(&url).await;
+    let config = BackupConfig { dir: std::env::temp_dir(), interval: Duration::from_secs(1), keep: 1 };
+    assert!(backup::spawn(repo, &url, config).is_none());
+}
@@ -0,0 +1,192 @@
+mod common;
+
+use std::time::Duration;
+
+use api::domain::{repository::TodoRepository, todo::{CreateTodo, Recurrence, TodoStatus, UpdateTodo}};
+use api::http::write_queue::{self, WriteQueueConfig};
+use api::infrastructure::sqlite_repo::SqliteTodoRepository;
+use axum::{routing::post, Router};
+use serde_json::json;
+
+use common::{app_for, request, spawn_test_repo};
+
+/// The server and the TUI are separate pools (usually separate processes) on one file.
+/// Under WAL with a busy timeout, their writes interleave instead of failing with "database is locked".
+#[tokio::test]
+async fn acceptance_two_connections_write_concurrently_under_wal() {
+    let dir = std::env::temp_dir().join(format!("concurrency-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let url = format!("sqlite://{}?mode=rwc", dir.join("shared.db").display());
+    let server = spawn_test_repo(&url).await;
+    let tui = SqliteTodoRepository::connect(&url).await.unwrap();
+
+    let writes = |repo: SqliteTodoRepository, who: &'static str| tokio::spawn(async move {
+        for i in 0..25 {
+            repo.create(CreateTodo { title: format!("{} {}", who, i), ..Default::default() }).await.unwrap();
+        }
+    });
+    let (a, b) = tokio::join!(writes(server.clone(), "server"), writes(tui.clone(), "tui"));
+    a.unwrap();
+    b.unwrap();
+
+    assert_eq!(server.list(Default::default(), Default::default()).await.unwrap().len(), 50);
+    assert_eq!(tui.list(Default::default(), Default::default()).await.unwrap().len(), 50);
+    // WAL is recorded in the file itself, so any later connection sees it
+    let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+    let (mode,): (String,) = sqlx::query_as("PRAGMA journal_mode").fetch_one(&pool).await.unwrap();
+    assert_eq!(mode, "wal");
+
+    pool.close().await;
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+/// Defers from many tasks on two pools at once; a read-modify-write would lose some of them.
+#[tokio::test]
+async fn acceptance_parallel_defers_are_all_counted() {
+    let dir = std::env::temp_dir().join(format!("defer-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let url = format!("sqlite://{}?mode=rwc", dir.join("defer.db").display());
+    let a = spawn_test_repo(&url).await;
+    let b = SqliteTodoRepository::connect(&url).await.unwrap();
+    let todo = a.create(CreateTodo { title: "put off".into(), ..Default::default() }).await.unwrap();
+
+    let tasks: Vec<_> = (0..40).map(|i| {
+        let repo = if i % 2 == 0 { a.clone() } else { b.clone() };
+        let id = todo.id.clone();
+        tokio::spawn(async move { repo.increment_defer_count(id).await.unwrap().unwrap() })
+    }).collect();
+    let mut seen = Vec::new();
+    for task in tasks { seen.push(task.await.unwrap()); }
+    seen.sort();
+
+    // every increment returned a distinct value
+    assert_eq!(seen, (1..=40).collect::<Vec<i64>>());
+    assert_eq!(a.get(todo.id.clone(), None).await.unwrap().unwrap().defer_count, 40);
+    assert!(a.increment_defer_count(api::domain::todo::TodoId(uuid::Uuid::new_v4())).await.unwrap().is_none());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Racing completions of one recurring todo, from two pools, create a single next occurrence.
+#[tokio::test]
+async fn acceptance_racing_completions_recur_once() {
+    let dir = std::env::temp_dir().join(format!("recur-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let url = format!("sqlite://{}?mode=rwc", dir.join("recur.db").display());
+    let a = spawn_test_repo(&url).await;
+    let b = SqliteTodoRepository::connect(&url).await.unwrap();
+    let todo = a.create(CreateTodo { title: "water plants".into(), recurrence: Some(Recurrence::Daily), ..Default::default() }).await.unwrap();
+
+    let tasks: Vec<_> = (0..10).map(|i| {
+        let repo = if i % 2 == 0 { a.clone() } else { b.clone() };
+        let id = todo.id.clone();
+        tokio::spawn(async move { repo.update(id, UpdateTodo { status: Some(TodoStatus::Done), ..Default::default() }).await.unwrap().unwrap().next })
+    }).collect();
+    let mut spawned = 0;
+    for task in tasks { spawned += usize::from(task.await.unwrap().is_some()); }
+
+    assert_eq!(spawned, 1);
+    let pending = a.list(api::domain::todo::TodoFilter { status: Some(TodoStatus::Pending), ..Default::default() }, Default::default()).await.unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_ne!(pending[0].id, todo.id);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// A burst of API writes on a file database goes through the write queue one at a time, so
+/// none of them fails on the SQLite write lock.
+#[tokio::test]
+async fn acceptance_write_queue_serializes_a_burst_of_writes() {
+    let dir = std::env::temp_dir().join(format!("write-queue-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let url = format!("sqlite://{}?mode=rwc", dir.join("queue.db").display());
+    let repo = spawn_test_repo(&url).await;
+    let app = write_queue::apply(app_for(repo.clone()), WriteQueueConfig { depth: 100 });
+
+    let tasks: Vec<_> = (0..60).map(|i| {
+        let app = app.clone();
+        tokio::spawn(async move { request(&app, "POST", "/todos", Some(json!({ "title": format!("burst {}", i) }))).await.status() })
+    }).collect();
+    for task in tasks { assert_eq!(task.await.unwrap(), 200); }
+    assert_eq!(repo.list(Default::default(), Default::default()).await.unwrap().len(), 60);
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+/// Writes beyond the queue depth are turned away with 503 while reads go straight through.
+#[tokio::test]
+async fn acceptance_write_queue_rejects_writes_when_full() {
+    let slow = Router::new().route("/slow", post(|| async { tokio::time::sleep(Duration::from_millis(300)).await; "written" }).get(|| async { "read" }));
+    let app = write_queue::apply(slow, WriteQueueConfig { depth: 2 });
+
+    let running: Vec<_> = (0..2).map(|_| {
+        let app = app.clone();
+        tokio::spawn(async move { request(&app, "POST", "/slow", None).await.status() })
+    }).collect();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let rejected = request(&app, "POST", "/slow", None).await;
+    assert_eq!(rejected.status(), 503);
+    assert_eq!(rejected.headers()["retry-after"], "1");
+    assert_eq!(request(&app, "GET", "/slow", None).await.status(), 200);
+    for task in running { assert_eq!(task.await.unwrap(), 200); }
+    // Slots are given back once the writes finish
+    assert_eq!(request(&app, "POST", "/slow", None).await.status(), 200);
+}
+
+/// A JSON Patch whose `test` passed must not overwrite a PUT that landed after the patch read
+/// the todo: either the patch wins the race and the PUT comes after it, or the patch is a 409.
+#[tokio::test]
+async fn acceptance_json_patch_does_not_overwrite_an_interleaved_put() {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    let app = common::spawn_test_app().await;
+    for round in 0..30 {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": "original" }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&axum::body::to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        let path = format!("/todos/{}", body["id"].as_str().unwrap());
+
+        let ops = json!([{ "op": "test", "path": "/title", "value": "original" }, { "op": "replace", "path": "/title", "value": "patched" }]);
+        let patch = Request::patch(&path).header("content-type", "application/json-patch+json").body(Body::from(ops.to_string())).unwrap();
+        let (patched, put) = tokio::join!(
+            app.clone().oneshot(patch),
+            request(&app, "PUT", &path, Some(json!({ "title": "put" }))),
+        );
+        let patched = patched.unwrap().status();
+        assert_eq!(put.status(), 200);
+        assert!(patched == 200 || patched == 409, "round {}: {}", round, patched);
+
+        let res = request(&app, "GET", &path, None).await;
+        let body: serde_json::Value = serde_json::from_slice(&axum::body::to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        // Whatever the order, the PUT's title is the last written: a patch that read before the
+        // PUT either wrote first or fails the version check, and one that read after fails `test`
+        assert_eq!(body["title"], "put", "round {}: patch answered {}", round, patched);
+    }
+}
+
+/// Each changelog entry is diffed against the row its update replaced, so under concurrent
+/// updates every entry starts where the previous one ended.
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn acceptance_concurrent_updates_log_an_unbroken_history() {
+    let dir = std::env::temp_dir().join(format!("changelog-test-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let url = format!("sqlite://{}?mode=rwc", dir.join("changes.db").display());
+    let app = app_for(spawn_test_repo(&url).await);
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "start" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&axum::body::to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let path = format!("/todos/{}", body["id"].as_str().unwrap());
+
+    let tasks: Vec<_> = (0..20).map(|i| {
+        let (app, path) = (app.clone(), path.clone());
+        tokio::spawn(async move { request(&app, "PUT", &path, Some(json!({ "title": format!("title {}", i) }))).await.status() })
+    }).collect();
+    for task in tasks { assert_eq!(task.await.unwrap(), 200); }
+
+    let res = request(&app, "GET", &format!("{}/changes", path), None).await;
+    let body: serde_json::Value = serde_json::from_slice(&axum::body::to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let titles: Vec<(&str, &str)> = body["items"].as_array().unwrap().iter()
+        .map(|c| (c["fields"]["title"]["from"].as_str().unwrap(), c["fields"]["title"]["to"].as_str().unwrap()))
+        .collect();
+    assert_eq!(titles.len(), 20);
+    assert_eq!(titles[0].0, "start");
+    for pair in titles.windows(2) { assert_eq!(pair[0].1, pair[1].0, "{:?}", titles); }
+    let _ = std::fs::remove_dir_all(&dir);
+}
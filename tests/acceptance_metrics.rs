@@ -0,0 +1,28 @@
+mod common;
+
+use axum::body::to_bytes;
+
+use common::{request, spawn_test_app};
+
+// The recorder is process-global, so this is the only test in its binary
+#[tokio::test]
+async fn acceptance_metrics_count_requests_by_route_template() {
+    let handle = api::http::routing::install_metrics().unwrap();
+    let app = spawn_test_app().await.merge(api::http::routing::metrics_router(handle));
+
+    let res = request(&app, "POST", "/todos", Some(serde_json::json!({ "title": "count me" }))).await;
+    assert_eq!(res.status(), 200);
+    for _ in 0..2 { request(&app, "GET", &format!("/todos/{}", uuid::Uuid::new_v4()), None).await; }
+    request(&app, "GET", "/health/live", None).await;
+
+    let res = request(&app, "GET", "/metrics", None).await;
+    assert_eq!(res.status(), 200);
+    let text = String::from_utf8(to_bytes(res.into_body(), 1024 * 1024).await.unwrap().to_vec()).unwrap();
+    let line = |prefix: &str| text.lines().find(|l| l.starts_with(prefix)).map(str::to_string);
+    assert_eq!(line(r#"http_requests_total{method="GET",path="/todos/:id",status="404"}"#).as_deref(), Some(r#"http_requests_total{method="GET",path="/todos/:id",status="404"} 2"#));
+    assert!(line(r#"http_requests_total{method="POST",path="/todos",status="200"}"#).is_some(), "{}", text);
+    assert!(line(r#"http_request_duration_seconds_count{method="GET",path="/todos/:id"}"#).is_some(), "{}", text);
+    // health checks are counted but kept out of the latency histogram
+    assert!(line(r#"http_requests_total{method="GET",path="/health/live",status="200"}"#).is_some(), "{}", text);
+    assert!(!text.contains(r#"http_request_duration_seconds_count{method="GET",path="/health/live"}"#), "{}", text);
+}
@@ -0,0 +1,49 @@
+//! Runs only with `--features postgres` and a `DATABASE_URL` pointing at a scratch PostgreSQL
+//! database, e.g. `DATABASE_URL=postgres://localhost/todos_test cargo test --features postgres`.
+#![cfg(feature = "postgres")]
+
+use api::domain::{repository::TodoRepository, todo::{CreateTodo, TodoFilter, TodoStatus, UpdateTodo}};
+use api::infrastructure::postgres_repo::PostgresTodoRepository;
+use futures_util::TryStreamExt;
+
+async fn postgres_repo() -> Option<PostgresTodoRepository> {
+    let url = std::env::var("DATABASE_URL").ok().filter(|u| u.starts_with("postgres://") || u.starts_with("postgresql://"))?;
+    let repo = PostgresTodoRepository::connect(&url).await.unwrap();
+    repo.init().await.unwrap();
+    Some(repo)
+}
+
+#[tokio::test]
+async fn acceptance_postgres_round_trips_todos() {
+    let Some(repo) = postgres_repo().await else {
+        eprintln!("DATABASE_URL is not a postgres URL; skipping");
+        return;
+    };
+    // init is idempotent
+    repo.init().await.unwrap();
+    assert!(repo.is_schema_current().await.unwrap());
+
+    let title = format!("pg {}", uuid::Uuid::new_v4());
+    let created = repo.create(CreateTodo { title: title.clone(), description: Some(Some("notes".into())), tags: vec!["pg".into(), title.clone()], ..Default::default() }).await.unwrap();
+    let got = repo.get(created.id.clone(), None).await.unwrap().unwrap();
+    assert_eq!(got.tags, created.tags);
+    assert_eq!(repo.list_by_tag(&title.to_uppercase()).await.unwrap().len(), 1);
+    assert_eq!(got.title, title);
+    assert_eq!(got.description.as_deref(), Some("notes"));
+    assert_eq!(repo.get_by_code(&created.code.to_lowercase(), None).await.unwrap().unwrap().id, created.id);
+
+    let done = repo.update(created.id.clone(), UpdateTodo { status: Some(TodoStatus::Done), description: Some(None), ..Default::default() }).await.unwrap().unwrap().todo;
+    assert!(done.completed_at.is_some());
+    assert_eq!(done.description, None);
+    let filter = TodoFilter { status: Some(TodoStatus::Done), ..Default::default() };
+    assert!(repo.list(filter.clone(), Default::default()).await.unwrap().iter().any(|t| t.id == created.id));
+    assert!(repo.list_paged(filter, Default::default(), 500, 0).await.unwrap().total >= 1);
+    assert_eq!(repo.increment_defer_count(created.id.clone()).await.unwrap(), Some(1));
+    assert!(repo.stream(TodoFilter::default()).try_collect::<Vec<_>>().await.unwrap().iter().any(|t| t.id == created.id));
+
+    assert!(repo.delete(created.id.clone()).await.unwrap());
+    assert!(repo.get(created.id.clone(), None).await.unwrap().is_none());
+    assert!(repo.restore(created.id.clone()).await.unwrap().is_some());
+    assert!(repo.purge(created.id.clone()).await.unwrap());
+    assert!(repo.get_including_deleted(created.id, None).await.unwrap().is_none());
+}
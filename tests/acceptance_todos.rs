@@ -1,16 +1,15 @@
-use api::{application::todo_service::TodoServiceImpl, http::routing, http::routing::todos, infrastructure::sqlite_repo::SqliteTodoRepository};
-use api::domain::repository::TodoRepository;
+mod common;
+
+use api::{application::todo_service::{ControlCharPolicy, TodoServiceConfig, TodoServiceImpl}, http::cache::{self, CacheConfig}, http::routing, http::routing::todos, http::routing::{admin, health}, infrastructure::sqlite_repo::SqliteTodoRepository};
 use axum::body::to_bytes;
 use axum::Router;
 use serde_json::json;
 
+use common::{app_with_config, isolated_db_url, request, spawn_test_app, spawn_test_app_with_pool, spawn_test_repo};
+
 #[tokio::test]
 async fn acceptance_create_list_get_update_delete() {
-    // use in-memory sqlite for tests
-    let repo = SqliteTodoRepository::connect("sqlite::memory:").await.unwrap();
-    repo.init().await.unwrap();
-    let service = TodoServiceImpl::new(repo);
-    let app: Router = routing::app(todos::router(todos::AppState { service }));
+    let app = spawn_test_app().await;
 
     // create
     let payload = json!({ "title": "Test", "description": "First" });
@@ -40,15 +39,1679 @@ async fn acceptance_create_list_get_update_delete() {
     assert_eq!(res.status(), 404);
 }
 
-async fn request(app: &Router, method: &str, path: &str, body: Option<serde_json::Value>) -> hyper::Response<axum::body::Body> {
+#[tokio::test]
+async fn acceptance_query_by_ids() {
+    let app = spawn_test_app().await;
+
+    let mut ids = Vec::new();
+    for i in 0..40 {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": format!("Todo {}", i) }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    for id in ids.iter().take(5) {
+        request(&app, "PUT", &format!("/todos/{}", id), Some(json!({"status":"done"}))).await;
+    }
+
+    // unknown ids are ignored
+    let mut queried = ids[..36].to_vec();
+    queried.push(uuid::Uuid::new_v4().to_string());
+    let res = request(&app, "POST", "/todos/query", Some(json!({ "ids": queried }))).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["items"].as_array().unwrap().len(), 36);
+
+    // optional status filter
+    let res = request(&app, "POST", "/todos/query", Some(json!({ "ids": ids, "status": "done" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["items"].as_array().unwrap().len(), 5);
+
+    // malformed id
+    let res = request(&app, "POST", "/todos/query", Some(json!({ "ids": ["nope"] }))).await;
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn acceptance_workload_sums_estimates() {
+    let app = spawn_test_app().await;
+
+    let mut ids = Vec::new();
+    for (title, estimate) in [("A", json!(30)), ("B", json!(45)), ("C", json!(null)), ("D", json!(60))] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title, "estimate_minutes": estimate }))).await;
+        assert_eq!(res.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        assert_eq!(body["estimate_minutes"], estimate);
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    request(&app, "PUT", &format!("/todos/{}", ids[3]), Some(json!({"status":"done"}))).await;
+
+    let res = request(&app, "GET", "/todos/workload?status=pending", None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body, json!({ "total_minutes": 75, "count": 3 }));
+
+    let res = request(&app, "GET", "/todos/workload", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["total_minutes"], 135);
+
+    // negative estimates are rejected
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "E", "estimate_minutes": -5 }))).await;
+    assert_eq!(res.status(), 400);
+    let res = request(&app, "PUT", &format!("/todos/{}", ids[0]), Some(json!({ "estimate_minutes": -1 }))).await;
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn acceptance_health_pings_the_database() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "GET", "/health", None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["status"], "ok");
+}
+
+#[tokio::test]
+async fn acceptance_liveness_and_readiness() {
+    let app = spawn_test_app().await;
+
+    let res = request(&app, "GET", "/health/live", None).await;
+    assert_eq!(res.status(), 200);
+    let res = request(&app, "GET", "/health/ready", None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["status"], "ready");
+
+    // a database whose schema was never created is not ready
+    let repo = SqliteTodoRepository::connect(&isolated_db_url()).await.unwrap();
+    let app: Router = routing::app(health::router(repo));
+    let res = request(&app, "GET", "/health/ready", None).await;
+    assert_eq!(res.status(), 503);
+}
+
+#[tokio::test]
+async fn acceptance_velocity_groups_completions_by_week() {
+    let (app, pool) = spawn_test_app_with_pool().await;
+
+    // 1 completion three weeks ago, 1 two weeks ago, 3 in the last week
+    for days_ago in [20, 9, 1, 2, 3] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": format!("done {} days ago", days_ago) }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        let id = body["id"].as_str().unwrap().to_string();
+        let res = request(&app, "PUT", &format!("/todos/{}", id), Some(json!({"status":"done"}))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        assert!(body["completed_at"].is_string());
+        let completed_at = chrono::Utc::now() - chrono::Duration::days(days_ago);
+        sqlx::query("UPDATE todos SET completed_at = ?1 WHERE id = ?2").bind(completed_at.to_rfc3339()).bind(&id).execute(&pool).await.unwrap();
+    }
+    // pending todos don't count
+    request(&app, "POST", "/todos", Some(json!({ "title": "still pending" }))).await;
+
+    let res = request(&app, "GET", "/stats/velocity?weeks=4", None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let counts: Vec<i64> = body["weeks"].as_array().unwrap().iter().map(|w| w["completed"].as_i64().unwrap()).collect();
+    assert_eq!(counts, vec![0, 1, 1, 3]);
+    assert_eq!(body["trend"], "up");
+
+    let res = request(&app, "GET", "/stats/velocity?weeks=0", None).await;
+    assert_eq!(res.status(), 400);
+
+}
+
+#[tokio::test]
+async fn acceptance_create_with_gzipped_body() {
+    use axum::body::Body;
+    use axum::http::Request;
+    use std::io::Write;
+    use tower::ServiceExt;
+
+    let app = spawn_test_app().await;
+
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(json!({ "title": "Compressed", "description": "gzip" }).to_string().as_bytes()).unwrap();
+    let gzipped = encoder.finish().unwrap();
+
+    let req = Request::post("/todos")
+        .header("content-type", "application/json")
+        .header("content-encoding", "gzip")
+        .body(Body::from(gzipped.clone()))
+        .unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["title"], "Compressed");
+
+    let req = Request::post("/todos")
+        .header("content-type", "application/json")
+        .header("content-encoding", "br")
+        .body(Body::from(gzipped))
+        .unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), 415);
+}
+
+#[tokio::test]
+async fn acceptance_get_by_code() {
+    let app = spawn_test_app().await;
+
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Coded" }))).await;
+    let created: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let code = created["code"].as_str().unwrap().to_string();
+    assert!(code.starts_with("TODO-") && code.len() == 9, "unexpected code {}", code);
+
+    let res = request(&app, "GET", &format!("/todos/code/{}", code.to_lowercase()), None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["id"], created["id"]);
+
+    let res = request(&app, "GET", "/todos/code/TODO-NOPE", None).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn acceptance_list_filters_by_status_and_completed_after() {
+    let (app, pool) = spawn_test_app_with_pool().await;
+
+    let mut ids = Vec::new();
+    for title in ["done this week", "done last month", "pending"] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    for id in &ids[..2] {
+        request(&app, "PUT", &format!("/todos/{}", id), Some(json!({"status":"done"}))).await;
+    }
+    let month_ago = chrono::Utc::now() - chrono::Duration::days(30);
+    sqlx::query("UPDATE todos SET completed_at = ?1 WHERE id = ?2").bind(month_ago.to_rfc3339()).bind(&ids[1]).execute(&pool).await.unwrap();
+
+    let week_ago = (chrono::Utc::now() - chrono::Duration::days(7)).format("%Y-%m-%d");
+    let res = request(&app, "GET", &format!("/todos?status=done&completed_after={}", week_ago), None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let titles: Vec<&str> = body["items"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, vec!["done this week"]);
+
+    let res = request(&app, "GET", "/todos?status=done", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["items"].as_array().unwrap().len(), 2);
+
+    let res = request(&app, "GET", "/todos?status=done&completed_after=last-tuesday", None).await;
+    assert_eq!(res.status(), 400);
+
+}
+
+#[tokio::test]
+async fn acceptance_admin_reindex_requires_key() {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    let repo = spawn_test_repo(&isolated_db_url()).await;
+    let app: Router = routing::app(admin::router(repo, "s3cret".into()));
+
+    let res = request(&app, "POST", "/admin/reindex", None).await;
+    assert_eq!(res.status(), 401);
+
+    let req = Request::post("/admin/reindex").header("x-admin-key", "s3cret").body(Body::empty()).unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["status"], "ok");
+    assert!(body["elapsed_ms"].is_u64());
+}
+
+#[tokio::test]
+async fn acceptance_archived_pagination() {
+    let app = spawn_test_app().await;
+
+    for title in ["a", "b", "c", "d", "e"] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        let id = body["id"].as_str().unwrap();
+        request(&app, "PUT", &format!("/todos/{}", id), Some(json!({"status":"archived"}))).await;
+    }
+    request(&app, "POST", "/todos", Some(json!({ "title": "active" }))).await;
+
+    // the default list leaves archived todos out
+    let res = request(&app, "GET", "/todos", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["items"].as_array().unwrap().len(), 1);
+
+    let mut titles = Vec::new();
+    let mut path = "/todos/archived?sort=title&order=asc&limit=2".to_string();
+    loop {
+        let res = request(&app, "GET", &path, None).await;
+        assert_eq!(res.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        titles.extend(body["items"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap().to_string()));
+        match body["next_offset"].as_u64() {
+            Some(next) => path = format!("/todos/archived?sort=title&order=asc&limit=2&offset={}", next),
+            None => break,
+        }
+    }
+    assert_eq!(titles, vec!["a", "b", "c", "d", "e"]);
+
+    let res = request(&app, "GET", "/todos/archived?sort=estimate", None).await;
+    assert_eq!(res.status(), 400);
+}
+
+#[tokio::test]
+async fn acceptance_batch_over_limit_is_rejected() {
+    let app = spawn_test_app().await;
+
+    let ids: Vec<String> = (0..1001).map(|_| uuid::Uuid::new_v4().to_string()).collect();
+    let res = request(&app, "POST", "/todos/query", Some(json!({ "ids": ids }))).await;
+    assert_eq!(res.status(), 422);
+    let body = String::from_utf8(to_bytes(res.into_body(), 1024 * 1024).await.unwrap().to_vec()).unwrap();
+    assert!(body.contains("1000"), "{}", body);
+
+    let res = request(&app, "POST", "/todos/query", Some(json!({ "ids": &ids[..1000] }))).await;
+    assert_eq!(res.status(), 200);
+
+    // Imports share the limit, checked before anything is written
+    request(&app, "POST", "/todos", Some(json!({ "title": "Template" }))).await;
+    let res = request(&app, "GET", "/todos/export", None).await;
+    let template = serde_json::from_slice::<serde_json::Value>(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap()[0].clone();
+    let todos: Vec<_> = ids.iter().enumerate().map(|(i, id)| {
+        let mut todo = template.clone();
+        todo["id"] = json!(id);
+        todo["code"] = json!(format!("TODO-{:04}", i));
+        todo
+    }).collect();
+    let res = request(&app, "POST", "/todos/import", Some(json!(todos))).await;
+    assert_eq!(res.status(), 422);
+    let res = request(&app, "GET", "/todos/stats", None).await;
+    let stats: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(stats["total"], 1);
+}
+
+#[tokio::test]
+async fn acceptance_admin_check_detects_and_fixes_inconsistencies() {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    let url = isolated_db_url();
+    let repo = spawn_test_repo(&url).await;
+    let app: Router = common::app_for(repo.clone()).merge(admin::router(repo, "s3cret".into()));
+    let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+
+    let mut ids = Vec::new();
+    for title in ["done, no completed_at", "pending, completed_at set", "fine", "due before created"] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    sqlx::query("UPDATE todos SET status = 'done' WHERE id = ?1").bind(&ids[0]).execute(&pool).await.unwrap();
+    sqlx::query("UPDATE todos SET completed_at = updated_at WHERE id = ?1").bind(&ids[1]).execute(&pool).await.unwrap();
+    sqlx::query("UPDATE todos SET due_at = '2020-01-01T00:00:00+00:00' WHERE id = ?1").bind(&ids[3]).execute(&pool).await.unwrap();
+
+    let admin = |method: &str| Request::builder().method(method).uri("/admin/check").header("x-admin-key", "s3cret").body(Body::empty()).unwrap();
+    let res = app.clone().oneshot(admin("GET")).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["count"], 3);
+    let found: Vec<(String, String)> = body["items"].as_array().unwrap().iter()
+        .map(|i| (i["id"].as_str().unwrap().to_string(), i["kind"].as_str().unwrap().to_string())).collect();
+    assert!(found.contains(&(ids[0].clone(), "done_without_completed_at".to_string())));
+    assert!(found.contains(&(ids[1].clone(), "completed_at_while_not_done".to_string())));
+    assert!(found.contains(&(ids[3].clone(), "due_before_created".to_string())));
+
+    let res = app.clone().oneshot(admin("POST")).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["fixed"], 2);
+
+    // A due date before creation is only reported
+    let res = app.clone().oneshot(admin("GET")).await.unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["count"], 1);
+    assert_eq!(body["items"][0]["kind"], "due_before_created");
+}
+
+#[tokio::test]
+async fn acceptance_list_with_range_header() {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    let app = spawn_test_app().await;
+    for i in 0..5 {
+        request(&app, "POST", "/todos", Some(json!({ "title": format!("Todo {}", i) }))).await;
+    }
+    let ranged = |range: &str| Request::get("/todos").header("range", range).body(Body::empty()).unwrap();
+
+    let res = app.clone().oneshot(ranged("items=0-1")).await.unwrap();
+    assert_eq!(res.status(), 206);
+    assert_eq!(res.headers()["content-range"], "items 0-1/5");
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["items"].as_array().unwrap().len(), 2);
+
+    // the end is clamped to what exists
+    let res = app.clone().oneshot(ranged("items=3-49")).await.unwrap();
+    assert_eq!(res.status(), 206);
+    assert_eq!(res.headers()["content-range"], "items 3-4/5");
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["items"].as_array().unwrap().len(), 2);
+
+    let res = app.clone().oneshot(ranged("items=5-9")).await.unwrap();
+    assert_eq!(res.status(), 416);
+    assert_eq!(res.headers()["content-range"], "items */5");
+
+    let res = app.clone().oneshot(ranged("items=4-2")).await.unwrap();
+    assert_eq!(res.status(), 400);
+
+    // without Range the whole list comes back as before
+    let res = request(&app, "GET", "/todos", None).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["accept-ranges"], "items");
+}
+
+#[tokio::test]
+async fn acceptance_open_ended_or_oversized_range_is_capped_at_the_list_limit() {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    let app = spawn_test_app().await;
+    let items: Vec<_> = (0..501).map(|i| json!({ "title": format!("Todo {}", i) })).collect();
+    assert_eq!(request(&app, "POST", "/todos/bulk", Some(json!(items))).await.status(), 200);
+
+    for (range, content_range) in [("items=0-", "items 0-499/501"), ("items=0-999999", "items 0-499/501"), ("items=1-", "items 1-500/501")] {
+        let res = app.clone().oneshot(Request::get("/todos").header("range", range).body(Body::empty()).unwrap()).await.unwrap();
+        assert_eq!(res.status(), 206, "{}", range);
+        assert_eq!(res.headers()["content-range"], content_range, "{}", range);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 16 * 1024 * 1024).await.unwrap()).unwrap();
+        assert_eq!(body["items"].as_array().unwrap().len(), 500, "{}", range);
+    }
+}
+
+#[tokio::test]
+async fn acceptance_list_limit_offset_with_total() {
+    let app = spawn_test_app().await;
+    for i in 0..7 {
+        request(&app, "POST", "/todos", Some(json!({ "title": format!("Todo {}", i) }))).await;
+    }
+    let page = |uri: String| {
+        let app = app.clone();
+        async move {
+            let res = request(&app, "GET", &uri, None).await;
+            assert_eq!(res.status(), 200);
+            serde_json::from_slice::<serde_json::Value>(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap()
+        }
+    };
+
+    let mut seen = Vec::new();
+    for offset in [0, 3, 6] {
+        let body = page(format!("/todos?limit=3&offset={}", offset)).await;
+        assert_eq!(body["total"], 7);
+        assert_eq!(body["limit"], 3);
+        assert_eq!(body["offset"], offset);
+        seen.extend(body["items"].as_array().unwrap().iter().map(|t| t["id"].as_str().unwrap().to_string()));
+    }
+    // pages don't overlap and cover everything
+    seen.sort();
+    seen.dedup();
+    assert_eq!(seen.len(), 7);
+
+    let body = page("/todos?offset=10".to_string()).await;
+    assert_eq!(body["total"], 7);
+    assert_eq!(body["limit"], 50);
+    assert!(body["items"].as_array().unwrap().is_empty());
+
+    // total follows the filter
+    let body = page("/todos?status=done&limit=1".to_string()).await;
+    assert_eq!(body["total"], 0);
+
+    assert_eq!(request(&app, "GET", "/todos?limit=501", None).await.status(), 400);
+    assert_eq!(request(&app, "GET", "/todos?limit=0", None).await.status(), 400);
+}
+
+#[tokio::test]
+async fn acceptance_get_deleted_is_gone_with_include_deleted() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "short-lived" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let id = body["id"].as_str().unwrap().to_string();
+    assert!(body["deleted_at"].is_null());
+    request(&app, "DELETE", &format!("/todos/{}", id), None).await;
+
+    // by default a deleted todo looks like any missing one
+    let res = request(&app, "GET", &format!("/todos/{}", id), None).await;
+    assert_eq!(res.status(), 404);
+
+    let res = request(&app, "GET", &format!("/todos/{}?include_deleted=true", id), None).await;
+    assert_eq!(res.status(), 410);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["title"], "short-lived");
+    assert!(body["deleted_at"].is_string());
+
+    // an id that never existed is still 404
+    let res = request(&app, "GET", &format!("/todos/{}?include_deleted=true", uuid::Uuid::new_v4()), None).await;
+    assert_eq!(res.status(), 404);
+
+    // deleting again finds nothing to delete
+    let res = request(&app, "DELETE", &format!("/todos/{}", id), None).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn acceptance_control_characters_are_stripped_or_rejected() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "nul\u{0}l \u{1b}[31mred", "description": "line 1\n\tline 2\u{7}" }))).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["title"], "null [31mred");
+    assert_eq!(body["description"], "line 1\n\tline 2");
+
+    let config = TodoServiceConfig { control_chars: ControlCharPolicy::Reject, ..Default::default() };
+    let app = app_with_config(spawn_test_repo(&isolated_db_url()).await, config);
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "bad\u{0}" }))).await;
+    assert_eq!(res.status(), 422);
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "ok", "description": "a\nb\tc" }))).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let path = format!("/todos/{}", body["id"].as_str().unwrap());
+    let res = request(&app, "PUT", &path, Some(json!({ "description": "\u{1b}]0;pwned\u{7}" }))).await;
+    assert_eq!(res.status(), 422);
+}
+
+#[tokio::test]
+async fn acceptance_done_without_description_is_422_when_required() {
+    use api::domain::todo::TodoStatus;
+
+    let config = TodoServiceConfig { description_required_for: vec![TodoStatus::Done], ..Default::default() };
+    let app = app_with_config(spawn_test_repo(&isolated_db_url()).await, config);
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "write report" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let path = format!("/todos/{}", body["id"].as_str().unwrap());
+
+    let res = request(&app, "PUT", &path, Some(json!({ "status": "done" }))).await;
+    assert_eq!(res.status(), 422);
+    let res = request(&app, "PUT", &path, Some(json!({ "status": "done", "description": "sent to the board" }))).await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn acceptance_cache_control_on_list_when_enabled() {
+    let cached = |max_age_secs| async move {
+        let service = TodoServiceImpl::new(spawn_test_repo(&isolated_db_url()).await);
+        routing::app(cache::apply(todos::router(todos::AppState { service }), CacheConfig { max_age_secs }))
+    };
+    let header = |res: &hyper::Response<axum::body::Body>| res.headers().get("cache-control").map(|v| v.to_str().unwrap().to_string());
+
+    let app = cached(None).await;
+    assert_eq!(header(&request(&app, "GET", "/todos", None).await).as_deref(), Some("no-store"));
+
+    let app = cached(Some(60)).await;
+    assert_eq!(header(&request(&app, "GET", "/todos", None).await).as_deref(), Some("max-age=60"));
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "A" }))).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(header(&res).as_deref(), Some("no-store"));
+    let res = request(&app, "GET", &format!("/todos/{}", uuid::Uuid::new_v4()), None).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(header(&res).as_deref(), Some("no-store"));
+}
+
+#[tokio::test]
+async fn acceptance_latest_tracks_most_recent_update() {
+    let app = spawn_test_app().await;
+    assert_eq!(request(&app, "GET", "/todos/latest", None).await.status(), 404);
+
+    let mut ids = Vec::new();
+    for title in ["first", "second"] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    let latest = |app: Router| async move {
+        let res = request(&app, "GET", "/todos/latest", None).await;
+        assert_eq!(res.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        body["id"].as_str().unwrap().to_string()
+    };
+    assert_eq!(latest(app.clone()).await, ids[1]);
+
+    request(&app, "PUT", &format!("/todos/{}", ids[0]), Some(json!({ "status": "done" }))).await;
+    assert_eq!(latest(app.clone()).await, ids[0]);
+}
+
+#[tokio::test]
+async fn acceptance_json_patch_replace_and_failed_test() {
     use axum::body::Body;
-    use axum::http::{Request, Method};
+    use axum::http::Request;
     use tower::ServiceExt;
 
-    let req = Request::builder().method(Method::from_bytes(method.as_bytes()).unwrap()).uri(path);
-    let req = match body {
-        Some(json) => req.header("content-type", "application/json").body(Body::from(json.to_string())).unwrap(),
-        None => req.body(Body::empty()).unwrap(),
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Draft", "description": "v1" }))).await;
+    let created: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let path = format!("/todos/{}", created["id"].as_str().unwrap());
+    let patch = |ops: serde_json::Value| {
+        Request::patch(&path).header("content-type", "application/json-patch+json").body(Body::from(ops.to_string())).unwrap()
+    };
+
+    let res = app.clone().oneshot(patch(json!([
+        { "op": "test", "path": "/title", "value": "Draft" },
+        { "op": "replace", "path": "/title", "value": "Final" },
+        { "op": "replace", "path": "/status", "value": "done" },
+    ]))).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["title"], "Final");
+    assert_eq!(body["status"], "done");
+    assert_eq!(body["description"], "v1");
+
+    // A failed test aborts the whole patch, including the ops before it
+    let res = app.clone().oneshot(patch(json!([
+        { "op": "replace", "path": "/description", "value": "v2" },
+        { "op": "test", "path": "/title", "value": "Draft" },
+    ]))).await.unwrap();
+    assert_eq!(res.status(), 409);
+    let res = request(&app, "GET", &path, None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["description"], "v1");
+
+    let res = app.clone().oneshot(patch(json!([{ "op": "replace", "path": "/code", "value": "TODO-0000" }]))).await.unwrap();
+    assert_eq!(res.status(), 422);
+    let res = app.clone().oneshot(patch(json!([{ "op": "move", "from": "/title", "path": "/description" }]))).await.unwrap();
+    assert_eq!(res.status(), 422);
+    let res = app.clone().oneshot(patch(json!([{ "op": "replace", "path": "/status", "value": "someday" }]))).await.unwrap();
+    assert_eq!(res.status(), 422);
+    let res = app.clone().oneshot(Request::patch(&path).header("content-type", "text/plain").body(Body::from("[]")).unwrap()).await.unwrap();
+    assert_eq!(res.status(), 415);
+
+    // remove clears the description
+    let res = app.clone().oneshot(patch(json!([{ "op": "remove", "path": "/description" }]))).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert!(body["description"].is_null());
+}
+
+#[tokio::test]
+async fn acceptance_patch_sets_clears_or_keeps_description() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Draft", "description": "v1" }))).await;
+    let created: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let path = format!("/todos/{}", created["id"].as_str().unwrap());
+    let patch = |body: serde_json::Value| {
+        let (app, path) = (app.clone(), path.clone());
+        async move {
+            let res = request(&app, "PATCH", &path, Some(body)).await;
+            assert_eq!(res.status(), 200);
+            serde_json::from_slice::<serde_json::Value>(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap()
+        }
     };
-    app.clone().oneshot(req).await.unwrap()
+
+    // omitted: unchanged
+    let body = patch(json!({ "title": "Final" })).await;
+    assert_eq!(body["title"], "Final");
+    assert_eq!(body["description"], "v1");
+
+    // set
+    let body = patch(json!({ "description": "v2" })).await;
+    assert_eq!(body["description"], "v2");
+    assert_eq!(body["title"], "Final");
+
+    // null: cleared
+    let body = patch(json!({ "description": null })).await;
+    assert!(body["description"].is_null());
+    let res = request(&app, "GET", &path, None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert!(body["description"].is_null());
+    assert_eq!(body["title"], "Final");
+
+    let res = request(&app, "PATCH", &path, Some(json!({ "status": "someday" }))).await;
+    assert_eq!(res.status(), 400);
+    let res = request(&app, "PATCH", &format!("/todos/{}", uuid::Uuid::new_v4()), Some(json!({ "description": null }))).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn acceptance_due_at_is_stored_and_echoed() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "File taxes", "due_at": "2025-04-15T17:00:00Z" }))).await;
+    assert_eq!(res.status(), 200);
+    let created: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(created["due_at"], "2025-04-15T17:00:00Z");
+    let path = format!("/todos/{}", created["id"].as_str().unwrap());
+
+    let res = request(&app, "PUT", &path, Some(json!({ "due_at": "2025-04-30" }))).await;
+    assert_eq!(res.status(), 200);
+    let res = request(&app, "GET", &path, None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["due_at"], "2025-04-30T00:00:00Z");
+
+    let res = request(&app, "PUT", &path, Some(json!({ "title": "File taxes!" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["due_at"], "2025-04-30T00:00:00Z");
+    assert_eq!(request(&app, "PUT", &path, Some(json!({ "due_at": "soon" }))).await.status(), 400);
+
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Someday" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert!(body["due_at"].is_null());
+}
+
+#[tokio::test]
+async fn acceptance_init_adds_missing_columns_to_old_databases() {
+    let url = isolated_db_url();
+    let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+    sqlx::query("CREATE TABLE todos (id TEXT PRIMARY KEY, title TEXT NOT NULL, description TEXT, status TEXT NOT NULL, created_at TEXT NOT NULL, updated_at TEXT NOT NULL)")
+        .execute(&pool).await.unwrap();
+    sqlx::query("INSERT INTO todos VALUES ('6f1c1f9e-7a51-4d44-9a51-0a6b8e1c2d3f', 'old', NULL, 'pending', '2024-01-01T00:00:00+00:00', '2024-01-01T00:00:00+00:00')")
+        .execute(&pool).await.unwrap();
+
+    let app = common::app_for(spawn_test_repo(&url).await);
+    let res = request(&app, "GET", "/todos/6f1c1f9e-7a51-4d44-9a51-0a6b8e1c2d3f", None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["title"], "old");
+    assert!(body["due_at"].is_null());
+}
+
+#[tokio::test]
+async fn acceptance_schema_lists_every_status() {
+    use api::domain::todo::TodoStatus;
+
+    let app = spawn_test_app().await;
+    let res = request(&app, "GET", "/schema", None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let statuses: Vec<&str> = body["statuses"].as_array().unwrap().iter().map(|s| s.as_str().unwrap()).collect();
+    assert_eq!(statuses, ["pending", "in_progress", "waiting", "done", "cancelled", "archived"]);
+    assert_eq!(statuses.len(), TodoStatus::ALL.len());
+    // Every advertised status is accepted by the API
+    for status in statuses {
+        assert_eq!(request(&app, "GET", &format!("/todos?status={}", status), None).await.status(), 200);
+    }
+    assert_eq!(body["fields"]["title"]["required"], true);
+}
+
+#[tokio::test]
+async fn acceptance_priority_defaults_to_medium_and_is_validated() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Plain", "description": null }))).await;
+    assert_eq!(res.status(), 200);
+    let plain: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(plain["priority"], "medium");
+    assert!(plain["description"].is_null());
+
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Urgent", "priority": "high" }))).await;
+    let urgent: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(urgent["priority"], "high");
+
+    let path = format!("/todos/{}", plain["id"].as_str().unwrap());
+    let res = request(&app, "PUT", &path, Some(json!({ "priority": "low" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["priority"], "low");
+
+    for res in [
+        request(&app, "POST", "/todos", Some(json!({ "title": "Bad", "priority": "urgent" }))).await,
+        request(&app, "PUT", &path, Some(json!({ "priority": "HIGH" }))).await,
+    ] {
+        assert_eq!(res.status(), 400);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        assert_eq!(body["message"], "invalid priority");
+    }
+}
+
+#[tokio::test]
+async fn acceptance_malformed_row_is_an_error_not_a_panic() {
+    use api::domain::repository::TodoRepository;
+
+    let url = isolated_db_url();
+    let repo = spawn_test_repo(&url).await;
+    let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+    sqlx::query("INSERT INTO todos (id, title, status, code, created_at, updated_at) VALUES ('0b7e5a3c-2f41-4c8e-9d6a-1e2f3a4b5c6d', 'imported', 'pending', 'TODO-BAD1', 'last tuesday', '2024-01-01T00:00:00+00:00')")
+        .execute(&pool).await.unwrap();
+
+    let err = repo.list(Default::default(), Default::default()).await.unwrap_err();
+    assert!(err.to_string().contains("created_at"), "{}", err);
+
+    let app = app_with_config(repo, TodoServiceConfig::default());
+    let res = request(&app, "GET", "/todos", None).await;
+    assert_eq!(res.status(), 500);
+    let body = String::from_utf8(to_bytes(res.into_body(), 1024 * 1024).await.unwrap().to_vec()).unwrap();
+    assert!(body.contains("0b7e5a3c-2f41-4c8e-9d6a-1e2f3a4b5c6d") && body.contains("last tuesday"), "{}", body);
+    // the server is still up
+    assert_eq!(request(&app, "GET", "/health", None).await.status(), 200);
+}
+
+#[tokio::test]
+async fn acceptance_list_truncates_descriptions_on_request() {
+    let app = spawn_test_app().await;
+    let long = "é".repeat(300);
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "long notes", "description": long }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let id = body["id"].as_str().unwrap().to_string();
+    request(&app, "POST", "/todos", Some(json!({ "title": "short notes", "description": "brief" }))).await;
+
+    let res = request(&app, "GET", "/todos?truncate_description=20", None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let items = body["items"].as_array().unwrap();
+    let by_title = |title: &str| items.iter().find(|t| t["title"] == title).unwrap().clone();
+    assert_eq!(by_title("long notes")["description"], "é".repeat(20));
+    assert_eq!(by_title("long notes")["description_truncated"], true);
+    assert_eq!(by_title("short notes")["description"], "brief");
+    assert_eq!(by_title("short notes")["description_truncated"], false);
+
+    // no truncation unless asked, and never on get
+    let res = request(&app, "GET", "/todos", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert!(body["items"].as_array().unwrap().iter().all(|t| t.get("description_truncated").is_none()));
+    let res = request(&app, "GET", &format!("/todos/{}?truncate_description=20", id), None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["description"], long);
+}
+
+#[tokio::test]
+async fn acceptance_defer_increments_count() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "later" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["defer_count"], 0);
+    let id = body["id"].as_str().unwrap().to_string();
+
+    for expected in 1..=2 {
+        let res = request(&app, "POST", &format!("/todos/{}/defer", id), None).await;
+        assert_eq!(res.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        assert_eq!(body["defer_count"], expected);
+    }
+    let res = request(&app, "GET", &format!("/todos/{}", id), None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["defer_count"], 2);
+
+    let res = request(&app, "POST", &format!("/todos/{}/defer", uuid::Uuid::new_v4()), None).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn acceptance_delete_due_clears_only_the_due_date() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "ship", "due_at": "2030-01-02T09:00:00Z", "priority": "high" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert!(body["due_at"].is_string());
+    let id = body["id"].as_str().unwrap().to_string();
+
+    let res = request(&app, "DELETE", &format!("/todos/{}/due", id), None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert!(body["due_at"].is_null());
+    assert_eq!(body["priority"], "high");
+    let res = request(&app, "GET", &format!("/todos/{}", id), None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert!(body["due_at"].is_null());
+
+    let res = request(&app, "DELETE", &format!("/todos/{}/due", uuid::Uuid::new_v4()), None).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn acceptance_changes_record_a_diff_per_update() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "draft", "description": "v1" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let id = body["id"].as_str().unwrap().to_string();
+
+    request(&app, "PUT", &format!("/todos/{}", id), Some(json!({ "title": "final", "description": "v2" }))).await;
+    // an update that changes nothing leaves no entry
+    request(&app, "PUT", &format!("/todos/{}", id), Some(json!({ "title": "final" }))).await;
+    request(&app, "PATCH", &format!("/todos/{}", id), Some(json!({ "status": "done" }))).await;
+
+    let res = request(&app, "GET", &format!("/todos/{}/changes", id), None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let items = body["items"].as_array().unwrap();
+    assert_eq!(items.len(), 2);
+    assert_eq!(items[0]["fields"], json!({ "title": { "from": "draft", "to": "final" }, "description": { "from": "v1", "to": "v2" } }));
+    assert_eq!(items[1]["fields"]["status"], json!({ "from": "pending", "to": "done" }));
+    assert!(items[1]["fields"]["completed_at"]["to"].is_string());
+    assert!(items[0]["changed_at"].as_str().unwrap() <= items[1]["changed_at"].as_str().unwrap());
+
+    let res = request(&app, "GET", &format!("/todos/{}/changes", uuid::Uuid::new_v4()), None).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn acceptance_undo_delete_only_within_the_window() {
+    let url = isolated_db_url();
+    let app = app_with_config(spawn_test_repo(&url).await, TodoServiceConfig { undo_delete_window: Some(std::time::Duration::from_secs(3600)), ..Default::default() });
+    let pool = sqlx::SqlitePool::connect(&url).await.unwrap();
+    let mut ids = Vec::new();
+    for title in ["recent", "old"] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+        assert_eq!(request(&app, "DELETE", &format!("/todos/{}", ids.last().unwrap()), None).await.status(), 204);
+    }
+    let two_hours_ago = chrono::Utc::now() - chrono::Duration::hours(2);
+    sqlx::query("UPDATE todos SET deleted_at = ?1 WHERE id = ?2").bind(two_hours_ago.to_rfc3339()).bind(&ids[1]).execute(&pool).await.unwrap();
+
+    let res = request(&app, "POST", &format!("/todos/{}/undo-delete", ids[0]), None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert!(body["deleted_at"].is_null());
+    assert_eq!(request(&app, "GET", &format!("/todos/{}", ids[0]), None).await.status(), 200);
+
+    let res = request(&app, "POST", &format!("/todos/{}/undo-delete", ids[1]), None).await;
+    assert_eq!(res.status(), 410);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["code"], "gone");
+    assert_eq!(request(&app, "GET", &format!("/todos/{}", ids[1]), None).await.status(), 404);
+
+    let res = request(&app, "POST", &format!("/todos/{}/undo-delete", uuid::Uuid::new_v4()), None).await;
+    assert_eq!(res.status(), 404);
+}
+
+#[tokio::test]
+async fn acceptance_restore_include_deleted_and_purge() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "oops" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let id = body["id"].as_str().unwrap().to_string();
+    request(&app, "POST", "/todos", Some(json!({ "title": "keeper" }))).await;
+    let list = |uri: &'static str| {
+        let app = app.clone();
+        async move {
+            let res = request(&app, "GET", uri, None).await;
+            let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+            body["items"].as_array().unwrap().clone()
+        }
+    };
+
+    assert_eq!(request(&app, "DELETE", &format!("/todos/{}", id), None).await.status(), 204);
+    assert_eq!(list("/todos").await.len(), 1);
+    let trashed = list("/todos?include_deleted=true").await;
+    assert_eq!(trashed.len(), 2);
+    assert!(trashed.iter().any(|t| t["id"] == id.as_str() && t["deleted_at"].is_string()));
+
+    let res = request(&app, "POST", &format!("/todos/{}/restore", id), None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert!(body["deleted_at"].is_null());
+    assert_eq!(list("/todos").await.len(), 2);
+
+    // purge removes the row itself, so there is no tombstone left to restore
+    assert_eq!(request(&app, "DELETE", &format!("/todos/{}?purge=true", id), None).await.status(), 204);
+    assert_eq!(list("/todos?include_deleted=true").await.len(), 1);
+    assert_eq!(request(&app, "GET", &format!("/todos/{}?include_deleted=true", id), None).await.status(), 404);
+    assert_eq!(request(&app, "POST", &format!("/todos/{}/restore", id), None).await.status(), 404);
+    assert_eq!(request(&app, "DELETE", &format!("/todos/{}?purge=true", id), None).await.status(), 404);
+}
+
+#[tokio::test]
+async fn acceptance_errors_are_json_with_a_code() {
+    let app = spawn_test_app().await;
+
+    let res = request(&app, "GET", &format!("/todos/{}", uuid::Uuid::new_v4()), None).await;
+    assert_eq!(res.status(), 404);
+    assert_eq!(res.headers()["content-type"], "application/json");
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body, json!({ "message": "Not found", "code": "not_found" }));
+
+    let res = request(&app, "GET", "/todos/not-a-uuid", None).await;
+    assert_eq!(res.status(), 400);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body, json!({ "message": "invalid id", "code": "bad_request" }));
+}
+
+#[tokio::test]
+async fn acceptance_tags_are_normalized_and_filterable() {
+    let (app, pool) = spawn_test_app_with_pool().await;
+
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Report", "tags": [" work", "Work", "", "Q3 "] }))).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["tags"], json!(["Q3", "work"]));
+    let id = body["id"].as_str().unwrap().to_string();
+    request(&app, "POST", "/todos", Some(json!({ "title": "Groceries", "tags": ["home"] }))).await;
+    request(&app, "POST", "/todos", Some(json!({ "title": "Untagged" }))).await;
+
+    let res = request(&app, "GET", "/todos?tag=WORK", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["items"][0]["id"], id);
+
+    // Replacing the tags rewrites the rows; omitting them leaves them alone
+    let res = request(&app, "PUT", &format!("/todos/{}", id), Some(json!({ "tags": ["home", "HOME"] }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["tags"], json!(["home"]));
+    let res = request(&app, "PUT", &format!("/todos/{}", id), Some(json!({ "title": "Report v2" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["tags"], json!(["home"]));
+    let res = request(&app, "GET", "/todos?tag=home", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["total"], 2);
+    let res = request(&app, "GET", "/todos?tag=work", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["total"], 0);
+
+    // Purging removes the tag rows with the todo
+    request(&app, "DELETE", &format!("/todos/{}?purge=true", id), None).await;
+    let (rows,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM todo_tags WHERE todo_id = ?1").bind(&id).fetch_one(&pool).await.unwrap();
+    assert_eq!(rows, 0);
+}
+
+#[tokio::test]
+async fn acceptance_status_label_accompanies_status() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Labelled" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!((body["status"].as_str(), body["status_label"].as_str()), (Some("pending"), Some("Pending")));
+    let path = format!("/todos/{}", body["id"].as_str().unwrap());
+
+    for (status, label) in [("done", "Done"), ("archived", "Archived"), ("pending", "Pending")] {
+        let res = request(&app, "PUT", &path, Some(json!({ "status": status }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        assert_eq!((body["status"].as_str(), body["status_label"].as_str()), (Some(status), Some(label)));
+    }
+    // Read-only: a label in the body is ignored
+    let res = request(&app, "PUT", &path, Some(json!({ "status_label": "Done" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["status_label"], "Pending");
+}
+
+#[tokio::test]
+async fn acceptance_list_sort_and_order() {
+    let app = spawn_test_app().await;
+    let mut ids = Vec::new();
+    for title in ["banana", "apple", "cherry"] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    // Touch the oldest so updated_at order differs from created_at order
+    request(&app, "PUT", &format!("/todos/{}", ids[0]), Some(json!({ "description": "ripe" }))).await;
+
+    let titles = |body: serde_json::Value| body["items"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+    for (query, expected) in [
+        ("", ["cherry", "apple", "banana"]),
+        ("?sort=created_at&order=asc", ["banana", "apple", "cherry"]),
+        ("?sort=title&order=asc", ["apple", "banana", "cherry"]),
+        ("?sort=title", ["cherry", "banana", "apple"]),
+        ("?sort=updated_at", ["banana", "cherry", "apple"]),
+    ] {
+        let res = request(&app, "GET", &format!("/todos{}", query), None).await;
+        assert_eq!(res.status(), 200, "{}", query);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        assert_eq!(titles(body), expected, "{}", query);
+    }
+
+    for query in ["?sort=title%3BDROP%20TABLE%20todos", "?sort=estimate", "?order=up"] {
+        let res = request(&app, "GET", &format!("/todos{}", query), None).await;
+        assert_eq!(res.status(), 400, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn acceptance_staleness_sort_surfaces_untouched_pending_todos() {
+    let app = spawn_test_app().await;
+    let mut ids = Vec::new();
+    for title in ["old", "older", "fresh", "finished"] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+    // "finished" is done early and "old" gets touched last, so "older" has gone longest untouched
+    request(&app, "PUT", &format!("/todos/{}", ids[3]), Some(json!({ "status": "done" }))).await;
+    tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    request(&app, "PUT", &format!("/todos/{}", ids[0]), Some(json!({ "description": "poked" }))).await;
+
+    let titles = |body: serde_json::Value| body["items"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+    for (query, expected) in [
+        ("?sort=staleness", vec!["older", "fresh", "old", "finished"]),
+        ("?sort=staleness&status=pending", vec!["older", "fresh", "old"]),
+        ("?sort=staleness&order=asc", vec!["old", "fresh", "older", "finished"]),
+    ] {
+        let res = request(&app, "GET", &format!("/todos{}", query), None).await;
+        assert_eq!(res.status(), 200, "{}", query);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        assert_eq!(titles(body), expected, "{}", query);
+    }
+}
+
+#[tokio::test]
+async fn acceptance_priority_sort_puts_high_priority_and_soonest_due_first() {
+    let app = spawn_test_app().await;
+    for body in [
+        json!({ "title": "low", "priority": "low", "due_at": "2030-01-01T00:00:00Z" }),
+        json!({ "title": "high undated", "priority": "high" }),
+        json!({ "title": "medium", "priority": "medium" }),
+        json!({ "title": "high later", "priority": "high", "due_at": "2031-01-01T00:00:00Z" }),
+        json!({ "title": "high soon", "priority": "high", "due_at": "2030-06-01T00:00:00Z" }),
+    ] {
+        assert_eq!(request(&app, "POST", "/todos", Some(body)).await.status(), 200);
+    }
+
+    let expected = vec!["high soon", "high later", "high undated", "medium", "low"];
+    let titles = |body: serde_json::Value| body["items"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+    let res = request(&app, "GET", "/todos?sort=priority", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(titles(body), expected);
+    let res = request(&app, "GET", "/todos?sort=priority&order=asc", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(titles(body), expected.into_iter().rev().collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn acceptance_bulk_create_is_all_or_nothing() {
+    let (app, pool) = spawn_test_app_with_pool().await;
+
+    let res = request(&app, "POST", "/todos/bulk", Some(json!([{ "title": "one" }, { "title": "two", "priority": "high" }, { "title": "three", "tags": ["x"] }]))).await;
+    assert_eq!(res.status(), 200);
+    let created: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let titles: Vec<_> = created.as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["one", "two", "three"]);
+    let res = request(&app, "GET", "/todos", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["total"], 3);
+    for todo in created.as_array().unwrap() {
+        assert!(body["items"].as_array().unwrap().iter().any(|t| t["id"] == todo["id"]));
+    }
+
+    // A failing insert in the middle rolls back the ones before it
+    sqlx::query("CREATE TRIGGER refuse_boom BEFORE INSERT ON todos WHEN NEW.title = 'boom' BEGIN SELECT RAISE(ABORT, 'boom refused'); END")
+        .execute(&pool).await.unwrap();
+    let res = request(&app, "POST", "/todos/bulk", Some(json!([{ "title": "four" }, { "title": "boom" }, { "title": "five" }]))).await;
+    assert_eq!(res.status(), 500);
+    let res = request(&app, "GET", "/todos", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["total"], 3);
+
+    // Invalid items are refused before anything is written
+    let res = request(&app, "POST", "/todos/bulk", Some(json!([{ "title": "six" }, { "title": "seven", "priority": "urgent" }]))).await;
+    assert_eq!(res.status(), 400);
+
+    let too_many: Vec<_> = (0..1001).map(|i| json!({ "title": format!("t{}", i) })).collect();
+    let res = request(&app, "POST", "/todos/bulk", Some(json!(too_many))).await;
+    assert_eq!(res.status(), 422);
+    let res = request(&app, "GET", "/todos", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["total"], 3);
+}
+
+#[tokio::test]
+async fn acceptance_deadline_header_bounds_the_request() {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    let app = spawn_test_app().await;
+    let with_deadline = |deadline: String| Request::post("/todos").header("content-type", "application/json").header("x-deadline", deadline).body(Body::from(json!({ "title": "in time" }).to_string())).unwrap();
+
+    let past = chrono::Utc::now().timestamp_millis() - 1_000;
+    let res = app.clone().oneshot(with_deadline(past.to_string())).await.unwrap();
+    assert_eq!(res.status(), 504);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["code"], "gateway_timeout");
+    // the handler never ran, so nothing was created
+    let res = request(&app, "GET", "/todos", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["total"], 0);
+
+    let future = chrono::Utc::now().timestamp_millis() + 60_000;
+    assert_eq!(app.clone().oneshot(with_deadline(future.to_string())).await.unwrap().status(), 200);
+    assert_eq!(app.clone().oneshot(with_deadline("soon".into())).await.unwrap().status(), 400);
+}
+
+#[tokio::test]
+async fn acceptance_cors_preflight_allows_configured_origins() {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    let app = routing::with_cors(spawn_test_app().await, routing::CorsOrigins::List(vec!["https://app.example.com".parse().unwrap()]));
+    let preflight = |origin: &str| Request::options(format!("/todos/{}", uuid::Uuid::new_v4()))
+        .header("origin", origin)
+        .header("access-control-request-method", "DELETE")
+        .body(Body::empty())
+        .unwrap();
+
+    let res = app.clone().oneshot(preflight("https://app.example.com")).await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["access-control-allow-origin"], "https://app.example.com");
+    let methods = res.headers()["access-control-allow-methods"].to_str().unwrap().to_string();
+    assert!(methods.contains("PUT") && methods.contains("DELETE"), "{}", methods);
+
+    let res = app.clone().oneshot(preflight("https://evil.example.com")).await.unwrap();
+    assert!(!res.headers().contains_key("access-control-allow-origin"));
+
+    let req = Request::get("/todos").header("origin", "https://app.example.com").body(Body::empty()).unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["access-control-allow-origin"], "https://app.example.com");
+}
+
+#[tokio::test]
+async fn acceptance_embedded_api_shares_the_service_with_its_host() {
+    use api::{application::todo_service::TodoService, domain::todo::{CreateTodo, ListOptions, TodoFilter}};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    // What `tui --serve` does: one service, used directly and served over HTTP on a background task
+    let repo = spawn_test_repo(&isolated_db_url()).await;
+    let service = TodoServiceImpl::with_config(repo.clone(), TodoServiceConfig::default());
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let router = routing::api(repo, service.clone(), None).unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await });
+
+    let http = |method: &str, path: &str, body: &str| {
+        let raw = format!("{} {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", method, path, addr, body.len(), body);
+        async move {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            stream.write_all(raw.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        }
+    };
+
+    let local = service.create(CreateTodo { title: "from the ui".into(), ..Default::default() }).await.unwrap();
+    let listed = http("GET", "/todos", "").await;
+    assert!(listed.starts_with("HTTP/1.1 200"), "{}", listed);
+    assert!(listed.contains(&local.id.0.to_string()), "{}", listed);
+
+    let created = http("POST", "/todos", r#"{"title":"from the api"}"#).await;
+    assert!(created.starts_with("HTTP/1.1 200"), "{}", created);
+    let titles: Vec<String> = service.list(TodoFilter::default(), ListOptions::default()).await.unwrap().into_iter().map(|t| t.title).collect();
+    assert!(titles.contains(&"from the api".to_string()), "{:?}", titles);
+}
+
+#[tokio::test]
+async fn acceptance_request_id_is_echoed_or_generated() {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    let app = spawn_test_app().await;
+    let req = Request::get("/todos").header("x-request-id", "trace-abc-123").body(Body::empty()).unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert_eq!(res.headers()["x-request-id"], "trace-abc-123");
+
+    // Missing or unusable ids are replaced, errors included
+    let res = request(&app, "GET", "/todos/not-a-uuid", None).await;
+    assert_eq!(res.status(), 400);
+    let generated = res.headers()["x-request-id"].to_str().unwrap();
+    assert!(uuid::Uuid::parse_str(generated).is_ok(), "{}", generated);
+    let req = Request::get("/todos").header("x-request-id", "x".repeat(200)).body(Body::empty()).unwrap();
+    let res = app.clone().oneshot(req).await.unwrap();
+    assert!(uuid::Uuid::parse_str(res.headers()["x-request-id"].to_str().unwrap()).is_ok());
+}
+
+#[tokio::test]
+async fn acceptance_openapi_spec_matches_the_responses() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "GET", "/openapi.json", None).await;
+    assert_eq!(res.status(), 200);
+    let spec: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+
+    for (path, methods) in [("/todos", &["get", "post"][..]), ("/todos/{id}", &["get", "put", "delete"][..])] {
+        for method in methods { assert!(spec["paths"][path][method].is_object(), "{} {}", method, path); }
+    }
+    let schemas = &spec["components"]["schemas"];
+    assert_eq!(schemas["TodoStatus"]["enum"], json!(["pending", "in_progress", "waiting", "done", "cancelled", "archived"]));
+    assert!(spec["paths"]["/todos/{id}"]["get"]["responses"]["404"].is_object());
+
+    // Every reference resolves, and the Todo schema lists exactly the fields a todo is returned with
+    let text = spec.to_string();
+    for reference in text.split("\"#/components/schemas/").skip(1).map(|s| &s[..s.find('"').unwrap()]) {
+        assert!(schemas[reference].is_object(), "dangling $ref {}", reference);
+    }
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "documented" }))).await;
+    let todo: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let mut returned: Vec<_> = todo.as_object().unwrap().keys().cloned().collect();
+    let mut documented: Vec<_> = schemas["Todo"]["properties"].as_object().unwrap().keys().cloned().collect();
+    returned.sort();
+    documented.sort();
+    assert_eq!(documented, returned);
+
+    let res = request(&app, "GET", "/docs", None).await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn acceptance_stale_lists_only_todos_untouched_since_the_cutoff() {
+    let (app, pool) = spawn_test_app_with_pool().await;
+    let mut ids = Vec::new();
+    for title in ["abandoned", "forgotten", "finished long ago", "active"] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    request(&app, "PUT", &format!("/todos/{}", ids[2]), Some(json!({ "status": "done" }))).await;
+    let now = chrono::Utc::now();
+    for (id, days_ago) in [(&ids[0], 40), (&ids[1], 90), (&ids[2], 60)] {
+        sqlx::query("UPDATE todos SET updated_at = ?1 WHERE id = ?2").bind((now - chrono::Duration::days(days_ago)).to_rfc3339()).bind(id).execute(&pool).await.unwrap();
+    }
+
+    let titles = |body: serde_json::Value| body["items"].as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap().to_string()).collect::<Vec<_>>();
+    let cutoff = (now - chrono::Duration::days(30)).format("%Y-%m-%d");
+    for (query, expected) in [
+        (format!("?before={}", cutoff), vec!["forgotten", "finished long ago", "abandoned"]),
+        (format!("?before={}&status=pending", cutoff), vec!["forgotten", "abandoned"]),
+        (format!("?before={}", (now - chrono::Duration::days(365)).format("%Y-%m-%d")), vec![]),
+    ] {
+        let res = request(&app, "GET", &format!("/todos/stale{}", query), None).await;
+        assert_eq!(res.status(), 200, "{}", query);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        assert_eq!(titles(body), expected, "{}", query);
+    }
+    assert_eq!(request(&app, "GET", "/todos/stale", None).await.status(), 400);
+    assert_eq!(request(&app, "GET", "/todos/stale?before=last-tuesday", None).await.status(), 400);
+}
+
+#[tokio::test]
+async fn acceptance_export_pages_through_every_todo_by_cursor() {
+    let app = spawn_test_app().await;
+    let mut ids = Vec::new();
+    for i in 0..8 {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": format!("Todo {}", i) }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    request(&app, "PUT", &format!("/todos/{}", ids[1]), Some(json!({ "status": "archived" }))).await;
+    request(&app, "DELETE", &format!("/todos/{}", ids[2]), None).await;
+
+    let mut exported = Vec::new();
+    let mut path = "/todos/export?limit=3".to_string();
+    loop {
+        let res = request(&app, "GET", &path, None).await;
+        assert_eq!(res.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        let items = body["items"].as_array().unwrap();
+        assert!(items.len() <= 3);
+        exported.extend(items.iter().map(|t| t["id"].as_str().unwrap().to_string()));
+        match body["next_cursor"].as_str() {
+            Some(cursor) => {
+                assert_eq!(Some(cursor), exported.last().map(String::as_str));
+                path = format!("/todos/export?limit=3&after={}", cursor);
+            }
+            None => break,
+        }
+    }
+
+    // Every live todo exactly once, archived included, in id order
+    let mut expected: Vec<String> = ids.iter().filter(|id| **id != ids[2]).cloned().collect();
+    expected.sort();
+    assert_eq!(exported, expected);
+
+    assert_eq!(request(&app, "GET", "/todos/export?after=nope", None).await.status(), 400);
+    assert_eq!(request(&app, "GET", "/todos/export?limit=0", None).await.status(), 400);
+}
+
+#[tokio::test]
+async fn acceptance_stats_counts_todos_by_status() {
+    let app = spawn_test_app().await;
+    let mut ids = Vec::new();
+    for title in ["a", "b", "c", "d", "e"] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    request(&app, "PUT", &format!("/todos/{}", ids[0]), Some(json!({ "status": "done" }))).await;
+    request(&app, "PUT", &format!("/todos/{}", ids[1]), Some(json!({ "status": "done" }))).await;
+    request(&app, "PUT", &format!("/todos/{}", ids[2]), Some(json!({ "status": "archived" }))).await;
+    request(&app, "DELETE", &format!("/todos/{}", ids[3]), None).await;
+
+    let res = request(&app, "GET", "/todos/stats", None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body, json!({ "pending": 1, "in_progress": 0, "waiting": 0, "done": 2, "cancelled": 0, "total": 3 }));
+}
+
+#[tokio::test]
+async fn acceptance_braced_and_uppercase_ids_match_the_todo() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Find me" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let id = body["id"].as_str().unwrap().to_string();
+
+    for variant in [id.to_uppercase(), format!("%7B{}%7D", id), format!("%7B{}%7D", id.to_uppercase()), id.replace('-', ""), format!("urn:uuid:{}", id)] {
+        let res = request(&app, "GET", &format!("/todos/{}", variant), None).await;
+        assert_eq!(res.status(), 200, "{}", variant);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        assert_eq!(body["id"], id, "{}", variant);
+    }
+
+    // Writes go through the same normalization
+    let res = request(&app, "PUT", &format!("/todos/%7B{}%7D", id.to_uppercase()), Some(json!({ "status": "done" }))).await;
+    assert_eq!(res.status(), 200);
+    let res = request(&app, "GET", &format!("/todos/{}", id), None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["status"], "done");
+    assert_eq!(request(&app, "GET", &format!("/todos/{{{}", id), None).await.status(), 400);
+}
+
+#[tokio::test]
+async fn acceptance_stale_if_match_update_is_rejected() {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Shared" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let path = format!("/todos/{}", body["id"].as_str().unwrap());
+
+    let res = request(&app, "GET", &path, None).await;
+    let read_etag = res.headers()["etag"].to_str().unwrap().to_string();
+    let put = |etag: &str, title: &str| Request::put(path.as_str())
+        .header("content-type", "application/json")
+        .header("if-match", etag)
+        .body(Body::from(json!({ "title": title }).to_string()))
+        .unwrap();
+
+    // The first client writes with the ETag it read and gets the new one back
+    let res = app.clone().oneshot(put(&read_etag, "First")).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let new_etag = res.headers()["etag"].to_str().unwrap().to_string();
+    assert_ne!(new_etag, read_etag);
+
+    // The second client still holds the old ETag, so its write is refused
+    let res = app.clone().oneshot(put(&read_etag, "Second")).await.unwrap();
+    assert_eq!(res.status(), 412);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["code"], "precondition_failed");
+    let res = request(&app, "GET", &path, None).await;
+    assert_eq!(res.headers()["etag"].to_str().unwrap(), new_etag);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["title"], "First");
+
+    assert_eq!(app.clone().oneshot(put("*", "Any version")).await.unwrap().status(), 200);
+    assert_eq!(app.clone().oneshot(put("not-an-etag", "Garbage")).await.unwrap().status(), 412);
+    // Without If-Match the update is unconditional, as before
+    assert_eq!(request(&app, "PUT", &path, Some(json!({ "title": "Last" }))).await.status(), 200);
+}
+
+#[tokio::test]
+async fn acceptance_stale_if_match_patch_is_rejected() {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Shared" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let path = format!("/todos/{}", body["id"].as_str().unwrap());
+    let read_etag = request(&app, "GET", &path, None).await.headers()["etag"].to_str().unwrap().to_string();
+    let patch = |content_type: &str, etag: &str, body: serde_json::Value| Request::patch(path.as_str())
+        .header("content-type", content_type)
+        .header("if-match", etag)
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    // A merge patch with the current ETag writes and hands back the next one
+    let res = app.clone().oneshot(patch("application/json", &read_etag, json!({ "title": "First" }))).await.unwrap();
+    assert_eq!(res.status(), 200);
+    let new_etag = res.headers()["etag"].to_str().unwrap().to_string();
+    assert_ne!(new_etag, read_etag);
+
+    // The old ETag is refused by both kinds of PATCH, and nothing is written
+    let res = app.clone().oneshot(patch("application/json", &read_etag, json!({ "title": "Second" }))).await.unwrap();
+    assert_eq!(res.status(), 412);
+    let ops = json!([{ "op": "replace", "path": "/title", "value": "Third" }]);
+    let res = app.clone().oneshot(patch("application/json-patch+json", &read_etag, ops.clone())).await.unwrap();
+    assert_eq!(res.status(), 412);
+    let res = request(&app, "GET", &path, None).await;
+    assert_eq!(res.headers()["etag"].to_str().unwrap(), new_etag);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["title"], "First");
+
+    let res = app.clone().oneshot(patch("application/json-patch+json", &new_etag, ops)).await.unwrap();
+    assert_eq!(res.status(), 200);
+    assert_ne!(res.headers()["etag"].to_str().unwrap(), new_etag);
+}
+
+#[tokio::test]
+async fn acceptance_export_delete_import_round_trips() {
+    let app = spawn_test_app().await;
+    let mut ids = Vec::new();
+    for body in [
+        json!({ "title": "Plain" }),
+        json!({ "title": "Tagged", "description": "with, commas", "tags": ["home", "Work"], "priority": "high", "due_at": "2030-01-02T03:04:05Z" }),
+        json!({ "title": "Estimated", "estimate_minutes": 45, "recurrence": "weekly" }),
+    ] {
+        let res = request(&app, "POST", "/todos", Some(body)).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    request(&app, "PUT", &format!("/todos/{}", ids[0]), Some(json!({ "status": "done" }))).await;
+    request(&app, "PUT", &format!("/todos/{}", ids[1]), Some(json!({ "status": "archived" }))).await;
+
+    let export = |app: Router| async move {
+        let res = request(&app, "GET", "/todos/export", None).await;
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.headers()["content-type"], "application/json");
+        serde_json::from_slice::<serde_json::Value>(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap()
+    };
+    let exported = export(app.clone()).await;
+    assert_eq!(exported.as_array().unwrap().len(), 3);
+    for id in &ids { assert_eq!(request(&app, "DELETE", &format!("/todos/{}", id), None).await.status(), 204); }
+    assert_eq!(export(app.clone()).await, json!([]));
+
+    let res = request(&app, "POST", "/todos/import", Some(exported.clone())).await;
+    assert_eq!(res.status(), 200);
+    let report: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(report, json!({ "inserted": 0, "skipped": 0, "overwritten": 3 }));
+    assert_eq!(export(app.clone()).await, exported);
+
+    // Into an empty database the same export inserts everything; a second import can skip it all
+    let fresh = spawn_test_app().await;
+    let res = request(&fresh, "POST", "/todos/import", Some(exported.clone())).await;
+    let report: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(report["inserted"], 3);
+    let res = request(&fresh, "POST", "/todos/import?on_conflict=skip", Some(exported.clone())).await;
+    let report: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(report, json!({ "inserted": 0, "skipped": 3, "overwritten": 0 }));
+    assert_eq!(export(fresh.clone()).await, exported);
+    let res = request(&fresh, "POST", "/todos/import?on_conflict=overwrite", Some(exported.clone())).await;
+    let report: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(report, json!({ "inserted": 0, "skipped": 0, "overwritten": 3 }));
+    assert_eq!(request(&fresh, "POST", "/todos/import?on_conflict=fail", Some(exported.clone())).await.status(), 409);
+    assert_eq!(request(&fresh, "POST", "/todos/import?on_conflict=merge", Some(exported.clone())).await.status(), 400);
+
+    // Imported todos are checked like created ones; one bad item fails the whole import
+    let mut blank = exported.clone();
+    blank[2]["title"] = json!("   ");
+    let res = request(&spawn_test_app().await, "POST", "/todos/import", Some(blank)).await;
+    assert_eq!(res.status(), 422);
+    let mut long = exported;
+    long[0]["description"] = json!("d".repeat(4097));
+    let empty = spawn_test_app().await;
+    assert_eq!(request(&empty, "POST", "/todos/import", Some(long)).await.status(), 422);
+    assert_eq!(export(empty).await, json!([]));
+}
+
+#[tokio::test]
+async fn acceptance_waiting_todos_show_the_contact_and_filter_by_status() {
+    let app = spawn_test_app().await;
+    request(&app, "POST", "/todos", Some(json!({ "title": "Book flights" }))).await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Sign contract" }))).await;
+    let created: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(created["waiting_on"], serde_json::Value::Null);
+    let id = created["id"].as_str().unwrap();
+
+    let res = request(&app, "PUT", &format!("/todos/{}", id), Some(json!({ "status": "waiting", "waiting_on": "Sam (legal)" }))).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!((&body["status"], &body["status_label"], &body["waiting_on"]), (&json!("waiting"), &json!("Waiting"), &json!("Sam (legal)")));
+
+    let res = request(&app, "GET", "/todos?status=waiting", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let items = body["items"].as_array().unwrap();
+    assert_eq!(items.iter().map(|t| t["id"].as_str().unwrap()).collect::<Vec<_>>(), [id]);
+    let res = request(&app, "GET", "/todos", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["items"].as_array().unwrap().len(), 2);
+
+    let res = request(&app, "GET", "/todos/stats", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body, json!({ "pending": 1, "in_progress": 0, "waiting": 1, "done": 0, "cancelled": 0, "total": 2 }));
+
+    // Unblocked: a JSON null clears the contact
+    let res = request(&app, "PATCH", &format!("/todos/{}", id), Some(json!({ "status": "pending", "waiting_on": null }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!((&body["status"], &body["waiting_on"]), (&json!("pending"), &serde_json::Value::Null));
+}
+
+#[tokio::test]
+async fn acceptance_csv_export_is_a_download_with_a_header_row() {
+    let app = spawn_test_app().await;
+    request(&app, "POST", "/todos", Some(json!({ "title": "Shop", "description": "milk, eggs" }))).await;
+
+    let res = request(&app, "GET", "/todos/export.csv", None).await;
+    assert_eq!(res.status(), 200);
+    assert_eq!(res.headers()["content-type"], "text/csv");
+    assert_eq!(res.headers()["content-disposition"], "attachment; filename=\"todos.csv\"");
+    let body = String::from_utf8(to_bytes(res.into_body(), 1024 * 1024).await.unwrap().to_vec()).unwrap();
+    let lines: Vec<&str> = body.lines().collect();
+    assert_eq!(lines[0], "id,title,description,status,created_at,updated_at");
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].contains(",Shop,\"milk, eggs\",pending,"), "{}", lines[1]);
+}
+
+#[tokio::test]
+async fn acceptance_csv_and_json_exports_list_the_same_todos() {
+    let app = spawn_test_app().await;
+    for title in ["First", "Second", "Third"] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        if title == "Second" { request(&app, "PUT", &format!("/todos/{}", body["id"].as_str().unwrap()), Some(json!({ "status": "archived" }))).await; }
+    }
+
+    for query in ["", "?status=archived", "?status=pending"] {
+        let res = request(&app, "GET", &format!("/todos/export{}", query), None).await;
+        let exported: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        let json_ids: Vec<&str> = exported.as_array().unwrap().iter().map(|t| t["id"].as_str().unwrap()).collect();
+        let res = request(&app, "GET", &format!("/todos/export.csv{}", query), None).await;
+        let body = String::from_utf8(to_bytes(res.into_body(), 1024 * 1024).await.unwrap().to_vec()).unwrap();
+        let csv_ids: Vec<&str> = body.lines().skip(1).map(|l| l.split(',').next().unwrap()).collect();
+        assert_eq!(csv_ids, json_ids, "{}", query);
+    }
+    // Without a status both include the archived todo
+    let res = request(&app, "GET", "/todos/export.csv", None).await;
+    let body = String::from_utf8(to_bytes(res.into_body(), 1024 * 1024).await.unwrap().to_vec()).unwrap();
+    assert_eq!(body.lines().count(), 4);
+    assert!(body.contains(",Second,,archived,"), "{}", body);
+}
+
+#[tokio::test]
+async fn acceptance_exports_only_done_todos_when_filtered_by_status() {
+    let app = spawn_test_app().await;
+    let mut ids = Vec::new();
+    for (title, tags) in [("Shipped", json!(["work"])), ("Still open", json!(["work"])), ("Also done", json!([]))] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title, "tags": tags }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    for id in [&ids[0], &ids[2]] {
+        request(&app, "PUT", &format!("/todos/{}", id), Some(json!({ "status": "done" }))).await;
+    }
+    let titles = |items: &serde_json::Value| {
+        let mut titles: Vec<String> = items.as_array().unwrap().iter().map(|t| t["title"].as_str().unwrap().to_string()).collect();
+        titles.sort();
+        titles
+    };
+
+    let res = request(&app, "GET", "/todos/export?status=done", None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(titles(&body), ["Also done", "Shipped"]);
+
+    let res = request(&app, "GET", "/todos/export?status=done&tag=work&limit=10", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(titles(&body["items"]), ["Shipped"]);
+    assert_eq!(body["next_cursor"], serde_json::Value::Null);
+
+    let res = request(&app, "GET", "/todos/export.csv?status=done", None).await;
+    let body = String::from_utf8(to_bytes(res.into_body(), 1024 * 1024).await.unwrap().to_vec()).unwrap();
+    assert_eq!(body.lines().count(), 3);
+    assert!(!body.contains("Still open"));
+
+    assert_eq!(request(&app, "GET", "/todos/export?status=finished", None).await.status(), 400);
+}
+
+#[tokio::test]
+async fn acceptance_unpaged_list_is_capped_at_the_soft_limit() {
+    let config = TodoServiceConfig { list_soft_limit: Some(3), ..Default::default() };
+    let app = app_with_config(spawn_test_repo(&isolated_db_url()).await, config);
+    for i in 0..5 { request(&app, "POST", "/todos", Some(json!({ "title": format!("t{}", i) }))).await; }
+
+    let res = request(&app, "GET", "/todos", None).await;
+    assert_eq!(res.status(), 200);
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["items"].as_array().unwrap().len(), 3);
+    assert_eq!((&body["total"], &body["truncated"], &body["next"]), (&json!(5), &json!(true), &json!(3)));
+
+    // Following `next` gets the rest
+    let res = request(&app, "GET", "/todos?offset=3", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["items"].as_array().unwrap().len(), 2);
+    assert!(body.get("truncated").is_none());
+
+    // Explicit paging isn't held to the soft limit
+    let res = request(&app, "GET", "/todos?limit=10", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["items"].as_array().unwrap().len(), 5);
+}
+
+#[tokio::test]
+async fn acceptance_in_progress_and_cancelled_round_trip_alongside_legacy_rows() {
+    let (app, pool) = spawn_test_app_with_pool().await;
+    let mut ids = Vec::new();
+    for title in ["Legacy pending", "Legacy done", "Started", "Dropped"] {
+        let res = request(&app, "POST", "/todos", Some(json!({ "title": title }))).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        ids.push(body["id"].as_str().unwrap().to_string());
+    }
+    // Rows as an older version wrote them
+    sqlx::query("UPDATE todos SET status = 'done' WHERE id = ?1").bind(&ids[1]).execute(&pool).await.unwrap();
+
+    for (id, status, label) in [(&ids[2], "in_progress", "In progress"), (&ids[3], "cancelled", "Cancelled")] {
+        let res = request(&app, "PUT", &format!("/todos/{}", id), Some(json!({ "status": status }))).await;
+        assert_eq!(res.status(), 200);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        assert_eq!((&body["status"], &body["status_label"], &body["completed_at"]), (&json!(status), &json!(label), &serde_json::Value::Null));
+        let stored: String = sqlx::query_scalar("SELECT status FROM todos WHERE id = ?1").bind(id).fetch_one(&pool).await.unwrap();
+        assert_eq!(stored, status);
+    }
+
+    for (status, id) in [("pending", &ids[0]), ("done", &ids[1]), ("in_progress", &ids[2]), ("cancelled", &ids[3])] {
+        let res = request(&app, "GET", &format!("/todos?status={}", status), None).await;
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        assert_eq!(body["items"].as_array().unwrap().iter().map(|t| t["id"].as_str().unwrap()).collect::<Vec<_>>(), [id.as_str()], "{}", status);
+    }
+    assert_eq!(request(&app, "GET", "/todos?status=inprogress", None).await.status(), 400);
+
+    let res = request(&app, "GET", "/todos/stats", None).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body, json!({ "pending": 1, "in_progress": 1, "waiting": 0, "done": 1, "cancelled": 1, "total": 4 }));
+}
+
+#[tokio::test]
+async fn acceptance_identical_requests_return_identical_bytes() {
+    let app = spawn_test_app().await;
+    let res = request(&app, "POST", "/todos", Some(json!({ "title": "Snapshot", "description": "d", "tags": ["b", "a"], "priority": "high" }))).await;
+    let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    let id = body["id"].as_str().unwrap().to_string();
+    request(&app, "PUT", &format!("/todos/{}", id), Some(json!({ "title": "Snapshot 2", "status": "done", "tags": ["c"] }))).await;
+    request(&app, "POST", "/todos", Some(json!({ "title": "Other" }))).await;
+
+    for path in ["/todos".to_string(), format!("/todos/{}", id), format!("/todos/{}/changes", id), "/todos/stats".into(), "/todos/export".into(), "/schema".into()] {
+        let first = to_bytes(request(&app, "GET", &path, None).await.into_body(), 1024 * 1024).await.unwrap();
+        let second = to_bytes(request(&app, "GET", &path, None).await.into_body(), 1024 * 1024).await.unwrap();
+        assert_eq!(first, second, "{}", path);
+    }
+    // Keys come out sorted, so a golden file diffs cleanly when a field is added
+    let text = String::from_utf8(to_bytes(request(&app, "GET", &format!("/todos/{}", id), None).await.into_body(), 1024 * 1024).await.unwrap().to_vec()).unwrap();
+    let body: serde_json::Value = serde_json::from_str(&text).unwrap();
+    let keys: Vec<&String> = body.as_object().unwrap().keys().collect();
+    let positions: Vec<usize> = keys.iter().map(|k| text.find(&format!("\"{}\":", k)).unwrap()).collect();
+    assert!(positions.is_sorted(), "{}", text);
+}
+
+#[tokio::test]
+async fn acceptance_blank_or_overlong_titles_are_422() {
+    let app = spawn_test_app().await;
+    for (body, field) in [(json!({ "title": "  " }), "title"), (json!({ "title": "x".repeat(257) }), "title"), (json!({ "title": "ok", "description": "d".repeat(4097) }), "description")] {
+        let res = request(&app, "POST", "/todos", Some(body)).await;
+        assert_eq!(res.status(), 422);
+        let body: serde_json::Value = serde_json::from_slice(&to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+        assert_eq!(body["code"], "unprocessable_entity");
+        assert!(body["message"].as_str().unwrap().starts_with(field), "{}", body);
+    }
 }
@@ -0,0 +1,45 @@
+//! One test per unique constraint, each checking the repository error and the API status.
+
+mod common;
+
+use api::domain::{repository::TodoRepository, todo::{ConflictPolicy, CreateTodo, Todo, TodoId}};
+use api::infrastructure::memory_repo::InMemoryTodoRepository;
+use serde_json::json;
+
+use common::{app_for, assert_conflict_response, assert_unique_violation, isolated_db_url, request, spawn_test_repo};
+
+/// A copy of `todo` under a new id, so only the other unique fields collide.
+fn with_new_id(todo: &Todo) -> Todo {
+    Todo { id: TodoId::default(), ..todo.clone() }
+}
+
+#[tokio::test]
+async fn acceptance_code_is_unique() {
+    let repo = spawn_test_repo(&isolated_db_url()).await;
+    let existing = repo.create(CreateTodo { title: "Original".into(), ..Default::default() }).await.unwrap();
+    for policy in [ConflictPolicy::Overwrite, ConflictPolicy::Fail] {
+        assert_unique_violation(repo.import_todos(vec![with_new_id(&existing)], policy).await, "code");
+    }
+    let memory = InMemoryTodoRepository::default();
+    let existing_in_memory = memory.create(CreateTodo { title: "Original".into(), ..Default::default() }).await.unwrap();
+    assert_unique_violation(memory.import_todos(vec![with_new_id(&existing_in_memory)], ConflictPolicy::Overwrite).await, "code");
+
+    let app = app_for(repo);
+    let res = request(&app, "POST", "/todos/import", Some(json!([with_new_id(&existing)]))).await;
+    assert_conflict_response(res, "code").await;
+    // Skipping leaves the existing todo alone instead of failing
+    let res = request(&app, "POST", "/todos/import?on_conflict=skip", Some(json!([with_new_id(&existing)]))).await;
+    assert_eq!(res.status(), 200);
+}
+
+#[tokio::test]
+async fn acceptance_id_is_unique_when_imports_must_not_overwrite() {
+    let repo = spawn_test_repo(&isolated_db_url()).await;
+    let existing = repo.create(CreateTodo { title: "Original".into(), ..Default::default() }).await.unwrap();
+    let same_id = Todo { code: "TODO-ZZZZ".into(), ..existing.clone() };
+    assert_unique_violation(repo.import_todos(vec![same_id.clone()], ConflictPolicy::Fail).await, "id");
+
+    let memory = InMemoryTodoRepository::default();
+    memory.import_todos(vec![existing.clone()], ConflictPolicy::Fail).await.unwrap();
+    assert_unique_violation(memory.import_todos(vec![same_id], ConflictPolicy::Fail).await, "id");
+}
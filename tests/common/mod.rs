@@ -0,0 +1,70 @@
+//! Shared setup for the integration tests.
+#![allow(dead_code)]
+
+use api::{application::todo_service::{TodoServiceConfig, TodoServiceImpl}, domain::repository::TodoRepository, http::routing::{self, health, openapi, schema, todos}, infrastructure::sqlite_repo::SqliteTodoRepository};
+use axum::Router;
+
+/// A named in-memory database unique to the caller. Unlike `sqlite::memory:`, a second
+/// pool can open the same URL (e.g. to backdate rows) while the first one is alive.
+pub fn isolated_db_url() -> String {
+    format!("sqlite:file:test-{}?mode=memory&cache=shared", uuid::Uuid::new_v4())
+}
+
+/// A repository on a fresh isolated database with the schema created.
+pub async fn spawn_test_repo(url: &str) -> SqliteTodoRepository {
+    let repo = SqliteTodoRepository::connect(url).await.unwrap();
+    repo.init().await.unwrap();
+    repo
+}
+
+/// The full API (todos, health, schema and OpenAPI) on a fresh isolated database.
+pub async fn spawn_test_app() -> Router {
+    app_for(spawn_test_repo(&isolated_db_url()).await)
+}
+
+/// Like [`spawn_test_app`], plus a separate pool on the same database for direct SQL.
+pub async fn spawn_test_app_with_pool() -> (Router, sqlx::SqlitePool) {
+    let url = isolated_db_url();
+    let app = app_for(spawn_test_repo(&url).await);
+    (app, sqlx::SqlitePool::connect(&url).await.unwrap())
+}
+
+pub fn app_for(repo: SqliteTodoRepository) -> Router {
+    app_with_config(repo, TodoServiceConfig::default())
+}
+
+pub fn app_with_config(repo: SqliteTodoRepository, config: TodoServiceConfig) -> Router {
+    let health_router = health::router(repo.clone());
+    let service = TodoServiceImpl::with_config(repo, config);
+    routing::app(todos::router(todos::AppState { service }).merge(health_router).merge(schema::router()).merge(openapi::router()))
+}
+
+pub async fn request(app: &Router, method: &str, path: &str, body: Option<serde_json::Value>) -> hyper::Response<axum::body::Body> {
+    use axum::body::Body;
+    use axum::http::{Request, Method};
+    use tower::ServiceExt;
+
+    let req = Request::builder().method(Method::from_bytes(method.as_bytes()).unwrap()).uri(path);
+    let req = match body {
+        Some(json) => req.header("content-type", "application/json").body(Body::from(json.to_string())).unwrap(),
+        None => req.body(Body::empty()).unwrap(),
+    };
+    app.clone().oneshot(req).await.unwrap()
+}
+
+/// Asserts that `result`, from a repository write that reuses a unique value, failed with
+/// [`UniqueViolation`](api::domain::todo::UniqueViolation) on `field` and not some other error.
+pub fn assert_unique_violation<T: std::fmt::Debug>(result: anyhow::Result<T>, field: &str) {
+    let err = result.expect_err("the write should violate a unique constraint");
+    let violation = err.downcast_ref::<api::domain::todo::UniqueViolation>().unwrap_or_else(|| panic!("expected a UniqueViolation on {}, got: {:#}", field, err));
+    assert_eq!(violation.field, field);
+}
+
+/// Asserts that `res`, the API's answer to a write that reuses a unique value, is a 409
+/// `conflict` naming `field` rather than a 500.
+pub async fn assert_conflict_response(res: hyper::Response<axum::body::Body>, field: &str) {
+    assert_eq!(res.status(), 409);
+    let body: serde_json::Value = serde_json::from_slice(&axum::body::to_bytes(res.into_body(), 1024 * 1024).await.unwrap()).unwrap();
+    assert_eq!(body["code"], "conflict");
+    assert!(body["message"].as_str().unwrap().ends_with(field), "{}", body);
+}